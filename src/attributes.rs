@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::ignore::wildmatch;
+use crate::repository::Repository;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttrValue {
+    Set,
+    Unset,
+    Value(String),
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    pattern: String,
+    anchored: bool,
+    attrs: Vec<(String, Option<AttrValue>)>,
+}
+
+impl Line {
+    fn parse(line: &str) -> Option<Line> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.split_whitespace();
+        let pattern = fields.next()?;
+        let attrs = fields
+            .map(|field| {
+                if let Some(name) = field.strip_prefix('-') {
+                    (name.to_string(), Some(AttrValue::Unset))
+                } else if let Some(name) = field.strip_prefix('!') {
+                    // `!name` returns the attribute to unspecified.
+                    (name.to_string(), None)
+                } else if let Some((name, value)) = field.split_once('=') {
+                    (name.to_string(), Some(AttrValue::Value(value.to_string())))
+                } else {
+                    (field.to_string(), Some(AttrValue::Set))
+                }
+            })
+            .collect();
+        Some(Line { pattern: pattern.trim_start_matches('/').to_string(), anchored: pattern.contains('/'), attrs })
+    }
+
+    // `path` is relative to the directory holding the line.
+    fn matches(&self, path: &str) -> bool {
+        if self.anchored {
+            wildmatch(&self.pattern, path, true)
+        } else {
+            wildmatch(&self.pattern, path.rsplit('/').next().unwrap_or(path), true)
+        }
+    }
+}
+
+
+// Gitattributes for a worktree, read lazily per directory like the ignore
+// rules.
+pub struct Attributes<'a> {
+    repo: &'a Repository,
+    global: Vec<Line>,
+    per_dir: HashMap<String, Vec<Line>>,
+}
+
+impl<'a> Attributes<'a> {
+    pub fn new(repo: &'a Repository) -> Attributes<'a> {
+        let global = fs::read_to_string(repo.repo_path("info").join("attributes"))
+            .map(|content| content.lines().filter_map(Line::parse).collect())
+            .unwrap_or_default();
+        Attributes { repo, global, per_dir: HashMap::new() }
+    }
+
+    fn lines_for(&mut self, dir: &str) -> &Vec<Line> {
+        let repo = self.repo;
+        self.per_dir.entry(dir.to_string()).or_insert_with(|| {
+            fs::read_to_string(repo.worktree_file(dir).join(".gitattributes"))
+                .map(|content| content.lines().filter_map(Line::parse).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    // The value of `name` for `path`. info/attributes overrides the
+    // .gitattributes files, deeper files override shallower ones and later
+    // lines override earlier ones.
+    pub fn get(&mut self, path: &str, name: &str) -> Option<AttrValue> {
+        let find = |lines: &[Line], relative: &str| {
+            lines.iter().rev().filter(|line| line.matches(relative)).find_map(|line| line.attrs.iter().rev().find(|(attr, _)| attr == name)).map(|(_, value)| value.clone())
+        };
+        if let Some(value) = find(&self.global, path) {
+            return value;
+        }
+
+        let mut dirs = vec![String::new()];
+        let parts: Vec<&str> = path.split('/').collect();
+        for i in 1..parts.len() {
+            dirs.push(parts[..i].join("/"));
+        }
+        for dir in dirs.iter().rev() {
+            let relative = if dir.is_empty() { path } else { &path[dir.len() + 1..] };
+            if let Some(value) = find(self.lines_for(dir), relative) {
+                return value;
+            }
+        }
+        None
+    }
+
+    // The attribute's value when it was given one with `name=value`.
+    pub fn value(&mut self, path: &str, name: &str) -> Option<String> {
+        match self.get(path, name) {
+            Some(AttrValue::Value(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::env;
+
+use anyhow::{bail, Result};
+use git_rs::repository::Repository;
+
+mod add;
+mod apply;
+mod blame;
+mod cat_file;
+mod checkout;
+mod cherry_pick;
+mod commit;
+mod commit_tree;
+mod diff;
+mod for_each_ref;
+mod gc;
+mod hash_object;
+mod log;
+mod ls_files;
+mod ls_tree;
+mod merge;
+mod merge_file;
+mod mktree;
+mod prune;
+mod read_tree;
+mod reflog;
+mod restore;
+mod rev_list;
+mod rev_parse;
+mod show_ref;
+mod stash;
+mod status;
+mod symbolic_ref;
+mod tag;
+mod update_index;
+mod verify_commit;
+mod verify_tag;
+mod write_tree;
+
+pub use add::add;
+pub use apply::apply;
+pub use blame::blame;
+pub use cat_file::cat_file;
+pub use checkout::checkout;
+pub use cherry_pick::cherry_pick;
+pub use commit::commit;
+pub use commit_tree::commit_tree;
+pub use diff::diff;
+pub use for_each_ref::for_each_ref;
+pub use gc::gc;
+pub use hash_object::hash_object;
+pub use log::log;
+pub use ls_files::ls_files;
+pub use ls_tree::ls_tree;
+pub use merge::merge;
+pub use merge_file::merge_file;
+pub use mktree::mktree;
+pub use prune::prune;
+pub use read_tree::read_tree;
+pub use reflog::reflog;
+pub use restore::restore;
+pub use rev_list::rev_list;
+pub use rev_parse::rev_parse;
+pub use show_ref::show_ref;
+pub use stash::stash;
+pub use status::status;
+pub use symbolic_ref::symbolic_ref;
+pub use tag::tag;
+pub use update_index::update_index;
+pub use verify_commit::verify_commit;
+pub use verify_tag::verify_tag;
+pub use write_tree::write_tree;
+
+
+pub fn open_repo() -> Result<Repository> {
+    Ok(Repository::find(env::current_dir()?)?)
+}
+
+// Handles -M[<n>], --find-renames[=<n>] and --no-renames, returning whether
+// `arg` was one of them.
+pub fn parse_rename_option(arg: &str, threshold: &mut Option<u32>) -> Result<bool> {
+    let value = if let Some(value) = arg.strip_prefix("--find-renames") {
+        value.strip_prefix('=').unwrap_or(value)
+    } else if let Some(value) = arg.strip_prefix("-M") {
+        value
+    } else if arg == "--no-renames" {
+        *threshold = None;
+        return Ok(true);
+    } else {
+        return Ok(false);
+    };
+
+    *threshold = Some(match value.trim_end_matches('%') {
+        "" => 50,
+        n => match n.parse::<u32>() {
+            Ok(n) if n <= 100 => n,
+            _ => bail!("invalid rename threshold '{}'", value),
+        },
+    });
+    Ok(true)
+}
+
+// Parses --depth=<n>, returning None when `arg` is something else.
+pub fn parse_depth_option(arg: &str) -> Result<Option<usize>> {
+    let Some(value) = arg.strip_prefix("--depth=") else {
+        return Ok(None);
+    };
+    match value.parse::<usize>() {
+        Ok(depth) if depth > 0 => Ok(Some(depth)),
+        _ => bail!("invalid depth '{}'", value),
+    }
+}
+
+// Turns an expiry like "2.weeks.ago", "now" or "never" into a cutoff time;
+// anything older than the cutoff has expired. "never" gives no cutoff.
+pub fn parse_expiry(expiry: &str, now: i64) -> Result<Option<i64>> {
+    match expiry {
+        "now" | "all" => return Ok(Some(now)),
+        "never" => return Ok(None),
+        _ => {}
+    }
+    if let Ok(timestamp) = expiry.parse::<i64>() {
+        return Ok(Some(timestamp));
+    }
+
+    let parts: Vec<&str> = expiry.split(['.', ' ']).collect();
+    let [count, unit, "ago"] = parts[..] else {
+        bail!("malformed expiration date '{}'", expiry);
+    };
+    let Ok(count) = count.parse::<i64>() else {
+        bail!("malformed expiration date '{}'", expiry);
+    };
+    let seconds = match unit.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        _ => bail!("malformed expiration date '{}'", expiry),
+    };
+    Ok(Some(now - count * seconds))
+}
@@ -0,0 +1,48 @@
+use std::fs;
+
+use anyhow::{bail, Result};
+use git_rs::index::GitIndex;
+use git_rs::worktree::{check_case_collisions, list_worktree_files, stage_file};
+
+use super::open_repo;
+
+
+pub fn add(args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        bail!("Nothing specified, nothing added.");
+    }
+
+    let repo = open_repo()?;
+    let mut index = GitIndex::read(&repo)?;
+
+    for arg in args {
+        let path = repo.worktree_path(arg)?;
+        let full = repo.worktree_file(&path);
+        let files = match fs::symlink_metadata(&full) {
+            Ok(metadata) if metadata.is_dir() => list_worktree_files(&repo, &path)?,
+            Ok(_) => vec![path.clone()],
+            Err(_) => {
+                // A pathspec naming deleted files stages their removal.
+                let prefix = format!("{}/", path);
+                let before = index.entries.len();
+                index.entries.retain(|entry| entry.name != path && !entry.name.starts_with(&prefix));
+                if index.entries.len() == before {
+                    bail!("pathspec '{}' did not match any files", arg);
+                }
+                continue;
+            }
+        };
+
+        for file in files {
+            if let Some(entry) = index.get(&file) {
+                if entry.stat_matches(&fs::symlink_metadata(repo.worktree_file(&file))?) {
+                    continue;
+                }
+            }
+            index.add(stage_file(&repo, &file)?);
+        }
+    }
+
+    check_case_collisions(&repo, index.entries.iter().map(|entry| entry.name.as_str()))?;
+    index.write(&repo)
+}
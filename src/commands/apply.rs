@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::object::{read_blob, FileMode, GitBlob, GitObject};
+use git_rs::patch::{apply_hunks, parse_patch, FilePatch};
+use git_rs::repository::Repository;
+use git_rs::utils::path_from_os;
+use git_rs::worktree::{file_mode, remove_worktree_file, write_worktree_data};
+
+use super::open_repo;
+
+
+// A file's content and mode before the patch, from the index with --cached
+// and from the worktree otherwise. None if the file doesn't exist there.
+fn preimage(repo: &Repository, index: &GitIndex, path: &str, cached: bool) -> Result<Option<(Vec<u8>, FileMode)>> {
+    if cached {
+        return match index.get(path) {
+            Some(entry) => Ok(Some((read_blob(repo, &entry.sha)?.raw_data, entry.mode))),
+            None => Ok(None),
+        };
+    }
+    let full = repo.worktree_file(path);
+    let Ok(metadata) = fs::symlink_metadata(&full) else {
+        return Ok(None);
+    };
+    let data = if metadata.file_type().is_symlink() {
+        path_from_os(fs::read_link(&full)?.as_os_str()).into_bytes()
+    } else {
+        fs::read(&full)?
+    };
+    Ok(Some((data, file_mode(&metadata))))
+}
+
+// Content and mode of each path touched by earlier patches, None once
+// deleted. Later patches for the same path apply on top of these.
+type Pending = BTreeMap<String, Option<(Vec<u8>, FileMode)>>;
+
+fn current(repo: &Repository, index: &GitIndex, pending: &Pending, path: &str, cached: bool) -> Result<Option<(Vec<u8>, FileMode)>> {
+    match pending.get(path) {
+        Some(state) => Ok(state.clone()),
+        None => preimage(repo, index, path, cached),
+    }
+}
+
+// Works out what one file patch turns its file into, returning the new
+// content and mode, or None if the file is deleted. Errors describe why the
+// patch doesn't apply.
+fn patch_file(repo: &Repository, index: &GitIndex, patch: &FilePatch, cached: bool, pending: &Pending) -> Result<Option<(Vec<u8>, FileMode)>> {
+    let place = if cached { "index" } else { "working directory" };
+    if patch.binary {
+        bail!("cannot apply binary patch to '{}' without full index line", patch.path());
+    }
+    let old = match &patch.old_path {
+        Some(path) => match current(repo, index, pending, path, cached)? {
+            Some(old) => Some(old),
+            None => bail!("{}: does not exist in {}", path, place),
+        },
+        None => None,
+    };
+    if let Some(path) = &patch.new_path {
+        let renamed = patch.old_path.as_ref().is_some_and(|old| old != path);
+        if (patch.old_path.is_none() || renamed) && current(repo, index, pending, path, cached)?.is_some() {
+            bail!("{}: already exists in {}", path, place);
+        }
+    }
+    if let (Some((_, mode)), Some(expected)) = (&old, patch.old_mode) {
+        if *mode != expected {
+            eprintln!("warning: {} has type {:o}, expected {:o}", patch.path(), mode.bits(), expected.bits());
+        }
+    }
+
+    let (old_data, old_mode) = old.unwrap_or((Vec::new(), FileMode::Regular));
+    let new_data = match apply_hunks(&old_data, &patch.hunks) {
+        Ok(data) => data,
+        Err(failed) => {
+            for n in failed {
+                eprintln!("error: patch failed: {}:{}", patch.old_path.as_deref().unwrap_or(patch.path()), patch.hunks[n].old_start);
+            }
+            bail!("{}: patch does not apply", patch.path());
+        }
+    };
+    if patch.new_path.is_none() {
+        if !new_data.is_empty() {
+            bail!("removal patch leaves file contents");
+        }
+        return Ok(None);
+    }
+    Ok(Some((new_data, patch.new_mode.unwrap_or(old_mode))))
+}
+
+pub fn apply(args: &[String]) -> Result<()> {
+    let mut check = false;
+    let mut cached = false;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--cached" => cached = true,
+            "-" => files.push(arg),
+            flag if flag.starts_with('-') => bail!("unknown option '{}'\nusage: git_rs apply [--check] [--cached] [<patch>...]", flag),
+            _ => files.push(arg),
+        }
+    }
+
+    let mut data = Vec::new();
+    if files.is_empty() || files.iter().any(|file| *file == "-") {
+        std::io::stdin().read_to_end(&mut data)?;
+    }
+    let mut patches = parse_patch(&data)?;
+    for file in files.iter().filter(|file| **file != "-") {
+        patches.extend(parse_patch(&fs::read(file).with_context(|| format!("can't open patch '{}'", file))?)?);
+    }
+    if patches.is_empty() {
+        bail!("No valid patches in input");
+    }
+
+    // Every patch is checked before anything is written, so a patch that
+    // doesn't apply leaves all files untouched.
+    let repo = open_repo()?;
+    let mut index = GitIndex::read(&repo)?;
+    let mut pending = Pending::new();
+    for patch in &patches {
+        let result = patch_file(&repo, &index, patch, cached, &pending)?;
+        // Deleted and renamed files go away at their old path.
+        if let Some(old) = &patch.old_path {
+            pending.insert(old.clone(), None);
+        }
+        if let Some(path) = &patch.new_path {
+            pending.insert(path.clone(), result);
+        }
+    }
+    if check {
+        return Ok(());
+    }
+
+    // Removals go first so a file can replace a directory that went away.
+    for path in pending.iter().filter(|(_, state)| state.is_none()).map(|(path, _)| path) {
+        if cached {
+            index.remove(path);
+        } else {
+            remove_worktree_file(&repo, path)?;
+        }
+    }
+    for (path, (data, mode)) in pending.into_iter().filter_map(|(path, state)| state.map(|state| (path, state))) {
+        if cached {
+            let sha = GitBlob::new(data).write(&repo)?;
+            index.add(GitIndexEntry::new(path, mode, sha, None));
+        } else {
+            write_worktree_data(&repo, &path, mode, &data)?;
+        }
+    }
+    if cached {
+        index.write(&repo)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::diff::{diff, split_lines, Edit};
+use git_rs::object::{read_blob, read_commit, resolve_path_in_tree, GitCommit, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::repository::Repository;
+use git_rs::walker::CommitWalker;
+
+use super::open_repo;
+
+
+// Lines still waiting to be attributed: (line number in the final file,
+// index of that line in this commit's version of the file).
+struct Pending {
+    blob: String,
+    lines: Vec<(usize, usize)>,
+}
+
+fn blob_at_path(repo: &Repository, commit: &GitCommit, path: &str) -> Result<Option<String>> {
+    Ok(resolve_path_in_tree(repo, commit.tree()?, path)?.filter(|leaf| !leaf.is_tree()).map(|leaf| leaf.sha))
+}
+
+// Parses an `-L` range into zero-based line indices, given the number of
+// lines in the file. Forms are `<start>,<end>`, `<start>,+<count>`,
+// `<start>,-<count>`, `<start>` (to the end) and `,<end>` (from the top); an
+// end past the last line stops there.
+fn parse_range(range: &str, path: &str, total: usize) -> Result<std::ops::Range<usize>> {
+    let invalid = || anyhow!("invalid -L range '{}'", range);
+    let number = |s: &str| s.parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(invalid);
+    let (start, end) = match range.split_once(',') {
+        None => (number(range)?, total),
+        Some(("", end)) => (1, number(end)?),
+        Some((start, end)) => {
+            let start = number(start)?;
+            if let Some(count) = end.strip_prefix('+') {
+                (start, start + number(count)? - 1)
+            } else if let Some(count) = end.strip_prefix('-') {
+                (start.saturating_sub(number(count)? - 1).max(1), start)
+            } else {
+                let end = number(end)?;
+                (start.min(end), start.max(end))
+            }
+        }
+    };
+    if start > total {
+        bail!("file {} has only {} line{}", path, total, if total == 1 { "" } else { "s" });
+    }
+    Ok(start - 1..end.min(total))
+}
+
+// Attributes every line of `path` at `rev` to the commit that introduced it,
+// or only the lines in the `-L` ranges given. Only the
+// first-parent-or-treesame history of the exact path is followed, so renames
+// are not detected and a renamed file is blamed on the rename commit.
+pub fn blame(args: &[String]) -> Result<()> {
+    let usage = "usage: git_rs blame [-L <range>]... [<rev>] [--] <path>";
+    let mut ranges = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-L" => ranges.push(args.next().ok_or_else(|| anyhow!("option -L requires a value"))?.clone()),
+            _ => match arg.strip_prefix("-L") {
+                Some(range) => ranges.push(range.to_string()),
+                None if arg.starts_with('-') && arg != "--" => bail!("unknown option '{}'\n{}", arg, usage),
+                None => positional.push(arg.as_str()),
+            },
+        }
+    }
+    let (rev, path) = match positional[..] {
+        [path] | ["--", path] => ("HEAD", path),
+        [rev, path] => (rev, path),
+        [rev, "--", path] => (rev, path),
+        _ => bail!(usage),
+    };
+
+    let repo = open_repo()?;
+    let path = repo.worktree_path(path)?;
+    let start = find_object(&repo, rev, Some(ObjectType::Commit))?;
+    let Some(start_blob) = blob_at_path(&repo, &read_commit(&repo, &start)?, &path)? else {
+        bail!("no such path '{}' in {}", path, rev);
+    };
+
+    let content = read_blob(&repo, &start_blob)?.raw_data;
+    let final_lines = split_lines(&content);
+    let mut wanted = vec![ranges.is_empty(); final_lines.len()];
+    for range in &ranges {
+        for i in parse_range(range, &path, final_lines.len())? {
+            wanted[i] = true;
+        }
+    }
+
+    let mut owners: Vec<Option<String>> = vec![None; final_lines.len()];
+    let mut pending: HashMap<String, Pending> = HashMap::new();
+    let lines = (0..final_lines.len()).filter(|&i| wanted[i]).map(|i| (i, i)).collect();
+    pending.insert(start.clone(), Pending { blob: start_blob, lines });
+
+    let mut commits: HashMap<String, GitCommit> = HashMap::new();
+    for entry in CommitWalker::new(&repo, &[start])? {
+        if pending.is_empty() {
+            break;
+        }
+        let (sha, commit) = entry?;
+        let Some(Pending { blob, mut lines }) = pending.remove(&sha) else {
+            continue;
+        };
+
+        let mut parent_blobs = Vec::new();
+        for parent in commit.parents() {
+            if let Some(parent_blob) = blob_at_path(&repo, &read_commit(&repo, parent)?, &path)? {
+                parent_blobs.push((parent.to_string(), parent_blob));
+            }
+        }
+
+        // An unchanged parent takes every line as-is.
+        if let Some((parent, parent_blob)) = parent_blobs.iter().find(|(_, parent_blob)| *parent_blob == blob) {
+            pending.entry(parent.clone()).or_insert_with(|| Pending { blob: parent_blob.clone(), lines: Vec::new() }).lines.append(&mut lines);
+        }
+
+        if !lines.is_empty() {
+            let current = read_blob(&repo, &blob)?.raw_data;
+            let current_lines = split_lines(&current);
+            for (parent, parent_blob) in &parent_blobs {
+                let old = read_blob(&repo, parent_blob)?.raw_data;
+                let mapping: HashMap<usize, usize> = diff(&split_lines(&old), &current_lines)
+                    .into_iter()
+                    .filter_map(|edit| match edit {
+                        Edit::Equal { old, new } => Some((new, old)),
+                        _ => None,
+                    })
+                    .collect();
+
+                let (passed, kept): (Vec<_>, Vec<_>) = lines.into_iter().partition(|(_, idx)| mapping.contains_key(idx));
+                lines = kept;
+                if !passed.is_empty() {
+                    let target = pending.entry(parent.clone()).or_insert_with(|| Pending { blob: parent_blob.clone(), lines: Vec::new() });
+                    target.lines.extend(passed.into_iter().map(|(line, idx)| (line, mapping[&idx])));
+                }
+            }
+        }
+
+        for (line, _) in lines {
+            owners[line] = Some(sha.clone());
+        }
+        commits.insert(sha, commit);
+    }
+
+    // Anything left over belongs to the commit it was last handed to.
+    for (sha, Pending { lines, .. }) in pending {
+        for (line, _) in lines {
+            owners[line] = Some(sha.clone());
+        }
+        if !commits.contains_key(&sha) {
+            commits.insert(sha.clone(), read_commit(&repo, &sha)?);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (i, owner) in owners.iter().enumerate() {
+        let Some(owner) = owner else {
+            continue;
+        };
+        let commit = &commits[owner];
+        let author = commit.author()?;
+        let id = if commit.parents().is_empty() { format!("^{}", &owner[..7]) } else { owner[..8].to_string() };
+        rows.push((i, id, author.name.clone(), author.format_date("%Y-%m-%d %H:%M:%S %z")));
+    }
+
+    let name_width = rows.iter().map(|(_, _, name, _)| name.chars().count()).max().unwrap_or(0);
+    let number_width = rows.last().map_or(0, |(i, ..)| (i + 1).to_string().len());
+    for (i, id, name, date) in rows {
+        let line = String::from_utf8_lossy(final_lines[i]);
+        println!("{} ({:<name_width$} {} {:>number_width$}) {}", id, name, date, i + 1, line.trim_end_matches('\n'));
+    }
+    Ok(())
+}
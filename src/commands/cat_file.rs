@@ -0,0 +1,124 @@
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use git_rs::convert::{textconv, to_worktree};
+use git_rs::error::GitError;
+use git_rs::object::{all_objects, parse_from_bytes, read_blob, read_loose, read_loose_header, read_object_header, read_raw, ObjectType};
+use git_rs::refs::find_object;
+
+use super::open_repo;
+
+
+// Prints `<sha> <type> <size>` for every object, or for each name read from
+// stdin, reporting names that don't resolve as missing.
+fn batch_check(all_objects_mode: bool) -> Result<()> {
+    let repo = open_repo()?;
+    let mut out = std::io::stdout().lock();
+    if all_objects_mode {
+        // One bad object shouldn't hide the rest: an unknown type is listed
+        // as written, and an unreadable object is reported and skipped.
+        for sha in all_objects(&repo)? {
+            match read_object_header(&repo, &sha) {
+                Ok((type_, size)) => writeln!(out, "{} {} {}", sha, type_, size)?,
+                Err(e) => match read_loose_header(&repo, &sha) {
+                    Ok((type_, size)) => writeln!(out, "{} {} {}", sha, type_, size)?,
+                    Err(_) => eprintln!("error: {}", e),
+                },
+            }
+        }
+        return Ok(());
+    }
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let name = line.trim();
+        match find_object(&repo, name, None) {
+            Ok(sha) => {
+                let (type_, size) = read_object_header(&repo, &sha)?;
+                writeln!(out, "{} {} {}", sha, type_, size)?;
+            }
+            Err(_) => writeln!(out, "{} missing", name)?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+pub fn cat_file(args: &[String]) -> Result<()> {
+    let all_objects_mode = args.iter().any(|arg| arg == "--batch-all-objects");
+    if args.iter().any(|arg| arg == "--batch-check") {
+        if args.iter().any(|arg| arg != "--batch-check" && arg != "--batch-all-objects") {
+            bail!("usage: git_rs cat-file --batch-check [--batch-all-objects]");
+        }
+        return batch_check(all_objects_mode);
+    }
+    if all_objects_mode {
+        bail!("option '--batch-all-objects' requires --batch-check");
+    }
+    let allow_unknown = args.iter().any(|arg| arg == "--allow-unknown-type");
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).filter(|arg| *arg != "--allow-unknown-type").collect();
+    let (mode, name) = match args[..] {
+        [mode, name] => (mode, name),
+        _ => bail!("usage: git_rs cat-file (-t | -s | -e | -p | --raw | --textconv | --filters | <type>) [--allow-unknown-type] <object>\n   or: git_rs cat-file --batch-check [--batch-all-objects]"),
+    };
+    if allow_unknown && mode != "-t" && mode != "-s" {
+        bail!("--allow-unknown-type can only be used with -t or -s");
+    }
+
+    let repo = open_repo()?;
+    let expected = match mode {
+        "-t" | "-s" | "-e" | "-p" | "--raw" => None,
+        "--textconv" | "--filters" => Some(ObjectType::Blob),
+        type_ => Some(ObjectType::from_str(type_)?),
+    };
+    // Both conversions are chosen by the attributes of the blob's path.
+    let path = match name.split_once(':') {
+        Some((_, path)) => path,
+        None if mode == "--textconv" || mode == "--filters" => bail!("<object>:<path> required, only <object> '{}' given", name),
+        None => "",
+    };
+    let sha = find_object(&repo, name, expected)?;
+
+    if mode == "--textconv" || mode == "--filters" {
+        let data = read_blob(&repo, &sha)?.raw_data;
+        let data = if mode == "--textconv" { textconv(&repo, path, data)? } else { to_worktree(&repo, path, data)? };
+        std::io::stdout().write_all(&data)?;
+        return Ok(());
+    }
+
+    // These only need the header, so the payload is never inflated and any
+    // type name can be reported.
+    if mode == "-t" || mode == "-s" {
+        let (type_, size) = if allow_unknown {
+            read_loose_header(&repo, &sha)?
+        } else {
+            let (type_, size) = read_object_header(&repo, &sha)?;
+            (type_.to_string(), size)
+        };
+        match mode {
+            "-t" => println!("{}", type_),
+            _ => println!("{}", size),
+        }
+        return Ok(());
+    }
+    if mode == "--raw" {
+        // Packs don't store the header, so a packed object gets it back.
+        let raw = match read_loose(&repo, &sha) {
+            Err(GitError::ObjectNotFound(_)) => {
+                let (type_, data) = read_raw(&repo, &sha)?;
+                [format!("{} {}\0", type_, data.len()).into_bytes(), data].concat()
+            }
+            result => result?,
+        };
+        std::io::stdout().write_all(&raw)?;
+        return Ok(());
+    }
+
+    let (type_, data) = read_raw(&repo, &sha)?;
+    match mode {
+        "-e" => {}
+        "-p" => std::io::stdout().write_all(&parse_from_bytes(type_, data)?.pretty()?)?,
+        _ => std::io::stdout().write_all(&data)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::{bail, Result};
+use git_rs::object::{read_commit, ObjectType};
+use git_rs::progress::is_quiet;
+use git_rs::refs::{find_object, is_valid_ref_name, log_ref_update, resolve_ref, update_ref, write_symbolic_ref};
+use git_rs::repository::{HeadState, Repository};
+use git_rs::worktree::checkout_tree;
+
+use super::{open_repo, restore};
+
+
+// Notices on stderr that -q silences.
+macro_rules! feedback {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+
+// How the reflog names where HEAD was: the branch, or the commit if detached.
+fn head_description(head: &HeadState) -> String {
+    match head {
+        HeadState::Detached(sha) => sha.clone(),
+        HeadState::Unborn { name } | HeadState::Branch { name, .. } => name.strip_prefix("refs/heads/").unwrap_or(name).to_string(),
+    }
+}
+
+// Points HEAD at a branch and logs the move in HEAD's reflog.
+fn switch_to_branch(repo: &Repository, previous: &HeadState, branch: &str, sha: &str) -> Result<()> {
+    write_symbolic_ref(repo, "HEAD", branch)?;
+    let message = format!("checkout: moving from {} to {}", head_description(previous), branch.strip_prefix("refs/heads/").unwrap_or(branch));
+    log_ref_update(repo, "HEAD", previous.sha(), sha, &message)
+}
+
+fn create_branch(repo: &Repository, name: &str, start: Option<&String>) -> Result<()> {
+    let branch = format!("refs/heads/{}", name);
+    if !is_valid_ref_name(name) {
+        bail!("'{}' is not a valid branch name", name);
+    }
+    if resolve_ref(repo, &branch)?.is_some() {
+        bail!("a branch named '{}' already exists", name);
+    }
+
+    // On an unborn HEAD there is nothing to check out, only HEAD to repoint.
+    if start.is_none() && repo.head_commit()?.is_none() {
+        write_symbolic_ref(repo, "HEAD", &branch)?;
+        feedback!("Switched to a new branch '{}'", name);
+        return Ok(());
+    }
+
+    let start = start.map(|s| s.as_str()).unwrap_or("HEAD");
+    let sha = find_object(repo, start, Some(ObjectType::Commit))?;
+    let previous = repo.head()?;
+    checkout_tree(repo, read_commit(repo, &sha)?.tree()?)?;
+    update_ref(repo, &branch, &sha, &format!("branch: Created from {}", start))?;
+    switch_to_branch(repo, &previous, &branch, &sha)?;
+    feedback!("Switched to a new branch '{}'", name);
+    Ok(())
+}
+
+pub fn checkout(args: &[String]) -> Result<()> {
+    // `checkout [<tree-ish>] -- <path>...` only rewrites the named paths:
+    // from the index, or from the tree-ish into both the index and worktree.
+    // Files the tree-ish doesn't have are left alone.
+    if let Some(split) = args.iter().position(|arg| arg == "--") {
+        let mut restore_args = match &args[..split] {
+            [] => Vec::new(),
+            [rev] => vec![format!("--source={}", rev), "--staged".to_string(), "--worktree".to_string(), "--overlay".to_string()],
+            _ => bail!("usage: git_rs checkout [<tree-ish>] -- <pathspec>..."),
+        };
+        restore_args.push("--".to_string());
+        restore_args.extend(args[split + 1..].iter().cloned());
+        return restore(&restore_args);
+    }
+
+    let repo = open_repo()?;
+    let rev = match args {
+        [flag, name] if flag == "-b" => return create_branch(&repo, name, None),
+        [flag, name, start] if flag == "-b" => return create_branch(&repo, name, Some(start)),
+        [rev] => rev,
+        _ => bail!("usage: git_rs checkout [-b <new-branch>] <branch | commit>\n   or: git_rs checkout [<tree-ish>] -- <pathspec>..."),
+    };
+
+    let sha = find_object(&repo, rev, Some(ObjectType::Commit))?;
+    let commit = read_commit(&repo, &sha)?;
+    let previous = repo.head()?;
+    checkout_tree(&repo, commit.tree()?)?;
+
+    let branch = format!("refs/heads/{}", rev);
+    if resolve_ref(&repo, &branch)?.is_some() {
+        switch_to_branch(&repo, &previous, &branch, &sha)?;
+        feedback!("Switched to branch '{}'", rev);
+    } else {
+        update_ref(&repo, "HEAD", &sha, &format!("checkout: moving from {} to {}", head_description(&previous), rev))?;
+        feedback!("HEAD is now at {} {}", &sha[..7], commit.kvlm.message.lines().next().unwrap_or(""));
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::fs;
+
+use anyhow::{bail, Result};
+use git_rs::diff::{compare_entries, index_entries, tree_entries};
+use git_rs::index::GitIndex;
+use git_rs::merge::merge_trees;
+use git_rs::object::{read_commit, ObjectType};
+use git_rs::refs::{find_object, update_head};
+
+use super::commit::{print_summary, write_commit};
+use super::merge::apply_merge;
+use super::open_repo;
+
+
+// Applies the change a commit made relative to its parent on top of HEAD,
+// as a three-way merge with the parent as the base. The new commit keeps
+// the original message and author. On conflicts the state is left for
+// `commit` to conclude, like a conflicted merge.
+pub fn cherry_pick(args: &[String]) -> Result<()> {
+    let [name] = args else {
+        bail!("usage: git_rs cherry-pick <commit>");
+    };
+
+    let repo = open_repo()?;
+    if repo.repo_path("CHERRY_PICK_HEAD").is_file() {
+        bail!("cherry-pick is already in progress\nPlease, commit your changes before you cherry-pick again.");
+    }
+    if repo.repo_path("MERGE_HEAD").is_file() {
+        bail!("You have not concluded your merge (MERGE_HEAD exists).\nPlease, commit your changes before you cherry-pick.");
+    }
+    let picked = find_object(&repo, name, Some(ObjectType::Commit))?;
+    let commit = read_commit(&repo, &picked)?;
+    let base_tree = match commit.parents()[..] {
+        [] => None,
+        [parent] => Some(read_commit(&repo, parent)?.tree()?.to_string()),
+        _ => bail!("commit {} is a merge but no -m option was given.", picked),
+    };
+    let Some(head) = repo.head_commit()? else {
+        bail!("You do not have the initial commit yet");
+    };
+
+    let head_tree = read_commit(&repo, &head)?.tree()?.to_string();
+    if !compare_entries(&tree_entries(&repo, &head_tree)?, &index_entries(&GitIndex::read(&repo)?)).is_empty() {
+        bail!("Your local changes would be overwritten by cherry-pick.\nPlease commit your changes or stash them before you cherry-pick.");
+    }
+
+    let subject = commit.kvlm.message.lines().next().unwrap_or("");
+    let label = format!("{} ({})", &picked[..7], subject);
+    let result = merge_trees(&repo, base_tree.as_deref(), &head_tree, commit.tree()?, "HEAD", &label)?;
+    apply_merge(&repo, &result)?;
+    for line in &result.messages {
+        println!("{}", line);
+    }
+
+    if !result.conflicts.is_empty() {
+        let mut message = commit.kvlm.message.clone();
+        message.push_str("\n# Conflicts:\n");
+        for path in &result.conflicts {
+            message.push_str(&format!("#\t{}\n", path));
+        }
+        fs::write(repo.repo_path("CHERRY_PICK_HEAD"), format!("{}\n", picked))?;
+        fs::write(repo.repo_path("MERGE_MSG"), message)?;
+        eprintln!("error: could not apply {}... {}", &picked[..7], subject);
+        eprintln!("hint: After resolving the conflicts, mark the corrected paths");
+        eprintln!("hint: with 'git_rs add <paths>' and run 'git_rs commit'.");
+        std::process::exit(1);
+    }
+
+    let tree = result.index.write_tree(&repo)?;
+    if tree == head_tree {
+        bail!("The cherry-pick of {} is empty; nothing was committed.", &picked[..7]);
+    }
+    let sha = write_commit(&repo, &tree, std::slice::from_ref(&head), Some(&commit.author()?), &commit.kvlm.message)?;
+    update_head(&repo, &sha, &format!("cherry-pick: {}", subject))?;
+    print_summary(&repo, &sha, subject, false)
+}
@@ -0,0 +1,134 @@
+use std::fs;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::gpg::sign_payload;
+use git_rs::index::GitIndex;
+use git_rs::object::{read_commit, GitCommit, GitObject, Signature};
+use git_rs::refs::update_head;
+use git_rs::repository::{HeadState, Repository};
+
+use super::open_repo;
+
+
+// Files describing a merge or cherry-pick that is waiting to be committed.
+pub const MERGE_STATE: [&str; 4] = ["MERGE_HEAD", "MERGE_MSG", "MERGE_MODE", "CHERRY_PICK_HEAD"];
+
+// Drops comment lines and surrounding blank lines from a message, like
+// git's default cleanup mode.
+fn cleanup_message(message: &str) -> String {
+    let lines: Vec<&str> = message.lines().filter(|line| !line.starts_with('#')).map(|line| line.trim_end()).collect();
+    let text = lines.join("\n");
+    let text = text.trim_matches('\n');
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+// Builds a commit with the current committer identity, and the current
+// author identity unless `author` is given.
+fn build_commit(repo: &Repository, tree: &str, parents: &[String], author: Option<&Signature>, message: &str) -> Result<GitCommit> {
+    let author = match author {
+        Some(author) => author.clone(),
+        None => Signature::identity(repo, "AUTHOR")?,
+    };
+    let committer = Signature::identity(repo, "COMMITTER")?;
+    Ok(GitCommit::build(tree, parents, &author, &committer, message))
+}
+
+pub fn write_commit(repo: &Repository, tree: &str, parents: &[String], author: Option<&Signature>, message: &str) -> Result<String> {
+    Ok(build_commit(repo, tree, parents, author, message)?.write(repo)?)
+}
+
+// Prints the `[branch sha] subject` line git shows after committing.
+pub fn print_summary(repo: &Repository, sha: &str, subject: &str, root: bool) -> Result<()> {
+    let location = match repo.head()? {
+        HeadState::Detached(_) => "detached HEAD".to_string(),
+        HeadState::Unborn { name } | HeadState::Branch { name, .. } => name.strip_prefix("refs/heads/").unwrap_or(&name).to_string(),
+    };
+    let root = if root { " (root-commit)" } else { "" };
+    println!("[{}{} {}] {}", location, root, &sha[..7], subject);
+    Ok(())
+}
+
+pub fn commit(args: &[String]) -> Result<()> {
+    let mut messages: Vec<String> = Vec::new();
+    let mut sign = None;
+    let mut key = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-m" => messages.push(args.next().ok_or_else(|| anyhow!("option -m requires a value"))?.clone()),
+            "-S" | "--gpg-sign" => sign = Some(true),
+            "--no-gpg-sign" => sign = Some(false),
+            _ => match arg.strip_prefix("--gpg-sign=").or_else(|| arg.strip_prefix("-S")) {
+                Some(value) => {
+                    sign = Some(true);
+                    key = Some(value.to_string());
+                }
+                None => bail!("usage: git_rs commit [-S[<keyid>] | --no-gpg-sign] [(-m <message>)...]"),
+            },
+        }
+    }
+
+    let repo = open_repo()?;
+    let sign = sign.unwrap_or_else(|| repo.config_get("commit", "gpgsign").is_some_and(|v| v.eq_ignore_ascii_case("true")));
+    let index = GitIndex::read(&repo)?;
+    if index.entries.iter().any(|entry| entry.stage != 0) {
+        bail!("Committing is not possible because you have unmerged files.");
+    }
+    let tree = index.write_tree(&repo)?;
+
+    let mut parents: Vec<String> = repo.head_commit()?.into_iter().collect();
+    let merge_head = fs::read_to_string(repo.repo_path("MERGE_HEAD")).ok();
+    parents.extend(merge_head.iter().flat_map(|content| content.lines().map(String::from)));
+
+    if merge_head.is_none() {
+        if let Some(parent) = parents.first() {
+            if read_commit(&repo, parent)?.tree()? == tree {
+                bail!("nothing to commit");
+            }
+        }
+    }
+
+    let message = if messages.is_empty() {
+        cleanup_message(&fs::read_to_string(repo.repo_path("MERGE_MSG")).unwrap_or_default())
+    } else {
+        messages.iter().map(|m| format!("{}\n", m.trim_end_matches('\n'))).collect::<Vec<_>>().join("\n")
+    };
+    if message.is_empty() {
+        bail!("Aborting commit due to empty commit message.");
+    }
+
+    // A commit concluding a cherry-pick keeps the picked commit's author.
+    let author = match fs::read_to_string(repo.repo_path("CHERRY_PICK_HEAD")) {
+        Ok(picked) => Some(read_commit(&repo, picked.trim())?.author()?),
+        Err(_) => None,
+    };
+    let mut new_commit = build_commit(&repo, &tree, &parents, author.as_ref(), &message)?;
+    if sign {
+        // Like git, sign as user.signingkey or else the committer identity.
+        let key = match key.or_else(|| repo.config_get("user", "signingkey")) {
+            Some(key) => key,
+            None => {
+                let committer = new_commit.committer()?;
+                format!("{} <{}>", committer.name, committer.email)
+            }
+        };
+        let signature = sign_payload(&repo, &new_commit.kvlm.serialize(), &key)?;
+        new_commit.add_signature(&signature);
+    }
+    let sha = new_commit.write(&repo)?;
+    let subject = message.lines().next().unwrap_or("");
+    let kind = match (merge_head.is_some(), parents.is_empty()) {
+        (true, _) => " (merge)",
+        (false, true) => " (initial)",
+        (false, false) => "",
+    };
+    update_head(&repo, &sha, &format!("commit{}: {}", kind, subject))?;
+    for name in MERGE_STATE {
+        let _ = fs::remove_file(repo.repo_path(name));
+    }
+    print_summary(&repo, &sha, subject, parents.is_empty())
+}
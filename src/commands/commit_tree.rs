@@ -0,0 +1,48 @@
+use std::io::Read;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::object::{GitCommit, GitObject, ObjectType, Signature};
+use git_rs::refs::find_object;
+
+use super::open_repo;
+
+
+pub fn commit_tree(args: &[String]) -> Result<()> {
+    let repo = open_repo()?;
+    let mut tree = None;
+    let mut parents: Vec<String> = Vec::new();
+    let mut messages: Vec<String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" => {
+                let parent = args.next().ok_or_else(|| anyhow!("option -p requires a value"))?;
+                let sha = find_object(&repo, parent, Some(ObjectType::Commit))?;
+                if !parents.contains(&sha) {
+                    parents.push(sha);
+                }
+            }
+            "-m" => messages.push(args.next().ok_or_else(|| anyhow!("option -m requires a value"))?.clone()),
+            name if tree.is_none() => tree = Some(find_object(&repo, name, Some(ObjectType::Tree))?),
+            _ => bail!("usage: git_rs commit-tree <tree> [(-p <parent>)...] [(-m <message>)...]"),
+        }
+    }
+    let Some(tree) = tree else {
+        bail!("usage: git_rs commit-tree <tree> [(-p <parent>)...] [(-m <message>)...]");
+    };
+
+    let message = if messages.is_empty() {
+        let mut message = String::new();
+        std::io::stdin().read_to_string(&mut message)?;
+        message
+    } else {
+        messages.iter().map(|m| format!("{}\n", m.trim_end_matches('\n'))).collect::<Vec<_>>().join("\n")
+    };
+
+    let author = Signature::identity(&repo, "AUTHOR")?;
+    let committer = Signature::identity(&repo, "COMMITTER")?;
+    let commit = GitCommit::build(&tree, &parents, &author, &committer, &message);
+    println!("{}", commit.write(&repo)?);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use git_rs::convert::textconv;
+use git_rs::diff::{compare_entries, detect_renames, diff_trees, index_entries, tree_entries, worktree_entries, write_patch, DiffEntry, FileChange};
+use git_rs::index::GitIndex;
+use git_rs::object::{read_blob, FileMode, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::repository::Repository;
+use git_rs::worktree::read_worktree_file;
+
+use super::{open_repo, parse_rename_option};
+
+
+fn entry_content(repo: &Repository, entry: Option<&DiffEntry>) -> Result<Vec<u8>> {
+    Ok(match entry {
+        None => Vec::new(),
+        Some(entry) if entry.mode == FileMode::Gitlink => format!("Subproject commit {}\n", entry.sha).into_bytes(),
+        Some(entry) => read_blob(repo, &entry.sha)?.raw_data,
+    })
+}
+
+// The tree of HEAD, or None on an unborn branch.
+fn head_tree(repo: &Repository) -> Result<Option<String>> {
+    match repo.head_commit()? {
+        Some(sha) => Ok(Some(find_object(repo, &sha, Some(ObjectType::Tree))?)),
+        None => Ok(None),
+    }
+}
+
+fn rev_entries(repo: &Repository, rev: &str) -> Result<BTreeMap<String, DiffEntry>> {
+    tree_entries(repo, &find_object(repo, rev, Some(ObjectType::Tree))?)
+}
+
+pub fn diff(args: &[String]) -> Result<()> {
+    let mut renames = None;
+    let mut cached = false;
+    let mut revs = Vec::new();
+    for arg in args {
+        if arg == "--cached" || arg == "--staged" {
+            cached = true;
+        } else if !parse_rename_option(arg, &mut renames)? {
+            revs.push(arg);
+        }
+    }
+
+    let repo = open_repo()?;
+    // Only the worktree side of an index-to-worktree diff is read from disk;
+    // everything else comes from the object store.
+    let (mut changes, from_worktree) = match (cached, &revs[..]) {
+        (false, []) => {
+            let index = GitIndex::read(&repo)?;
+            (compare_entries(&index_entries(&index), &worktree_entries(&repo, &index)?), true)
+        }
+        (true, []) => {
+            let old = match head_tree(&repo)? {
+                Some(tree) => tree_entries(&repo, &tree)?,
+                None => BTreeMap::new(),
+            };
+            (compare_entries(&old, &index_entries(&GitIndex::read(&repo)?)), false)
+        }
+        (true, [rev]) => (compare_entries(&rev_entries(&repo, rev)?, &index_entries(&GitIndex::read(&repo)?)), false),
+        (false, [old, new]) => {
+            let old = find_object(&repo, old, Some(ObjectType::Tree))?;
+            let new = find_object(&repo, new, Some(ObjectType::Tree))?;
+            (diff_trees(&repo, &old, &new)?.into_iter().map(FileChange::from).collect(), false)
+        }
+        _ => bail!("usage: git_rs diff [-M[<n>]] [--cached [<commit>] | <commit> <commit>]"),
+    };
+
+    let load_new = |path: &str, entry: Option<&DiffEntry>| match entry {
+        Some(entry) if from_worktree && entry.mode != FileMode::Gitlink => read_worktree_file(&repo, path),
+        _ => entry_content(&repo, entry),
+    };
+    if let Some(threshold) = renames {
+        changes = detect_renames(changes, threshold, |sha| Ok(read_blob(&repo, sha)?.raw_data))?;
+    }
+
+    // Patches show blobs through their textconv driver, if any.
+    let display = |path: &str, entry: Option<&DiffEntry>, data: Vec<u8>| match entry {
+        Some(entry) if entry.mode != FileMode::Gitlink => textconv(&repo, path, data),
+        _ => Ok(data),
+    };
+
+    let mut out = std::io::stdout().lock();
+    for change in &changes {
+        let old = display(&change.old_path, change.old.as_ref(), entry_content(&repo, change.old.as_ref())?)?;
+        let new = display(&change.new_path, change.new.as_ref(), load_new(&change.new_path, change.new.as_ref())?)?;
+        write_patch(&mut out, change, &old, &new)?;
+    }
+    out.flush()?;
+    Ok(())
+}
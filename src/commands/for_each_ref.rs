@@ -0,0 +1,127 @@
+use anyhow::{bail, Result};
+use git_rs::ignore::wildmatch;
+use git_rs::object::{read_commit, read_object_header, read_raw, GitTag, ObjectType};
+use git_rs::refs::{list_refs, read_symbolic_ref};
+
+use super::open_repo;
+
+
+const DEFAULT_FORMAT: &str = "%(objectname) %(objecttype)\t%(refname)";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Atom {
+    Literal(String),
+    RefName,
+    ShortRefName,
+    ObjectName,
+    ShortObjectName,
+    ObjectType,
+    ObjectSize,
+    Subject,
+    Head,
+}
+
+// Splits a --format string into literal text and %(field) atoms; `%%` is a
+// literal percent sign and `%xx` a hex-escaped byte.
+fn parse_format(format: &str) -> Result<Vec<Atom>> {
+    let mut atoms = Vec::new();
+    let mut literal = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        literal.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix('%') {
+            literal.push('%');
+            rest = after;
+            continue;
+        }
+        if let Some(byte) = rest.get(..2).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            literal.push(byte as char);
+            rest = &rest[2..];
+            continue;
+        }
+        let Some((field, after)) = rest.strip_prefix('(').and_then(|inner| inner.split_once(')')) else {
+            literal.push('%');
+            continue;
+        };
+        let atom = match field {
+            "refname" => Atom::RefName,
+            "refname:short" => Atom::ShortRefName,
+            "objectname" => Atom::ObjectName,
+            "objectname:short" => Atom::ShortObjectName,
+            "objecttype" => Atom::ObjectType,
+            "objectsize" => Atom::ObjectSize,
+            "subject" => Atom::Subject,
+            "HEAD" => Atom::Head,
+            _ => bail!("unknown field name: {}", field),
+        };
+        if !literal.is_empty() {
+            atoms.push(Atom::Literal(std::mem::take(&mut literal)));
+        }
+        atoms.push(atom);
+        rest = after;
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        atoms.push(Atom::Literal(literal));
+    }
+    Ok(atoms)
+}
+
+fn short_ref_name(name: &str) -> &str {
+    ["refs/heads/", "refs/tags/", "refs/remotes/", "refs/"].iter().find_map(|prefix| name.strip_prefix(prefix)).unwrap_or(name)
+}
+
+// A pattern matches a ref when it is a leading run of whole path components
+// of the name or a glob matching all of it.
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    name == pattern || name.strip_prefix(pattern).is_some_and(|rest| rest.starts_with('/')) || wildmatch(pattern, name, true)
+}
+
+pub fn for_each_ref(args: &[String]) -> Result<()> {
+    let mut format = DEFAULT_FORMAT.to_string();
+    let mut patterns = Vec::new();
+    for arg in args {
+        if let Some(value) = arg.strip_prefix("--format=") {
+            format = value.to_string();
+        } else if arg.starts_with('-') {
+            bail!("unknown option '{}'", arg);
+        } else {
+            patterns.push(arg);
+        }
+    }
+    let atoms = parse_format(&format)?;
+
+    let repo = open_repo()?;
+    let head = read_symbolic_ref(&repo, "HEAD")?;
+    for (name, sha) in list_refs(&repo)? {
+        if !patterns.is_empty() && !patterns.iter().any(|pattern| matches_pattern(&name, pattern)) {
+            continue;
+        }
+        let (type_, size) = read_object_header(&repo, &sha)?;
+        let mut out = String::new();
+        for atom in &atoms {
+            match atom {
+                Atom::Literal(text) => out.push_str(text),
+                Atom::RefName => out.push_str(&name),
+                Atom::ShortRefName => out.push_str(short_ref_name(&name)),
+                Atom::ObjectName => out.push_str(&sha),
+                Atom::ShortObjectName => out.push_str(&sha[..7]),
+                Atom::ObjectType => out.push_str(type_.as_str()),
+                Atom::ObjectSize => out.push_str(&size.to_string()),
+                Atom::Subject => {
+                    let message = match type_ {
+                        ObjectType::Commit => read_commit(&repo, &sha)?.kvlm.message,
+                        ObjectType::Tag => GitTag::deserialize(&read_raw(&repo, &sha)?.1)?.kvlm.message,
+                        _ => String::new(),
+                    };
+                    out.push_str(message.lines().next().unwrap_or(""));
+                }
+                Atom::Head => out.push(if head.as_deref() == Some(name.as_str()) { '*' } else { ' ' }),
+            }
+        }
+        println!("{}", out);
+    }
+    Ok(())
+}
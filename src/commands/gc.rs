@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use flate2::Compression;
+use git_rs::object::loose_objects;
+use git_rs::pack::write_pack;
+use git_rs::refs::list_reflogs;
+
+use super::prune::{prune_loose, reachable};
+use super::{open_repo, parse_expiry, reflog};
+
+
+const DEFAULT_AUTO_THRESHOLD: usize = 6700;
+
+// Cleans up the object store once there are more loose objects than
+// `gc.auto` allows (or always with --force). Reflog entries older than
+// `gc.reflogExpire` are expired first, then unreachable loose objects older
+// than `gc.pruneExpire` are pruned, with every reflog entry left still
+// keeping its objects. Everything reachable that we store ourselves is then
+// written to one new pack, replacing our older packs and the loose copies.
+// --aggressive packs at the best zlib level.
+pub fn gc(args: &[String]) -> Result<()> {
+    let mut aggressive = false;
+    let mut force = false;
+    let mut prune_expire = None;
+    for arg in args {
+        match arg.as_str() {
+            "--aggressive" => aggressive = true,
+            "--force" => force = true,
+            _ => match arg.strip_prefix("--prune=") {
+                Some(value) => prune_expire = Some(value.to_string()),
+                None => bail!("usage: git_rs gc [--aggressive] [--force] [--prune=<date>]"),
+            },
+        }
+    }
+
+    let repo = open_repo()?;
+    let threshold = match repo.config_get("gc", "auto") {
+        Some(value) => value.trim().parse().map_err(|_| anyhow!("bad numeric config value '{}' for 'gc.auto'", value))?,
+        None => DEFAULT_AUTO_THRESHOLD,
+    };
+    // A threshold of 0 turns automatic collection off altogether.
+    if !force && (threshold == 0 || loose_objects(&repo)?.len() <= threshold) {
+        return Ok(());
+    }
+
+    if !list_reflogs(&repo)?.is_empty() {
+        reflog(&["expire".to_string(), "--all".to_string()])?;
+    }
+    let expire = prune_expire.or_else(|| repo.config_get("gc", "pruneexpire")).unwrap_or_else(|| "2.weeks.ago".to_string());
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let reachable = reachable(&repo, None)?;
+    prune_loose(&repo, &reachable, parse_expiry(&expire, now)?, false, false)?;
+
+    // Objects only found in an alternate stay there. Unreachable objects in
+    // our old packs are dropped along with them.
+    let pack_dir = repo.repo_path("objects/pack");
+    let old_packs: Vec<_> = repo.packs()?.iter().filter(|pack| pack.pack.starts_with(&pack_dir)).collect();
+    let mut stored: BTreeSet<String> = loose_objects(&repo)?.into_iter().collect();
+    stored.extend(old_packs.iter().flat_map(|pack| pack.shas()));
+    let shas: Vec<String> = stored.into_iter().filter(|sha| reachable.contains(sha)).collect();
+    if shas.is_empty() {
+        return Ok(());
+    }
+
+    let compression = if aggressive { Compression::best() } else { Compression::default() };
+    let name = write_pack(&repo, &shas, compression)?;
+    let new_pack = pack_dir.join(format!("pack-{}.pack", name));
+    for pack in old_packs.iter().filter(|pack| pack.pack != new_pack) {
+        // The index goes first, so the pack is never listed without its data.
+        fs::remove_file(pack.pack.with_extension("idx"))?;
+        fs::remove_file(&pack.pack)?;
+    }
+    for sha in &shas {
+        let path = repo.object_path(sha);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            if let Some(dir) = path.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+    }
+    Ok(())
+}
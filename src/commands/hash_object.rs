@@ -0,0 +1,63 @@
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use git_rs::convert::to_git;
+use git_rs::object::{hash_literally, validate, write_literally, ObjectType};
+
+use super::open_repo;
+
+
+pub fn hash_object(args: &[String]) -> Result<()> {
+    let mut write = false;
+    let mut stdin = false;
+    let mut literally = false;
+    let mut type_name = "blob".to_string();
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-w" => write = true,
+            "--stdin" => stdin = true,
+            "--literally" => literally = true,
+            "-t" => {
+                type_name = args.next().ok_or_else(|| anyhow!("option -t requires a value"))?.clone();
+            }
+            flag if flag.starts_with('-') => bail!("unknown option '{}'", flag),
+            file => files.push(file),
+        }
+    }
+    if !stdin && files.is_empty() {
+        bail!("usage: git_rs hash-object [-t <type>] [-w] [--literally] (--stdin | <file>...)");
+    }
+
+    // Only --literally may write objects of a type git doesn't know.
+    let type_ = match ObjectType::from_str(&type_name) {
+        Ok(type_) => Some(type_),
+        Err(_) if literally => None,
+        Err(_) => bail!("invalid object type '{}'", type_name),
+    };
+
+    let repo = open_repo()?;
+    let mut inputs = Vec::new();
+    if stdin {
+        let mut data = Vec::new();
+        std::io::stdin().read_to_end(&mut data)?;
+        inputs.push(data);
+    }
+    for file in files {
+        let data = std::fs::read(file).with_context(|| format!("could not open '{}' for reading", file))?;
+        // Attributes apply by the file's path within the worktree.
+        let path = repo.worktree_path(file).unwrap_or_else(|_| file.to_string());
+        inputs.push(if type_ == Some(ObjectType::Blob) { to_git(&repo, &path, data)? } else { data });
+    }
+
+    for data in inputs {
+        if let (Some(type_), false) = (type_, literally) {
+            validate(type_, &data)?;
+        }
+        println!("{}", if write { write_literally(&repo, &type_name, &data)? } else { hash_literally(&type_name, &data) });
+    }
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use anyhow::{bail, Result};
+use git_rs::object::{read_commit, resolve_path_in_tree, FileMode, GitCommit, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::repository::Repository;
+use git_rs::walker::CommitWalker;
+
+use super::{open_repo, parse_depth_option};
+
+
+fn path_entry(repo: &Repository, commit: &GitCommit, path: &str) -> Result<Option<(FileMode, String)>> {
+    if path.is_empty() {
+        return Ok(Some((FileMode::Tree, commit.tree()?.to_string())));
+    }
+    Ok(resolve_path_in_tree(repo, commit.tree()?, path)?.map(|leaf| (leaf.mode, leaf.sha)))
+}
+
+// Whether any of `paths` differs between the commit and its first parent.
+// Comparing entry SHAs means a directory counts as changed whenever anything
+// below it changed.
+fn touches_paths(repo: &Repository, commit: &GitCommit, paths: &[String]) -> Result<bool> {
+    let parent = match commit.parents().first() {
+        Some(parent) => Some(read_commit(repo, parent)?),
+        None => None,
+    };
+    for path in paths {
+        let current = path_entry(repo, commit, path)?;
+        let previous = match &parent {
+            Some(parent) => path_entry(repo, parent, path)?,
+            None => None,
+        };
+        if current != previous {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+const DEFAULT_DATE: &str = "%a %b %-d %H:%M:%S %Y %z";
+
+#[derive(Debug, Clone, PartialEq)]
+enum FormatPart {
+    Literal(String),
+    Hash,
+    ShortHash,
+    Tree,
+    ShortTree,
+    Parents,
+    ShortParents,
+    AuthorName,
+    AuthorEmail,
+    AuthorDate,
+    CommitterName,
+    CommitterEmail,
+    CommitterDate,
+    Subject,
+    Body,
+}
+
+// Splits a --format string into literal text and placeholders. Unknown
+// placeholders are kept as literal text, as git does.
+fn parse_format(format: &str) -> Vec<FormatPart> {
+    let placeholders = [
+        ("H", FormatPart::Hash),
+        ("h", FormatPart::ShortHash),
+        ("T", FormatPart::Tree),
+        ("t", FormatPart::ShortTree),
+        ("P", FormatPart::Parents),
+        ("p", FormatPart::ShortParents),
+        ("an", FormatPart::AuthorName),
+        ("ae", FormatPart::AuthorEmail),
+        ("ad", FormatPart::AuthorDate),
+        ("cn", FormatPart::CommitterName),
+        ("ce", FormatPart::CommitterEmail),
+        ("cd", FormatPart::CommitterDate),
+        ("s", FormatPart::Subject),
+        ("b", FormatPart::Body),
+        ("n", FormatPart::Literal("\n".to_string())),
+        ("%", FormatPart::Literal("%".to_string())),
+    ];
+
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        literal.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        match placeholders.iter().find(|(key, _)| rest.starts_with(key)) {
+            Some((key, part)) => {
+                rest = &rest[key.len()..];
+                if let FormatPart::Literal(text) = part {
+                    literal.push_str(text);
+                    continue;
+                }
+                if !literal.is_empty() {
+                    parts.push(FormatPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(part.clone());
+            }
+            None => literal.push('%'),
+        }
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        parts.push(FormatPart::Literal(literal));
+    }
+    parts
+}
+
+// The subject is the first paragraph joined onto one line; the body is
+// everything after it.
+fn split_message(message: &str) -> (String, &str) {
+    let mut subject = Vec::new();
+    let mut rest = message;
+    while let Some(line) = rest.split_inclusive('\n').next() {
+        if line.trim().is_empty() {
+            break;
+        }
+        subject.push(line.trim());
+        rest = &rest[line.len()..];
+    }
+    (subject.join(" "), rest.trim_start_matches(['\n', '\r']))
+}
+
+fn format_commit(sha: &str, commit: &GitCommit, format: &[FormatPart]) -> Result<String> {
+    let author = commit.author()?;
+    let committer = commit.committer()?;
+    let (subject, body) = split_message(&commit.kvlm.message);
+    let mut out = String::new();
+    for part in format {
+        match part {
+            FormatPart::Literal(text) => out.push_str(text),
+            FormatPart::Hash => out.push_str(sha),
+            FormatPart::ShortHash => out.push_str(&sha[..7]),
+            FormatPart::Tree => out.push_str(commit.tree()?),
+            FormatPart::ShortTree => out.push_str(&commit.tree()?[..7]),
+            FormatPart::Parents => out.push_str(&commit.parents().join(" ")),
+            FormatPart::ShortParents => out.push_str(&commit.parents().iter().map(|p| &p[..7]).collect::<Vec<_>>().join(" ")),
+            FormatPart::AuthorName => out.push_str(&author.name),
+            FormatPart::AuthorEmail => out.push_str(&author.email),
+            FormatPart::AuthorDate => out.push_str(&author.format_date(DEFAULT_DATE)),
+            FormatPart::CommitterName => out.push_str(&committer.name),
+            FormatPart::CommitterEmail => out.push_str(&committer.email),
+            FormatPart::CommitterDate => out.push_str(&committer.format_date(DEFAULT_DATE)),
+            FormatPart::Subject => out.push_str(&subject),
+            FormatPart::Body => out.push_str(body),
+        }
+    }
+    Ok(out)
+}
+
+// Commits whose parents were cut off by --depth are marked grafted, like
+// the edge of a shallow clone.
+fn print_commit(sha: &str, commit: &GitCommit, grafted: bool) -> Result<()> {
+    println!("commit {}{}", sha, if grafted { " (grafted)" } else { "" });
+    let parents = commit.parents();
+    if parents.len() > 1 {
+        println!("Merge: {}", parents.iter().map(|p| &p[..7]).collect::<Vec<_>>().join(" "));
+    }
+    let author = commit.author()?;
+    println!("Author: {} <{}>", author.name, author.email);
+    println!("Date:   {}", author.format_date(DEFAULT_DATE));
+    println!();
+    for line in commit.kvlm.message.trim_end().lines() {
+        println!("    {}", line);
+    }
+    Ok(())
+}
+
+pub fn log(args: &[String]) -> Result<()> {
+    let (revs, paths) = match args.iter().position(|arg| arg == "--") {
+        Some(split) => (&args[..split], &args[split + 1..]),
+        None => (args, &[][..]),
+    };
+
+    // `--format` terminates every commit with a newline while
+    // `--pretty=format:` only separates them.
+    let mut format = None;
+    let mut terminate = true;
+    let mut depth = None;
+    let mut rev_args = Vec::new();
+    for arg in revs {
+        if let Some(n) = parse_depth_option(arg)? {
+            depth = Some(n);
+        } else if let Some(fmt) = arg.strip_prefix("--format=").or_else(|| arg.strip_prefix("--pretty=tformat:")) {
+            format = Some(parse_format(fmt));
+            terminate = true;
+        } else if let Some(fmt) = arg.strip_prefix("--pretty=format:") {
+            format = Some(parse_format(fmt));
+            terminate = false;
+        } else if arg.starts_with('-') {
+            bail!("unknown option '{}'", arg);
+        } else {
+            rev_args.push(arg);
+        }
+    }
+    let revs = rev_args;
+
+    let repo = open_repo()?;
+    let paths: Vec<String> = paths.iter().map(|path| repo.worktree_path(path)).collect::<Result<_>>()?;
+    let starts: Vec<String> = if revs.is_empty() {
+        vec![find_object(&repo, "HEAD", Some(ObjectType::Commit))?]
+    } else {
+        revs.iter().map(|rev| find_object(&repo, rev, Some(ObjectType::Commit))).collect::<Result<_, _>>()?
+    };
+
+    let mut walker = CommitWalker::new(&repo, &starts)?;
+    if let Some(depth) = depth {
+        walker = walker.with_max_depth(depth);
+    }
+
+    let mut first = true;
+    while let Some(entry) = walker.next() {
+        let (sha, commit) = entry?;
+        if !paths.is_empty() && !touches_paths(&repo, &commit, &paths)? {
+            continue;
+        }
+        match &format {
+            Some(format) => {
+                let line = format_commit(&sha, &commit, format)?;
+                if terminate {
+                    println!("{}", line);
+                } else {
+                    print!("{}{}", if first { "" } else { "\n" }, line);
+                }
+            }
+            None => {
+                if !first {
+                    println!();
+                }
+                print_commit(&sha, &commit, walker.is_boundary(&sha))?;
+            }
+        }
+        first = false;
+    }
+    Ok(())
+}
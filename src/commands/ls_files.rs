@@ -0,0 +1,73 @@
+use std::fs;
+
+use anyhow::{bail, Result};
+use git_rs::convert::eol_info;
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::object::{read_blob, FileMode};
+use git_rs::repository::Repository;
+use git_rs::utils::display_path;
+use git_rs::worktree::path_matches;
+
+use super::open_repo;
+
+
+// The `i/<eol> w/<eol> attr/` columns: line endings in the index and in the
+// worktree, left blank for things that are not regular files.
+fn eol_columns(repo: &Repository, entry: &GitIndexEntry) -> Result<String> {
+    let regular = entry.mode != FileMode::Gitlink && entry.mode != FileMode::Symlink;
+    let index = if regular { eol_info(&read_blob(repo, &entry.sha)?.raw_data) } else { "" };
+    let full = repo.worktree_file(&entry.name);
+    let worktree = match fs::symlink_metadata(&full) {
+        Ok(metadata) if regular && metadata.is_file() => eol_info(&fs::read(&full)?),
+        _ => "",
+    };
+    Ok(format!("i/{:<5} w/{:<5} attr/{:<17}\t", index, worktree, ""))
+}
+
+fn print_debug(entry: &GitIndexEntry) {
+    let flags = ((entry.stage as u32) << 12) | if entry.assume_valid { 0x8000 } else { 0 };
+    println!("  ctime: {}:{}", entry.ctime.0, entry.ctime.1);
+    println!("  mtime: {}:{}", entry.mtime.0, entry.mtime.1);
+    println!("  dev: {}\tino: {}", entry.dev, entry.ino);
+    println!("  uid: {}\tgid: {}", entry.uid, entry.gid);
+    println!("  size: {}\tflags: {:x}", entry.size, flags);
+}
+
+pub fn ls_files(args: &[String]) -> Result<()> {
+    let mut stage = false;
+    let mut debug = false;
+    let mut eol = false;
+    let mut specs = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-c" | "--cached" => {}
+            "-s" | "--stage" => stage = true,
+            "--debug" => debug = true,
+            "--eol" => eol = true,
+            "--" => {}
+            _ if arg.starts_with('-') => bail!("unknown option '{}'", arg),
+            _ => specs.push(arg),
+        }
+    }
+
+    let repo = open_repo()?;
+    let specs: Vec<String> = specs.iter().map(|spec| repo.worktree_path(spec)).collect::<Result<_>>()?;
+    let index = GitIndex::read(&repo)?;
+    for entry in &index.entries {
+        if !specs.is_empty() && !specs.iter().any(|spec| path_matches(&entry.name, spec)) {
+            continue;
+        }
+        let mut line = String::new();
+        if stage {
+            line.push_str(&format!("{:0>6} {} {}\t", entry.mode, entry.sha, entry.stage));
+        }
+        if eol {
+            line.push_str(&eol_columns(&repo, entry)?);
+        }
+        println!("{}{}", line, display_path(&entry.name));
+        if debug {
+            print_debug(entry);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::{bail, Result};
+use git_rs::object::{read_object_header, read_tree, GitTreeLeaf, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::repository::Repository;
+use git_rs::utils::display_path;
+
+use super::open_repo;
+
+
+fn print_leaf(repo: &Repository, leaf: GitTreeLeaf, long: bool) -> Result<()> {
+    if !long {
+        println!("{}", leaf);
+        return Ok(());
+    }
+    // Only blobs have a size column; trees and submodules show `-`.
+    let size = match leaf.object_type() {
+        ObjectType::Blob => read_object_header(repo, &leaf.sha)?.1.to_string(),
+        _ => "-".to_string(),
+    };
+    println!("{:0>6} {} {} {:>7}\t{}", leaf.mode, leaf.object_type(), leaf.sha, size, display_path(&leaf.path));
+    Ok(())
+}
+
+fn print_tree(repo: &Repository, sha: &str, prefix: &str, recursive: bool, long: bool) -> Result<()> {
+    for leaf in read_tree(repo, sha)?.leaves {
+        let path = format!("{}{}", prefix, leaf.path);
+        if recursive && leaf.is_tree() {
+            print_tree(repo, &leaf.sha, &format!("{}/", path), recursive, long)?;
+        } else {
+            print_leaf(repo, GitTreeLeaf { path, ..leaf }, long)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn ls_tree(args: &[String]) -> Result<()> {
+    let mut recursive = false;
+    let mut long = false;
+    let mut names = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-r" => recursive = true,
+            "-l" | "--long" => long = true,
+            _ if arg.starts_with('-') => bail!("unknown option '{}'", arg),
+            _ => names.push(arg),
+        }
+    }
+    let [name] = names[..] else {
+        bail!("usage: git_rs ls-tree [-r] [-l | --long] <tree-ish>");
+    };
+
+    let repo = open_repo()?;
+    let sha = find_object(&repo, name, Some(ObjectType::Tree))?;
+    print_tree(&repo, &sha, "", recursive, long)
+}
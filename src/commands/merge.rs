@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::convert::to_worktree;
+use git_rs::diff::{compare_entries, index_entries, tree_entries};
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::merge::{merge_trees, TreeMerge};
+use git_rs::object::{read_commit, ObjectType};
+use git_rs::refs::{find_object, resolve_ref, update_head};
+use git_rs::repository::{HeadState, Repository};
+use git_rs::walker::merge_base;
+use git_rs::worktree::{checkout_tree, is_modified, remove_worktree_file, write_worktree_file};
+
+use super::commit::write_commit;
+use super::open_repo;
+
+
+// The message git proposes for merging `name` into the current branch.
+fn default_message(repo: &Repository, head: &HeadState, name: &str) -> Result<String> {
+    let kind = if resolve_ref(repo, &format!("refs/heads/{}", name))?.is_some() {
+        "branch"
+    } else if resolve_ref(repo, &format!("refs/tags/{}", name))?.is_some() {
+        "tag"
+    } else {
+        "commit"
+    };
+    let mut message = format!("Merge {} '{}'", kind, name);
+    if let Some(branch) = head.branch() {
+        let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+        if branch != "master" && branch != "main" {
+            message.push_str(&format!(" into {}", branch));
+        }
+    }
+    Ok(message)
+}
+
+// Brings the worktree and index in line with a merge result, refusing to
+// overwrite local changes to any file the merge touches.
+pub fn apply_merge(repo: &Repository, merge: &TreeMerge) -> Result<()> {
+    let index = GitIndex::read(repo)?;
+    let current: HashMap<&str, &GitIndexEntry> = index.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+    let merged: HashMap<&str, &GitIndexEntry> = merge.index.entries.iter().filter(|entry| entry.stage == 0).map(|entry| (entry.name.as_str(), entry)).collect();
+    let touched = |name: &str| merge.conflict_files.contains_key(name) || merged.get(name).map(|entry| &entry.sha) != current.get(name).map(|entry| &entry.sha);
+
+    let mut dirty = Vec::new();
+    for entry in &index.entries {
+        if touched(&entry.name) && fs::symlink_metadata(repo.worktree_file(&entry.name)).is_ok() && is_modified(repo, entry)? {
+            dirty.push(entry.name.clone());
+        }
+    }
+    if !dirty.is_empty() {
+        bail!("Your local changes to the following files would be overwritten by merge:\n\t{}\nPlease commit your changes or stash them before you merge.", dirty.join("\n\t"));
+    }
+    // Files the merge adds must not clobber untracked ones already there.
+    let mut untracked: Vec<&str> = merge.index.entries.iter().map(|entry| entry.name.as_str())
+        .filter(|name| !current.contains_key(name) && fs::symlink_metadata(repo.worktree_file(name)).is_ok_and(|metadata| !metadata.is_dir()))
+        .collect();
+    untracked.dedup();
+    if !untracked.is_empty() {
+        bail!("The following untracked working tree files would be overwritten by merge:\n\t{}\nPlease move or remove them before you merge.", untracked.join("\n\t"));
+    }
+
+    for entry in &index.entries {
+        if !merged.contains_key(entry.name.as_str()) && !merge.conflicts.contains(&entry.name) {
+            remove_worktree_file(repo, &entry.name)?;
+        }
+    }
+
+    let mut result = GitIndex { version: index.version, entries: Vec::new() };
+    for entry in &merge.index.entries {
+        match current.get(entry.name.as_str()) {
+            // Keep the stat data of files the merge leaves alone.
+            Some(existing) if entry.stage == 0 && existing.sha == entry.sha && existing.mode == entry.mode => result.entries.push((*existing).clone()),
+            _ if entry.stage == 0 => {
+                let metadata = write_worktree_file(repo, &entry.name, entry.mode, &entry.sha)?;
+                result.entries.push(GitIndexEntry::new(entry.name.clone(), entry.mode, entry.sha.clone(), Some(&metadata)));
+            }
+            _ => result.entries.push(entry.clone()),
+        }
+    }
+    for (path, content) in &merge.conflict_files {
+        let full = repo.worktree_file(path);
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(full, to_worktree(repo, path, content.clone())?)?;
+    }
+    result.write(repo)
+}
+
+pub fn merge(args: &[String]) -> Result<()> {
+    let mut commit = true;
+    let mut message = None;
+    let mut names = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-commit" => commit = false,
+            "--commit" => commit = true,
+            "-m" => message = Some(args.next().ok_or_else(|| anyhow!("option -m requires a value"))?.clone()),
+            flag if flag.starts_with('-') => bail!("unknown option '{}'", flag),
+            name => names.push(name),
+        }
+    }
+    let [name] = names[..] else {
+        bail!("usage: git_rs merge [--no-commit] [-m <message>] <commit>");
+    };
+
+    let repo = open_repo()?;
+    if repo.repo_path("MERGE_HEAD").is_file() {
+        bail!("You have not concluded your merge (MERGE_HEAD exists).\nPlease, commit your changes before you merge.");
+    }
+    let theirs = find_object(&repo, name, Some(ObjectType::Commit))?;
+    let head = repo.head()?;
+    let Some(ours) = head.sha().map(String::from) else {
+        // Merging into an unborn branch just starts it at the other commit.
+        checkout_tree(&repo, read_commit(&repo, &theirs)?.tree()?)?;
+        return update_head(&repo, &theirs, &format!("merge {}: Fast-forward", name));
+    };
+
+    let base = merge_base(&repo, &ours, &theirs)?;
+    if base.as_deref() == Some(theirs.as_str()) {
+        println!("Already up to date.");
+        return Ok(());
+    }
+    let ours_tree = read_commit(&repo, &ours)?.tree()?.to_string();
+    if !compare_entries(&tree_entries(&repo, &ours_tree)?, &index_entries(&GitIndex::read(&repo)?)).is_empty() {
+        bail!("Your local changes would be overwritten by merge.\nPlease commit your changes or stash them before you merge.");
+    }
+    fs::write(repo.repo_path("ORIG_HEAD"), format!("{}\n", ours))?;
+
+    if base.as_deref() == Some(ours.as_str()) {
+        println!("Updating {}..{}", &ours[..7], &theirs[..7]);
+        println!("Fast-forward");
+        checkout_tree(&repo, read_commit(&repo, &theirs)?.tree()?)?;
+        return update_head(&repo, &theirs, &format!("merge {}: Fast-forward", name));
+    }
+
+    let base_tree = match &base {
+        Some(base) => Some(read_commit(&repo, base)?.tree()?.to_string()),
+        None => None,
+    };
+    let theirs_tree = read_commit(&repo, &theirs)?.tree()?.to_string();
+    let result = merge_trees(&repo, base_tree.as_deref(), &ours_tree, &theirs_tree, "HEAD", name)?;
+    apply_merge(&repo, &result)?;
+    for line in &result.messages {
+        println!("{}", line);
+    }
+
+    let mut message = message.unwrap_or(default_message(&repo, &head, name)?);
+    message.push('\n');
+    if result.conflicts.is_empty() && commit {
+        let tree = result.index.write_tree(&repo)?;
+        let sha = write_commit(&repo, &tree, &[ours, theirs], None, &message)?;
+        update_head(&repo, &sha, &format!("merge {}: Merge made by the 'ort' strategy.", name))?;
+        println!("Merge made by the 'ort' strategy.");
+        return Ok(());
+    }
+
+    // Leave the merge for `commit` to conclude.
+    if !result.conflicts.is_empty() {
+        message.push_str("\n# Conflicts:\n");
+        for path in &result.conflicts {
+            message.push_str(&format!("#\t{}\n", path));
+        }
+    }
+    fs::write(repo.repo_path("MERGE_HEAD"), format!("{}\n", theirs))?;
+    fs::write(repo.repo_path("MERGE_MSG"), message)?;
+    fs::write(repo.repo_path("MERGE_MODE"), "")?;
+    if result.conflicts.is_empty() {
+        println!("Automatic merge went well; stopped before committing as requested");
+        return Ok(());
+    }
+    println!("Automatic merge failed; fix conflicts and then commit the result.");
+    std::process::exit(1);
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Context, Result};
+use git_rs::convert::is_binary;
+use git_rs::merge::merge_content;
+
+
+const USAGE: &str = "usage: git_rs merge-file [-p | --stdout] [-q | --quiet] [-L <name1> [-L <orig> [-L <name2>]]] <file1> <orig-file> <file2>";
+
+// Merges the changes from <orig-file> to <file2> into <file1>, writing the
+// result back to <file1> (or stdout with -p). Like git, the exit status is
+// the number of conflicts, so it needs no repository at all.
+pub fn merge_file(args: &[String]) -> Result<()> {
+    let mut stdout = false;
+    let mut labels = Vec::new();
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" | "--stdout" => stdout = true,
+            // Conflicts are only reported through the exit status anyway.
+            "-q" | "--quiet" => {}
+            "-L" => labels.push(args.next().ok_or_else(|| anyhow!("option -L requires a value"))?.as_str()),
+            flag if flag.starts_with('-') => bail!("unknown option '{}'\n{}", flag, USAGE),
+            file => files.push(file),
+        }
+    }
+    let [current, base, other] = files[..] else {
+        bail!(USAGE);
+    };
+    if labels.len() > 3 {
+        bail!("too many labels on the command line");
+    }
+
+    let mut contents = Vec::new();
+    for file in [current, base, other] {
+        let data = fs::read(file).with_context(|| format!("could not open '{}' for reading", file))?;
+        if is_binary(&data) {
+            eprintln!("error: Cannot merge binary files: {}", file);
+            std::process::exit(255);
+        }
+        contents.push(data);
+    }
+
+    let ours_label = labels.first().copied().unwrap_or(current);
+    let theirs_label = labels.get(2).copied().unwrap_or(other);
+    let (merged, conflicts) = merge_content(&contents[1], &contents[0], &contents[2], ours_label, theirs_label);
+    if stdout {
+        std::io::stdout().write_all(&merged)?;
+    } else {
+        fs::write(current, merged).with_context(|| format!("could not write '{}'", current))?;
+    }
+
+    if conflicts > 0 {
+        std::process::exit(conflicts.min(127) as i32);
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::io::BufRead;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::object::{read_object_header, GitObject, GitTree, GitTreeLeaf, ObjectType};
+
+use super::open_repo;
+
+
+// Reads `ls-tree` formatted lines from stdin and writes them as a tree. Every
+// line is checked before any of the objects it names are looked up.
+pub fn mktree(args: &[String]) -> Result<()> {
+    let allow_missing = match args {
+        [] => false,
+        [flag] if flag == "--missing" => true,
+        _ => bail!("usage: git_rs mktree [--missing]"),
+    };
+
+    let repo = open_repo()?;
+    let mut leaves: Vec<GitTreeLeaf> = Vec::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let format_error = || anyhow!("input format error: {}", line);
+
+        let (info, path) = line.split_once('\t').ok_or_else(format_error)?;
+        let [mode, type_, sha] = info.split(' ').collect::<Vec<_>>()[..] else {
+            return Err(format_error());
+        };
+        if path.is_empty() || path.contains('/') || sha.len() != 40 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format_error());
+        }
+
+        let leaf = GitTreeLeaf { mode: mode.parse().map_err(|_| format_error())?, path: path.to_string(), sha: sha.to_lowercase() };
+        let declared = ObjectType::from_str(type_).map_err(|_| format_error())?;
+        if leaf.object_type() != declared {
+            bail!("entry '{}' object type ({}) doesn't match mode type ({})", path, declared, leaf.object_type());
+        }
+
+        if leaves.iter().any(|existing| existing.path == leaf.path) {
+            bail!("duplicate entry '{}'", path);
+        }
+        leaves.push(leaf);
+    }
+
+    // Submodule commits live in another repository, so they are never checked.
+    if !allow_missing {
+        for leaf in leaves.iter().filter(|leaf| leaf.object_type() != ObjectType::Commit) {
+            let (actual, _) = read_object_header(&repo, &leaf.sha).map_err(|_| anyhow!("entry '{}' object {} is unavailable", leaf.path, leaf.sha))?;
+            if actual != leaf.object_type() {
+                bail!("entry '{}' object {} is a {} but specified type was ({})", leaf.path, leaf.sha, actual, leaf.object_type());
+            }
+        }
+    }
+
+    println!("{}", GitTree::from_leaves(leaves).write(&repo)?);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use git_rs::index::GitIndex;
+use git_rs::object::{loose_objects, read_raw, FileMode};
+use git_rs::progress::Progress;
+use git_rs::refs::{list_reflogs, list_refs, read_reflog, NULL_SHA};
+use git_rs::repository::Repository;
+use git_rs::walker::reachable_objects;
+
+use super::{open_repo, parse_expiry};
+
+
+// The old and new SHAs of every reflog entry. With a cutoff, entries from
+// before it no longer keep their objects alive.
+fn reflog_roots(repo: &Repository, cutoff: Option<i64>) -> Result<Vec<String>> {
+    let mut roots = Vec::new();
+    for log in list_reflogs(repo)? {
+        for entry in read_reflog(repo, &log)? {
+            if cutoff.is_some_and(|cutoff| entry.committer.time < cutoff) {
+                continue;
+            }
+            roots.extend([entry.old, entry.new].into_iter().filter(|sha| sha != NULL_SHA));
+        }
+    }
+    Ok(roots)
+}
+
+// Everything a ref, HEAD, the index or the reflog can still reach. Reflog
+// entries from before `reflog_cutoff` are not counted.
+pub fn reachable(repo: &Repository, reflog_cutoff: Option<i64>) -> Result<HashSet<String>> {
+    let mut roots: Vec<String> = list_refs(repo)?.into_iter().map(|(_, sha)| sha).collect();
+    roots.extend(repo.head_commit()?);
+    roots.extend(GitIndex::read(repo)?.entries.into_iter().filter(|entry| entry.mode != FileMode::Gitlink).map(|entry| entry.sha));
+    roots.extend(reflog_roots(repo, reflog_cutoff)?);
+    reachable_objects(repo, &roots)
+}
+
+// Deletes the loose objects outside `reachable` that are older than
+// `cutoff`, or with `dry_run` only lists them.
+pub fn prune_loose(repo: &Repository, reachable: &HashSet<String>, cutoff: Option<i64>, dry_run: bool, verbose: bool) -> Result<()> {
+    let loose = loose_objects(repo)?;
+    let mut progress = Progress::new("Pruning objects", Some(loose.len()));
+    for sha in loose {
+        progress.tick();
+        if reachable.contains(&sha) {
+            continue;
+        }
+        let path = repo.object_path(&sha);
+        let mtime = fs::metadata(&path)?.modified()?.duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        // Objects newer than the cutoff may belong to a command still running.
+        if cutoff.is_none_or(|cutoff| mtime > cutoff) {
+            continue;
+        }
+
+        if dry_run || verbose {
+            match read_raw(repo, &sha) {
+                Ok((type_, _)) => println!("{} {}", sha, type_),
+                Err(_) => println!("{} unknown", sha),
+            }
+        }
+        if !dry_run {
+            fs::remove_file(&path)?;
+            if let Some(dir) = path.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+    }
+    progress.finish();
+    Ok(())
+}
+
+pub fn prune(args: &[String]) -> Result<()> {
+    let mut dry_run = false;
+    let mut verbose = false;
+    let mut expire = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" | "--dry-run" => dry_run = true,
+            "-v" | "--verbose" => verbose = true,
+            "--expire" => match args.next() {
+                Some(value) => expire = Some(value.clone()),
+                None => bail!("option '--expire' requires a value"),
+            },
+            _ => match arg.strip_prefix("--expire=") {
+                Some(value) => expire = Some(value.to_string()),
+                None => bail!("usage: git_rs prune [-n | --dry-run] [-v | --verbose] [--expire <time>]"),
+            },
+        }
+    }
+
+    let repo = open_repo()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let cutoff = parse_expiry(expire.as_deref().unwrap_or("2.weeks.ago"), now)?;
+    let reachable = reachable(&repo, expire.as_ref().and(cutoff))?;
+    prune_loose(&repo, &reachable, cutoff, dry_run, verbose)
+}
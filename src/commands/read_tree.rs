@@ -0,0 +1,20 @@
+use anyhow::{bail, Result};
+use git_rs::index::GitIndex;
+use git_rs::object::ObjectType;
+use git_rs::refs::find_object;
+use git_rs::worktree::check_case_collisions;
+
+use super::open_repo;
+
+
+// Replaces the index with the contents of a tree without touching the worktree.
+pub fn read_tree(args: &[String]) -> Result<()> {
+    let [name] = args else {
+        bail!("usage: git_rs read-tree <tree-ish>");
+    };
+    let repo = open_repo()?;
+    let tree = find_object(&repo, name, Some(ObjectType::Tree))?;
+    let mut index = GitIndex::from_tree(&repo, &tree)?;
+    check_case_collisions(&repo, index.entries.iter().map(|entry| entry.name.as_str()))?;
+    index.write(&repo)
+}
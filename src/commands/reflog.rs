@@ -0,0 +1,64 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use git_rs::refs::{list_reflogs, read_reflog, reflog_name, write_reflog};
+use git_rs::repository::Repository;
+
+use super::{open_repo, parse_expiry};
+
+
+fn show(repo: &Repository, name: Option<&String>) -> Result<()> {
+    let name = name.map(|n| n.as_str()).unwrap_or("HEAD");
+    let entries = read_reflog(repo, &reflog_name(repo, name)?)?;
+    for (n, entry) in entries.iter().rev().enumerate() {
+        println!("{} {}@{{{}}}: {}", &entry.new[..7], name, n, entry.message);
+    }
+    Ok(())
+}
+
+fn expire(repo: &Repository, args: &[String]) -> Result<()> {
+    let mut expiry = None;
+    let mut all = false;
+    let mut names = Vec::new();
+    for arg in args {
+        if let Some(value) = arg.strip_prefix("--expire=") {
+            expiry = Some(value.to_string());
+        } else if arg == "--all" {
+            all = true;
+        } else if arg.starts_with('-') {
+            bail!("unknown option '{}'", arg);
+        } else {
+            names.push(reflog_name(repo, arg)?);
+        }
+    }
+    if all {
+        names = list_reflogs(repo)?;
+    }
+    if names.is_empty() {
+        bail!("no reflog specified to delete");
+    }
+
+    let expiry = expiry.or_else(|| repo.config_get("gc", "reflogexpire")).unwrap_or_else(|| "90.days.ago".to_string());
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let Some(cutoff) = parse_expiry(&expiry, now)? else {
+        return Ok(());
+    };
+    for name in names {
+        let entries = read_reflog(repo, &name)?;
+        let kept: Vec<_> = entries.iter().filter(|entry| entry.committer.time >= cutoff).cloned().collect();
+        if kept.len() != entries.len() {
+            write_reflog(repo, &name, &kept)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn reflog(args: &[String]) -> Result<()> {
+    let repo = open_repo()?;
+    match args.first().map(|arg| arg.as_str()) {
+        Some("expire") => expire(&repo, &args[1..]),
+        Some("show") => show(&repo, args.get(1)),
+        _ if args.len() <= 1 => show(&repo, args.first()),
+        _ => bail!("usage: git_rs reflog [show] [<ref>]\n   or: git_rs reflog expire [--expire=<time>] [--all | <ref>...]"),
+    }
+}
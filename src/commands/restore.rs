@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::object::{flatten_tree, FileMode, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::worktree::{path_matches, remove_worktree_file, write_worktree_file};
+
+use super::open_repo;
+
+
+const USAGE: &str = "usage: git_rs restore [--source=<tree-ish>] [--staged] [--worktree] [--overlay | --no-overlay] [--] <pathspec>...";
+
+pub fn restore(args: &[String]) -> Result<()> {
+    let mut source = None;
+    let mut staged = false;
+    let mut worktree = false;
+    let mut overlay = false;
+    let mut specs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--source" => source = Some(args.next().ok_or_else(|| anyhow!("option '{}' requires a value", arg))?.clone()),
+            "-S" | "--staged" => staged = true,
+            "-W" | "--worktree" => worktree = true,
+            "--overlay" => overlay = true,
+            "--no-overlay" => overlay = false,
+            "--" => specs.extend(args.by_ref()),
+            _ if arg.starts_with("--source=") => source = Some(arg["--source=".len()..].to_string()),
+            _ if arg.starts_with('-') => bail!("unknown option '{}'\n{}", arg, USAGE),
+            _ => specs.push(arg),
+        }
+    }
+    if specs.is_empty() {
+        bail!("you must specify path(s) to restore");
+    }
+    // Without --staged only the worktree is restored.
+    if !staged {
+        worktree = true;
+    }
+
+    let repo = open_repo()?;
+    let specs: Vec<String> = specs.iter().map(|spec| repo.worktree_path(spec)).collect::<Result<_>>()?;
+    let mut index = GitIndex::read(&repo)?;
+
+    // The worktree alone is restored from the index unless a source is given;
+    // the index is restored from HEAD by default.
+    let from_index = source.is_none() && !staged;
+    let mut wanted: BTreeMap<String, (FileMode, String)> = BTreeMap::new();
+    if from_index {
+        for entry in index.entries.iter().filter(|entry| entry.stage == 0) {
+            wanted.insert(entry.name.clone(), (entry.mode, entry.sha.clone()));
+        }
+    } else {
+        let rev = source.as_deref().unwrap_or("HEAD");
+        if rev != "HEAD" || repo.head_commit()?.is_some() {
+            for leaf in flatten_tree(&repo, &find_object(&repo, rev, Some(ObjectType::Tree))?)? {
+                wanted.insert(leaf.path, (leaf.mode, leaf.sha));
+            }
+        }
+    }
+
+    let matches = |path: &str| specs.iter().any(|spec| path_matches(path, spec));
+    for spec in &specs {
+        let known = if overlay {
+            wanted.keys().any(|path| path_matches(path, spec))
+        } else {
+            wanted.keys().chain(index.entries.iter().map(|entry| &entry.name)).any(|path| path_matches(path, spec))
+        };
+        if !known {
+            bail!("pathspec '{}' did not match any file(s) known to git", spec);
+        }
+    }
+
+    // Tracked paths missing from the source are removed, unless in overlay
+    // mode where the source only adds and updates files.
+    let stale: Vec<String> = if overlay {
+        Vec::new()
+    } else {
+        index.entries.iter().map(|entry| entry.name.clone()).filter(|path| matches(path) && !wanted.contains_key(path)).collect()
+    };
+    for path in &stale {
+        if worktree {
+            remove_worktree_file(&repo, path)?;
+        }
+        if staged {
+            index.remove(path);
+        }
+    }
+
+    for (path, (mode, sha)) in wanted.iter().filter(|(path, _)| matches(path)) {
+        let metadata = if worktree { Some(write_worktree_file(&repo, path, *mode, sha)?) } else { None };
+        let unchanged = index.get(path).is_some_and(|entry| entry.mode == *mode && &entry.sha == sha);
+        if staged && !unchanged {
+            index.add(GitIndexEntry::new(path.clone(), *mode, sha.clone(), metadata.as_ref()));
+        } else if unchanged {
+            // Refresh the stat data of entries whose file was just rewritten.
+            if let (Some(metadata), Some(entry)) = (&metadata, index.entries.iter_mut().find(|entry| &entry.name == path && entry.stage == 0)) {
+                entry.update_stat(metadata);
+            }
+        }
+    }
+
+    index.write(&repo)
+}
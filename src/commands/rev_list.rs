@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use git_rs::object::ObjectType;
+use git_rs::refs::find_object;
+use git_rs::walker::CommitWalker;
+
+use super::{open_repo, parse_depth_option};
+
+
+pub fn rev_list(args: &[String]) -> Result<()> {
+    let mut depth = None;
+    let mut boundary = false;
+    let mut revs = Vec::new();
+    for arg in args {
+        if let Some(n) = parse_depth_option(arg)? {
+            depth = Some(n);
+        } else if arg == "--boundary" {
+            boundary = true;
+        } else if arg.starts_with('-') {
+            bail!("unknown option '{}'", arg);
+        } else {
+            revs.push(arg);
+        }
+    }
+    if revs.is_empty() {
+        bail!("usage: git_rs rev-list [--depth=<n>] [--boundary] <commit>...");
+    }
+
+    let repo = open_repo()?;
+    let starts: Vec<String> = revs.iter().map(|rev| find_object(&repo, rev, Some(ObjectType::Commit))).collect::<Result<_, _>>()?;
+    let mut walker = CommitWalker::new(&repo, &starts)?;
+    if let Some(depth) = depth {
+        walker = walker.with_max_depth(depth);
+    }
+    // With --boundary, the parents that --depth cut off follow the listed
+    // commits, marked with a leading `-`.
+    let mut listed = HashSet::new();
+    let mut excluded = Vec::new();
+    while let Some(sha) = walker.next_sha()? {
+        println!("{}", sha);
+        if boundary && walker.is_boundary(&sha) {
+            excluded.extend(repo.commit_node(&sha)?.parents);
+        }
+        listed.insert(sha);
+    }
+    let mut printed = HashSet::new();
+    for parent in excluded {
+        if !listed.contains(&parent) && printed.insert(parent.clone()) {
+            println!("-{}", parent);
+        }
+    }
+    Ok(())
+}
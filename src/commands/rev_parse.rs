@@ -0,0 +1,55 @@
+use std::env;
+
+use anyhow::{bail, Result};
+use git_rs::refs::find_object;
+
+use super::open_repo;
+
+
+// `--verify` resolves exactly one revision and fails instead of printing
+// anything else; with -q it fails silently.
+fn verify(args: &[&str]) -> Result<()> {
+    let quiet = args.iter().any(|arg| *arg == "-q" || *arg == "--quiet");
+    let revs: Vec<&str> = args.iter().copied().filter(|arg| *arg != "-q" && *arg != "--quiet").collect();
+    let repo = open_repo()?;
+    let sha = match revs[..] {
+        [rev] => find_object(&repo, rev, None).ok(),
+        _ => None,
+    };
+    match sha {
+        Some(sha) => println!("{}", sha),
+        None if quiet => std::process::exit(1),
+        None => bail!("Needed a single revision"),
+    }
+    Ok(())
+}
+
+pub fn rev_parse(args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        bail!("usage: git_rs rev-parse [--verify [-q]] [--is-inside-work-tree] [--show-toplevel] [--git-dir] <rev>...");
+    }
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    if args.contains(&"--verify") {
+        let rest: Vec<&str> = args.iter().copied().filter(|arg| *arg != "--verify").collect();
+        return verify(&rest);
+    }
+
+    let repo = open_repo()?;
+    let cwd = env::current_dir()?;
+    for arg in args {
+        match arg {
+            "--is-inside-work-tree" => println!("{}", cwd.starts_with(&repo.worktree) && !cwd.starts_with(&repo.gitdir)),
+            "--show-toplevel" => println!("{}", repo.worktree.display()),
+            // Like git, the git dir is relative when run from the top level
+            // or from inside it.
+            "--git-dir" => match repo.gitdir.strip_prefix(&cwd) {
+                Ok(_) if cwd == repo.gitdir => println!("."),
+                Ok(relative) if cwd == repo.worktree => println!("{}", relative.display()),
+                _ => println!("{}", repo.gitdir.display()),
+            },
+            flag if flag.starts_with('-') => bail!("unknown option '{}'", flag),
+            rev => println!("{}", find_object(&repo, rev, None)?),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anyhow::{bail, Result};
+use git_rs::refs::list_refs;
+
+use super::open_repo;
+
+
+// Patterns match whole trailing path components, so "v1" matches
+// refs/tags/v1 but not refs/tags/xv1.
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    name == pattern || name.ends_with(&format!("/{}", pattern))
+}
+
+pub fn show_ref(args: &[String]) -> Result<()> {
+    let mut heads = false;
+    let mut tags = false;
+    let mut patterns = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--heads" | "--branches" => heads = true,
+            "--tags" => tags = true,
+            _ if arg.starts_with('-') => bail!("unknown option '{}'", arg),
+            _ => patterns.push(arg),
+        }
+    }
+
+    let repo = open_repo()?;
+    let mut found = false;
+    for (name, sha) in list_refs(&repo)? {
+        let kind_ok = (!heads && !tags) || (heads && name.starts_with("refs/heads/")) || (tags && name.starts_with("refs/tags/"));
+        if !kind_ok || (!patterns.is_empty() && !patterns.iter().any(|pattern| matches_pattern(&name, pattern))) {
+            continue;
+        }
+        println!("{} {}", sha, name);
+        found = true;
+    }
+    if !found {
+        std::process::exit(1);
+    }
+    Ok(())
+}
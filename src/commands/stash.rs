@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{bail, Result};
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::merge::merge_trees;
+use git_rs::object::read_commit;
+use git_rs::refs::{pop_reflog, read_reflog, resolve_ref, update_ref, write_reflog};
+use git_rs::repository::{HeadState, Repository};
+use git_rs::worktree::{is_modified, reset_tree, stage_file};
+
+use super::commit::write_commit;
+use super::merge::apply_merge;
+use super::open_repo;
+
+
+const STASH_REF: &str = "refs/stash";
+
+// The index with every tracked file replaced by its worktree version, as
+// `commit -a` would see it.
+fn worktree_index(repo: &Repository, index: &GitIndex) -> Result<GitIndex> {
+    let mut result = GitIndex { version: index.version, entries: Vec::new() };
+    for entry in &index.entries {
+        if fs::symlink_metadata(repo.worktree_file(&entry.name)).is_err() {
+            continue;
+        }
+        result.entries.push(if is_modified(repo, entry)? { stage_file(repo, &entry.name)? } else { entry.clone() });
+    }
+    Ok(result)
+}
+
+fn read_index(repo: &Repository) -> Result<GitIndex> {
+    let index = GitIndex::read(repo)?;
+    if let Some(entry) = index.entries.iter().find(|entry| entry.stage != 0) {
+        bail!("{}: needs merge", entry.name);
+    }
+    Ok(index)
+}
+
+// Saves local changes as a pair of commits on top of HEAD: one holding the
+// index and a second, with HEAD and the first as parents, holding the
+// worktree. `refs/stash` points at the worktree commit and its reflog holds
+// the older entries. Untracked files are left alone.
+fn push(repo: &Repository) -> Result<()> {
+    let head = repo.head()?;
+    let Some(head_sha) = head.sha().map(String::from) else {
+        bail!("You do not have the initial commit yet");
+    };
+    let head_commit = read_commit(repo, &head_sha)?;
+    let head_tree = head_commit.tree()?;
+
+    let index = read_index(repo)?;
+    let index_tree = index.write_tree(repo)?;
+    let worktree_tree = worktree_index(repo, &index)?.write_tree(repo)?;
+    if index_tree == head_tree && worktree_tree == head_tree {
+        println!("No local changes to save");
+        return Ok(());
+    }
+
+    let branch = match &head {
+        HeadState::Branch { name, .. } => name.strip_prefix("refs/heads/").unwrap_or(name),
+        _ => "(no branch)",
+    };
+    let subject = head_commit.kvlm.message.lines().next().unwrap_or("");
+    let summary = format!("{}: {} {}", branch, &head_sha[..7], subject);
+    let index_commit = write_commit(repo, &index_tree, std::slice::from_ref(&head_sha), None, &format!("index on {}\n", summary))?;
+    let stash = write_commit(repo, &worktree_tree, &[head_sha.clone(), index_commit], None, &format!("WIP on {}\n", summary))?;
+
+    // The stash is always logged, since its log is the list of entries.
+    if read_reflog(repo, STASH_REF)?.is_empty() {
+        write_reflog(repo, STASH_REF, &[])?;
+    }
+    update_ref(repo, STASH_REF, &stash, &format!("WIP on {}", summary))?;
+    reset_tree(repo, head_tree)?;
+    println!("Saved working directory and index state WIP on {}", summary);
+    Ok(())
+}
+
+// Merges the newest stash into the worktree, returning whether it applied
+// without conflicts. Like `git stash apply` without --index, changes come
+// back unstaged except for files the stash adds. On conflicts the index
+// holds the conflicting stages and the files have conflict markers.
+fn apply(repo: &Repository) -> Result<bool> {
+    let Some(stash) = resolve_ref(repo, STASH_REF)? else {
+        bail!("No stash entries found.");
+    };
+    let commit = read_commit(repo, &stash)?;
+    let Some(base) = commit.parents().first().map(|sha| sha.to_string()) else {
+        bail!("'{}' is not a stash-like commit", stash);
+    };
+    let base_tree = read_commit(repo, &base)?.tree()?.to_string();
+
+    let index = read_index(repo)?;
+    let ours_tree = index.write_tree(repo)?;
+    let result = merge_trees(repo, Some(&base_tree), &ours_tree, commit.tree()?, "Updated upstream", "Stashed changes")?;
+    apply_merge(repo, &result)?;
+    for line in &result.messages {
+        println!("{}", line);
+    }
+    if !result.conflicts.is_empty() {
+        return Ok(false);
+    }
+
+    let before: HashMap<&str, &GitIndexEntry> = index.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+    let mut restored = GitIndex::read(repo)?;
+    let merged: Vec<String> = restored.entries.iter().map(|entry| entry.name.clone()).collect();
+    for entry in &mut restored.entries {
+        if let Some(previous) = before.get(entry.name.as_str()) {
+            *entry = (*previous).clone();
+        }
+    }
+    restored.entries.extend(index.entries.iter().filter(|entry| !merged.contains(&entry.name)).cloned());
+    restored.write(repo)?;
+    Ok(true)
+}
+
+fn list(repo: &Repository) -> Result<()> {
+    for (n, entry) in read_reflog(repo, STASH_REF)?.iter().rev().enumerate() {
+        println!("stash@{{{}}}: {}", n, entry.message);
+    }
+    Ok(())
+}
+
+fn drop_stash(repo: &Repository) -> Result<()> {
+    let Some(entry) = pop_reflog(repo, STASH_REF)? else {
+        bail!("No stash entries found.");
+    };
+    println!("Dropped refs/stash@{{0}} ({})", entry.new);
+    Ok(())
+}
+
+pub fn stash(args: &[String]) -> Result<()> {
+    let repo = open_repo()?;
+    match args.first().map(|arg| arg.as_str()) {
+        None | Some("push") => push(&repo),
+        Some("list") => list(&repo),
+        Some("drop") => drop_stash(&repo),
+        Some("apply") => {
+            if !apply(&repo)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some("pop") => {
+            if !apply(&repo)? {
+                println!("The stash entry is kept in case you need it again.");
+                std::process::exit(1);
+            }
+            drop_stash(&repo)
+        }
+        Some(other) => bail!("unknown subcommand: {}\nusage: git_rs stash [push | list | apply | pop | drop]", other),
+    }
+}
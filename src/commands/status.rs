@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use anyhow::{bail, Result};
+use git_rs::diff::{compare_entries, detect_renames, index_entries, tree_entries, FileChange};
+use git_rs::ignore::IgnoreRules;
+use git_rs::index::GitIndex;
+use git_rs::object::{read_blob, read_commit};
+use git_rs::repository::{HeadState, Repository};
+use git_rs::utils::{display_path, path_from_os, quote_path};
+use git_rs::worktree::is_modified;
+
+use super::{open_repo, parse_rename_option};
+
+
+fn is_tracked_dir(tracked: &BTreeSet<&str>, dir: &str) -> bool {
+    let prefix = format!("{}/", dir);
+    tracked.range(prefix.as_str()..).next().is_some_and(|path| path.starts_with(&prefix))
+}
+
+fn has_untracked_files(repo: &Repository, dir: &str, ignore: &mut IgnoreRules) -> Result<bool> {
+    for entry in fs::read_dir(repo.worktree_file(dir))? {
+        let entry = entry?;
+        let path = format!("{}/{}", dir, path_from_os(&entry.file_name()));
+        let is_dir = entry.file_type()?.is_dir();
+        if ignore.is_ignored(&path, is_dir) {
+            continue;
+        }
+        if !is_dir || has_untracked_files(repo, &path, ignore)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Collects untracked paths, collapsing directories without any tracked
+// files into a single `dir/` entry like git does.
+fn untracked_files(repo: &Repository, dir: &str, tracked: &BTreeSet<&str>, ignore: &mut IgnoreRules, out: &mut Vec<String>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(repo.worktree_file(dir))?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = path_from_os(&entry.file_name());
+        if name == ".git" {
+            continue;
+        }
+        let path = if dir.is_empty() { name } else { format!("{}/{}", dir, name) };
+        let is_dir = entry.file_type()?.is_dir();
+        if ignore.is_ignored(&path, is_dir) {
+            continue;
+        }
+
+        if !is_dir {
+            if !tracked.contains(path.as_str()) {
+                out.push(path);
+            }
+        } else if is_tracked_dir(tracked, &path) {
+            untracked_files(repo, &path, tracked, ignore, out)?;
+        } else if has_untracked_files(repo, &path, ignore)? {
+            out.push(format!("{}/", path));
+        }
+    }
+    Ok(())
+}
+
+// The single-letter code porcelain output uses for a staged change.
+fn staged_code(change: &FileChange) -> char {
+    match (&change.old, &change.new) {
+        _ if change.is_rename() => 'R',
+        (None, _) => 'A',
+        (_, None) => 'D',
+        _ => 'M',
+    }
+}
+
+// The XY code of a path with conflict stages, from which sides have it.
+fn unmerged_code(stages: &BTreeSet<u8>) -> &'static str {
+    match (stages.contains(&1), stages.contains(&2), stages.contains(&3)) {
+        (true, true, true) => "UU",
+        (false, true, true) => "AA",
+        (true, true, false) => "UD",
+        (true, false, true) => "DU",
+        (false, true, false) => "AU",
+        (false, false, true) => "UA",
+        _ => "DD",
+    }
+}
+
+// `--porcelain` prints one `XY path` line per changed path, X for the index
+// against HEAD and Y for the worktree against the index, followed by the
+// untracked files. Unlike the human output its format never changes.
+fn print_porcelain(index: &GitIndex, staged: &[FileChange], unstaged: &[(char, &str)], untracked: &[String]) {
+    let mut lines: BTreeMap<&str, (String, String)> = BTreeMap::new();
+    for change in staged {
+        let (path, shown) = match &change.new {
+            _ if change.is_rename() => (change.new_path.as_str(), format!("{} -> {}", quote_path(&change.old_path, true), quote_path(&change.new_path, true))),
+            None => (change.old_path.as_str(), quote_path(&change.old_path, true)),
+            Some(_) => (change.new_path.as_str(), quote_path(&change.new_path, true)),
+        };
+        lines.insert(path, (format!("{} ", staged_code(change)), shown));
+    }
+    for (code, path) in unstaged {
+        let line = lines.entry(path).or_insert_with(|| (" ".to_string(), quote_path(path, true)));
+        line.0.truncate(1);
+        line.0.push(*code);
+    }
+
+    let mut conflicts: BTreeMap<&str, BTreeSet<u8>> = BTreeMap::new();
+    for entry in index.entries.iter().filter(|entry| entry.stage != 0) {
+        conflicts.entry(&entry.name).or_default().insert(entry.stage);
+    }
+    for (path, stages) in conflicts {
+        lines.insert(path, (unmerged_code(&stages).to_string(), quote_path(path, true)));
+    }
+
+    for (code, shown) in lines.values() {
+        println!("{:<2} {}", code, shown);
+    }
+    for path in untracked {
+        println!("?? {}", quote_path(path, true));
+    }
+}
+
+fn describe(change: &FileChange) -> String {
+    let (label, path) = match (&change.old, &change.new) {
+        _ if change.is_rename() => ("renamed:", format!("{} -> {}", change.old_path, change.new_path)),
+        (None, _) => ("new file:", change.new_path.clone()),
+        (_, None) => ("deleted:", change.old_path.clone()),
+        _ => ("modified:", change.new_path.clone()),
+    };
+    format!("\t{:<12}{}", label, display_path(&path))
+}
+
+pub fn status(args: &[String]) -> Result<()> {
+    let mut renames = Some(50);
+    let mut porcelain = false;
+    for arg in args {
+        match arg.as_str() {
+            "--porcelain" | "--porcelain=v1" => porcelain = true,
+            _ if arg.starts_with("--porcelain=") => bail!("unsupported porcelain version '{}'", &arg["--porcelain=".len()..]),
+            _ if parse_rename_option(arg, &mut renames)? => {}
+            _ => bail!("unknown option '{}'", arg),
+        }
+    }
+
+    let repo = open_repo()?;
+    let index = GitIndex::read(&repo)?;
+    let state = repo.head()?;
+    let head = state.sha();
+
+    let head_entries = match head {
+        Some(sha) => tree_entries(&repo, read_commit(&repo, sha)?.tree()?)?,
+        None => BTreeMap::new(),
+    };
+    let mut staged = compare_entries(&head_entries, &index_entries(&index));
+    if let Some(threshold) = renames {
+        staged = detect_renames(staged, threshold, |sha| Ok(read_blob(&repo, sha)?.raw_data))?;
+    }
+
+    let mut unstaged = Vec::new();
+    for entry in index.entries.iter().filter(|entry| entry.stage == 0) {
+        if fs::symlink_metadata(repo.worktree_file(&entry.name)).is_err() {
+            unstaged.push(('D', entry.name.as_str()));
+        } else if is_modified(&repo, entry)? {
+            unstaged.push(('M', entry.name.as_str()));
+        }
+    }
+
+    let tracked: BTreeSet<&str> = index.entries.iter().map(|entry| entry.name.as_str()).collect();
+    let mut untracked = Vec::new();
+    untracked_files(&repo, "", &tracked, &mut IgnoreRules::new(&repo), &mut untracked)?;
+
+    if porcelain {
+        print_porcelain(&index, &staged, &unstaged, &untracked);
+        return Ok(());
+    }
+
+    match &state {
+        HeadState::Detached(sha) => println!("HEAD detached at {}", &sha[..7]),
+        HeadState::Unborn { name } | HeadState::Branch { name, .. } => println!("On branch {}", name.strip_prefix("refs/heads/").unwrap_or(name)),
+    }
+    if head.is_none() {
+        println!("\nNo commits yet\n");
+    }
+
+    let mut sections = Vec::new();
+    if !staged.is_empty() {
+        sections.push(format!("Changes to be committed:\n{}", staged.iter().map(describe).collect::<Vec<_>>().join("\n")));
+    }
+    if !unstaged.is_empty() {
+        let lines: Vec<String> = unstaged
+            .iter()
+            .map(|(code, path)| format!("\t{:<12}{}", if *code == 'D' { "deleted:" } else { "modified:" }, display_path(path)))
+            .collect();
+        sections.push(format!("Changes not staged for commit:\n{}", lines.join("\n")));
+    }
+    if !untracked.is_empty() {
+        sections.push(format!("Untracked files:\n{}", untracked.iter().map(|path| format!("\t{}", display_path(path))).collect::<Vec<_>>().join("\n")));
+    }
+
+    if sections.is_empty() {
+        println!("nothing to commit, working tree clean");
+    } else {
+        println!("{}", sections.join("\n\n"));
+    }
+    Ok(())
+}
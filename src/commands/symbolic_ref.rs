@@ -0,0 +1,29 @@
+use anyhow::{bail, Result};
+use git_rs::refs::{read_symbolic_ref, write_symbolic_ref};
+
+use super::open_repo;
+
+
+pub fn symbolic_ref(args: &[String]) -> Result<()> {
+    let short = args.iter().any(|arg| arg == "--short");
+    let args: Vec<&String> = args.iter().filter(|arg| *arg != "--short").collect();
+
+    let repo = open_repo()?;
+    match args[..] {
+        [name] => {
+            let Some(target) = read_symbolic_ref(&repo, name)? else {
+                bail!("ref {} is not a symbolic ref", name);
+            };
+            let shown = if short { target.strip_prefix("refs/heads/").unwrap_or(&target) } else { &target };
+            println!("{}", shown);
+        }
+        [name, target] => {
+            if name == "HEAD" && !target.starts_with("refs/") {
+                bail!("Refusing to point HEAD outside of refs/");
+            }
+            write_symbolic_ref(&repo, name, target)?;
+        }
+        _ => bail!("usage: git_rs symbolic-ref [--short] <name> [<ref>]"),
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, bail, Result};
+use git_rs::ignore::wildmatch;
+use git_rs::object::{read_raw, GitCommit, GitObject, GitTag, ObjectType, Signature};
+use git_rs::refs::{delete_ref, find_object, is_valid_ref_name, list_refs, resolve_ref, update_ref};
+use git_rs::repository::Repository;
+
+use super::open_repo;
+use super::verify_tag::verify_tags;
+
+
+const USAGE: &str = "usage: git_rs tag [-a] [-f] [-m <msg>] <tagname> [<object>]\n   or: git_rs tag -d <tagname>...\n   or: git_rs tag -v <tagname>...\n   or: git_rs tag [-n[<num>]] -l [--sort=<key>] [<pattern>...]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    RefName,
+    CreatorDate,
+}
+
+fn delete_tags(repo: &Repository, names: &[&String]) -> Result<()> {
+    for name in names {
+        let refname = format!("refs/tags/{}", name);
+        let Some(sha) = resolve_ref(repo, &refname)? else {
+            bail!("tag '{}' not found.", name);
+        };
+        delete_ref(repo, &refname)?;
+        println!("Deleted tag '{}' (was {})", name, &sha[..7]);
+    }
+    Ok(())
+}
+
+fn parse_sort(key: &str) -> Result<(SortKey, bool)> {
+    let (key, reverse) = match key.strip_prefix('-') {
+        Some(key) => (key, true),
+        None => (key, false),
+    };
+    match key {
+        "refname" => Ok((SortKey::RefName, reverse)),
+        "creatordate" => Ok((SortKey::CreatorDate, reverse)),
+        _ => bail!("unsupported sort specification '{}'", key),
+    }
+}
+
+// The creation time and message of what a tag points at: the tagger and
+// message of an annotated tag, or the committer and message of a commit.
+fn tag_details(repo: &Repository, sha: &str) -> Result<(i64, String)> {
+    let (type_, raw) = read_raw(repo, sha)?;
+    match type_ {
+        ObjectType::Tag => {
+            let tag = GitTag::deserialize(&raw)?;
+            let message = match tag.signature() {
+                Some((_, signature)) => tag.kvlm.message.strip_suffix(&signature).unwrap_or(&tag.kvlm.message).to_string(),
+                None => tag.kvlm.message.clone(),
+            };
+            Ok((tag.tagger().map(|tagger| tagger.time).unwrap_or(0), message))
+        }
+        ObjectType::Commit => {
+            let commit = GitCommit::deserialize(&raw)?;
+            Ok((commit.committer()?.time, commit.kvlm.message))
+        }
+        _ => Ok((0, String::new())),
+    }
+}
+
+// Lists the tags matching any of `patterns` (all of them without any), with
+// the first `lines` lines of each tag's message when asked for.
+fn list_tags(repo: &Repository, patterns: &[&String], sort: (SortKey, bool), lines: Option<usize>) -> Result<()> {
+    let mut tags = Vec::new();
+    for (refname, sha) in list_refs(repo)? {
+        let Some(name) = refname.strip_prefix("refs/tags/") else {
+            continue;
+        };
+        if !patterns.is_empty() && !patterns.iter().any(|pattern| wildmatch(pattern, name, false)) {
+            continue;
+        }
+        let (date, message) = if sort.0 == SortKey::CreatorDate || lines.is_some() { tag_details(repo, &sha)? } else { (0, String::new()) };
+        tags.push((name.to_string(), date, message));
+    }
+
+    // Ties in date fall back to the name, which list_refs already sorts by.
+    if sort.0 == SortKey::CreatorDate {
+        tags.sort_by_key(|(_, date, _)| *date);
+    }
+    if sort.1 {
+        tags.reverse();
+    }
+
+    for (name, _, message) in tags {
+        match lines {
+            Some(lines) => println!("{:<15} {}", name, message.lines().take(lines).collect::<Vec<_>>().join("\n    ")),
+            None => println!("{}", name),
+        }
+    }
+    Ok(())
+}
+
+pub fn tag(args: &[String]) -> Result<()> {
+    let mut delete = false;
+    let mut verify = false;
+    let mut annotate = false;
+    let mut force = false;
+    let mut list = false;
+    let mut sort = (SortKey::RefName, false);
+    let mut lines = None;
+    let mut messages: Vec<String> = Vec::new();
+    let mut names = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-d" | "--delete" => delete = true,
+            "-v" | "--verify" => verify = true,
+            "-a" | "--annotate" => annotate = true,
+            "-f" | "--force" => force = true,
+            "-l" | "--list" => list = true,
+            "-m" => messages.push(args.next().ok_or_else(|| anyhow!("option -m requires a value"))?.clone()),
+            _ if arg.starts_with("--sort=") => sort = parse_sort(&arg["--sort=".len()..])?,
+            _ if arg.starts_with("-n") => {
+                let count = &arg[2..];
+                lines = Some(if count.is_empty() { 1 } else { count.parse().map_err(|_| anyhow!("option `n' expects a numerical value"))? });
+            }
+            _ if arg.starts_with('-') => bail!("unknown option '{}'\n{}", arg, USAGE),
+            _ => names.push(arg),
+        }
+    }
+
+    let repo = open_repo()?;
+    if delete || verify {
+        if names.is_empty() || (delete && verify) {
+            bail!(USAGE);
+        }
+        return if delete { delete_tags(&repo, &names) } else { verify_tags(&repo, &names, true) };
+    }
+
+    // Like git, -n on its own also means listing.
+    if list || lines.is_some() || (names.is_empty() && !annotate && messages.is_empty()) {
+        if annotate || force || !messages.is_empty() {
+            bail!(USAGE);
+        }
+        return list_tags(&repo, &names, sort, lines);
+    }
+
+    if sort != (SortKey::RefName, false) {
+        bail!("--sort is only allowed in list mode");
+    }
+    let (name, target) = match names[..] {
+        [name] => (name, "HEAD"),
+        [name, target] => (name, target.as_str()),
+        _ => bail!(USAGE),
+    };
+    if !is_valid_ref_name(name) {
+        bail!("'{}' is not a valid tag name.", name);
+    }
+    let refname = format!("refs/tags/{}", name);
+    if !force && resolve_ref(&repo, &refname)?.is_some() {
+        bail!("tag '{}' already exists", name);
+    }
+
+    let sha = find_object(&repo, target, None)?;
+    if !annotate && messages.is_empty() {
+        return update_ref(&repo, &refname, &sha, "");
+    }
+    if messages.is_empty() {
+        bail!("no tag message given, use -m <msg>");
+    }
+    let message = messages.iter().map(|m| format!("{}\n", m.trim_end_matches('\n'))).collect::<Vec<_>>().join("\n");
+    let (type_, _) = read_raw(&repo, &sha)?;
+    let tagger = Signature::identity(&repo, "COMMITTER")?;
+    let tag_sha = GitTag::build(&sha, type_, name, &tagger, &message).write(&repo)?;
+    update_ref(&repo, &refname, &tag_sha, "")
+}
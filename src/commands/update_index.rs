@@ -0,0 +1,87 @@
+use std::fs;
+
+use anyhow::{anyhow, bail, Result};
+use git_rs::index::{GitIndex, GitIndexEntry};
+use git_rs::repository::Repository;
+use git_rs::object::FileMode;
+use git_rs::worktree::{is_modified, stage_file};
+
+use super::open_repo;
+
+
+fn cacheinfo(repo: &Repository, index: &mut GitIndex, mode: &str, sha: &str, path: &str) -> Result<()> {
+    let mode: FileMode = mode.parse()?;
+    if !repo.object_exists(sha) {
+        bail!("invalid object {} for '{}'", sha, path);
+    }
+    let path = repo.worktree_path(path)?;
+    index.add(GitIndexEntry::new(path, mode, sha.to_lowercase(), None));
+    Ok(())
+}
+
+// Re-stats every tracked file so entries whose content is unchanged no longer
+// look modified, reporting the ones that really changed.
+fn refresh(repo: &Repository, index: &mut GitIndex) -> Result<()> {
+    for i in 0..index.entries.len() {
+        let entry = &index.entries[i];
+        let Ok(metadata) = fs::symlink_metadata(repo.worktree_file(&entry.name)) else {
+            println!("{}: needs update", entry.name);
+            continue;
+        };
+        if entry.stat_matches(&metadata) {
+            continue;
+        }
+        if is_modified(repo, entry)? {
+            println!("{}: needs update", entry.name);
+        } else {
+            index.entries[i].update_stat(&metadata);
+        }
+    }
+    Ok(())
+}
+
+pub fn update_index(args: &[String]) -> Result<()> {
+    let repo = open_repo()?;
+    let mut index = GitIndex::read(&repo)?;
+    let mut allow_add = false;
+    let mut allow_remove = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--add" => allow_add = true,
+            "--remove" => allow_remove = true,
+            "--refresh" => refresh(&repo, &mut index)?,
+            "--cacheinfo" => {
+                let value = args.next().ok_or_else(|| anyhow!("option 'cacheinfo' expects <mode>,<sha1>,<path>"))?;
+                match value.splitn(3, ',').collect::<Vec<_>>()[..] {
+                    [mode, sha, path] => cacheinfo(&repo, &mut index, mode, sha, path)?,
+                    _ => {
+                        let (Some(sha), Some(path)) = (args.next(), args.next()) else {
+                            bail!("option 'cacheinfo' expects <mode>,<sha1>,<path>");
+                        };
+                        cacheinfo(&repo, &mut index, value, sha, path)?;
+                    }
+                }
+            }
+            flag if flag.starts_with("--") => bail!("unknown option '{}'", flag),
+            path => {
+                let path = repo.worktree_path(path)?;
+                let exists = fs::symlink_metadata(repo.worktree_file(&path)).is_ok();
+                if allow_remove && (!exists || !allow_add) {
+                    if !index.remove(&path) {
+                        bail!("{}: not in the index", path);
+                    }
+                } else if !exists {
+                    bail!("{}: does not exist and --remove not passed", path);
+                } else if index.get(&path).is_none() && !allow_add {
+                    bail!("{}: cannot add to the index - missing --add option?", path);
+                } else {
+                    index.add(stage_file(&repo, &path)?);
+                }
+            }
+        }
+    }
+
+    index.write(&repo)
+}
@@ -0,0 +1,36 @@
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use git_rs::gpg::verify_signature;
+use git_rs::object::{read_commit, ObjectType};
+use git_rs::refs::find_object;
+
+use super::open_repo;
+
+
+pub fn verify_commit(args: &[String]) -> Result<()> {
+    let verbose = args.iter().any(|arg| arg == "-v" || arg == "--verbose");
+    let revs: Vec<&String> = args.iter().filter(|arg| *arg != "-v" && *arg != "--verbose").collect();
+    if revs.is_empty() {
+        bail!("usage: git_rs verify-commit [-v | --verbose] <commit>...");
+    }
+
+    let repo = open_repo()?;
+    let mut failed = Vec::new();
+    for rev in revs {
+        let sha = find_object(&repo, rev, Some(ObjectType::Commit))?;
+        let Some((payload, signature)) = read_commit(&repo, &sha)?.signature() else {
+            bail!("{}: no signature found", rev);
+        };
+        if verbose {
+            std::io::stdout().write_all(&payload)?;
+        }
+        if !verify_signature(&repo, &payload, &signature)? {
+            failed.push(rev.as_str());
+        }
+    }
+    if !failed.is_empty() {
+        bail!("could not verify the signature of {}", failed.join(", "));
+    }
+    Ok(())
+}
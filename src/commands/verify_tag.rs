@@ -0,0 +1,44 @@
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use git_rs::gpg::verify_signature;
+use git_rs::object::{read_raw, GitTag, ObjectType};
+use git_rs::refs::find_object;
+use git_rs::repository::Repository;
+
+use super::open_repo;
+
+
+// Shared with `tag -v`, which always prints the tag.
+pub fn verify_tags(repo: &Repository, names: &[&String], verbose: bool) -> Result<()> {
+    let mut failed = Vec::new();
+    for name in names {
+        let sha = find_object(repo, name, None)?;
+        let (type_, data) = read_raw(repo, &sha)?;
+        if type_ != ObjectType::Tag {
+            bail!("{}: cannot verify a non-tag object of type {}.", name, type_);
+        }
+        let Some((payload, signature)) = GitTag::deserialize(&data)?.signature() else {
+            bail!("{}: no signature found", name);
+        };
+        if verbose {
+            std::io::stdout().write_all(&payload)?;
+        }
+        if !verify_signature(repo, &payload, &signature)? {
+            failed.push(name.as_str());
+        }
+    }
+    if !failed.is_empty() {
+        bail!("could not verify the signature of {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+pub fn verify_tag(args: &[String]) -> Result<()> {
+    let verbose = args.iter().any(|arg| arg == "-v" || arg == "--verbose");
+    let names: Vec<&String> = args.iter().filter(|arg| *arg != "-v" && *arg != "--verbose").collect();
+    if names.is_empty() {
+        bail!("usage: git_rs verify-tag [-v | --verbose] <tag>...");
+    }
+    verify_tags(&open_repo()?, &names, verbose)
+}
@@ -0,0 +1,14 @@
+use anyhow::{bail, Result};
+use git_rs::index::GitIndex;
+
+use super::open_repo;
+
+
+pub fn write_tree(args: &[String]) -> Result<()> {
+    if !args.is_empty() {
+        bail!("usage: git_rs write-tree");
+    }
+    let repo = open_repo()?;
+    println!("{}", GitIndex::read(&repo)?.write_tree(&repo)?);
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::attributes::Attributes;
+use crate::repository::Repository;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoCrlf {
+    True,
+    Input,
+    False,
+}
+
+impl AutoCrlf {
+    pub fn from_config(repo: &Repository) -> AutoCrlf {
+        match repo.config_get("core", "autocrlf").map(|v| v.to_lowercase()).as_deref() {
+            Some("true") | Some("yes") | Some("on") | Some("1") => AutoCrlf::True,
+            Some("input") => AutoCrlf::Input,
+            _ => AutoCrlf::False,
+        }
+    }
+}
+
+
+// Git treats anything containing a NUL byte as binary and never converts it.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.contains(&0)
+}
+
+// Describes the line endings of some content the way `ls-files --eol` does.
+pub fn eol_info(data: &[u8]) -> &'static str {
+    let crlf = data.windows(2).filter(|pair| pair == b"\r\n").count();
+    let lone_cr = data.iter().filter(|&&b| b == b'\r').count() - crlf;
+    let lone_lf = data.iter().filter(|&&b| b == b'\n').count() - crlf;
+    if is_binary(data) || lone_cr > 0 {
+        "-text"
+    } else if crlf > 0 && lone_lf > 0 {
+        "mixed"
+    } else if crlf > 0 {
+        "crlf"
+    } else if lone_lf > 0 {
+        "lf"
+    } else {
+        "none"
+    }
+}
+
+pub fn crlf_to_lf(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\r' && data.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        out.push(b);
+    }
+    out
+}
+
+pub fn lf_to_crlf(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            out.push(b'\r');
+        }
+        out.push(b);
+    }
+    out
+}
+
+// Quotes a path for substitution into a shell command.
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', "'\\''"))
+}
+
+// Runs a configured filter command through the shell, streaming `data` into
+// its stdin from a separate thread so large outputs can't deadlock it.
+pub fn run_filter(command: &str, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let command = command.replace("%f", &shell_quote(path));
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("cannot run external filter '{}'", command))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut output = Vec::new();
+    std::thread::scope(|scope| {
+        // The filter may exit without reading everything, so write errors
+        // are left for the exit status to report.
+        scope.spawn(move || {
+            let _ = stdin.write_all(data);
+        });
+        child.stdout.take().unwrap().read_to_end(&mut output)
+    })?;
+    if !child.wait()?.success() {
+        bail!("external filter '{}' failed", command);
+    }
+    Ok(output)
+}
+
+// Applies the `clean` or `smudge` command of the filter driver named by the
+// path's `filter` attribute. A failing filter is only fatal when the driver
+// is marked `required`; otherwise the content passes through unchanged.
+fn apply_filter(repo: &Repository, path: &str, data: Vec<u8>, kind: &str) -> Result<Vec<u8>> {
+    let Some(driver) = Attributes::new(repo).value(path, "filter") else {
+        return Ok(data);
+    };
+    let section = format!("filter \"{}\"", driver);
+    let Some(command) = repo.config_get(&section, kind) else {
+        return Ok(data);
+    };
+    let required = repo.config_get(&section, "required").is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    match run_filter(&command, path, &data) {
+        Ok(filtered) => Ok(filtered),
+        Err(e) if required => Err(e.context(format!("{}: {} filter '{}' failed", path, kind, driver))),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            Ok(data)
+        }
+    }
+}
+
+// Converts worktree content into the form stored in a blob.
+pub fn to_git(repo: &Repository, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    let data = apply_filter(repo, path, data, "clean")?;
+    Ok(match AutoCrlf::from_config(repo) {
+        AutoCrlf::True | AutoCrlf::Input if !is_binary(&data) => crlf_to_lf(&data),
+        _ => data,
+    })
+}
+
+// Converts blob content into the form written to the worktree.
+pub fn to_worktree(repo: &Repository, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    let data = match AutoCrlf::from_config(repo) {
+        AutoCrlf::True if !is_binary(&data) => lf_to_crlf(&data),
+        _ => data,
+    };
+    apply_filter(repo, path, data, "smudge")
+}
+
+// Converts blob content for display with the `textconv` command of the diff
+// driver named by the path's `diff` attribute. Like git, the command gets
+// the content in a temporary file rather than on stdin.
+pub fn textconv(repo: &Repository, path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(driver) = Attributes::new(repo).value(path, "diff") else {
+        return Ok(data);
+    };
+    let Some(command) = repo.config_get(&format!("diff \"{}\"", driver), "textconv") else {
+        return Ok(data);
+    };
+
+    let file = std::env::temp_dir().join(format!("git_rs-textconv-{}", std::process::id()));
+    fs::write(&file, &data)?;
+    let result = run_filter(&format!("{} {}", command, shell_quote(&file.to_string_lossy())), path, &[]);
+    let _ = fs::remove_file(&file);
+    result
+}
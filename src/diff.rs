@@ -0,0 +1,543 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+
+use crate::convert::is_binary;
+use crate::index::GitIndex;
+use crate::object::{flatten_tree, hash_data, read_tree, FileMode, GitTreeLeaf, ObjectType};
+use crate::repository::Repository;
+use crate::worktree::{file_mode, read_worktree_file};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal { old: usize, new: usize },
+    Delete { old: usize },
+    Insert { new: usize },
+}
+
+
+// Splits content into lines, keeping the trailing newline on each line so
+// that a missing newline at end of file still counts as a change.
+pub fn split_lines(data: &[u8]) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\n' {
+            lines.push(&data[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < data.len() {
+        lines.push(&data[start..]);
+    }
+    lines
+}
+
+// Computes a shortest edit script turning `old` into `new` using Myers'
+// algorithm; the `Equal` edits form a longest common subsequence.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal { old: i, new: i }).collect();
+    for edit in myers(a, b) {
+        edits.push(match edit {
+            Edit::Equal { old, new } => Edit::Equal { old: old + prefix, new: new + prefix },
+            Edit::Delete { old } => Edit::Delete { old: old + prefix },
+            Edit::Insert { new } => Edit::Insert { new: new + prefix },
+        });
+    }
+    let old_tail = old.len() - suffix;
+    let new_tail = new.len() - suffix;
+    edits.extend((0..suffix).map(|i| Edit::Equal { old: old_tail + i, new: new_tail + i }));
+    edits
+}
+
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // Walk the trace backwards to recover the path.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..trace.len() as isize).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let idx = (k + offset) as usize;
+        let prev_k = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) { k + 1 } else { k - 1 };
+        let prev_x = if d == 0 { 0 } else { v[(prev_k + offset) as usize] };
+        let prev_y = if d == 0 { 0 } else { prev_x - prev_k };
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal { old: x as usize, new: y as usize });
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert { new: prev_y as usize });
+            } else {
+                edits.push(Edit::Delete { old: prev_x as usize });
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(Vec<u8>),
+    Delete(Vec<u8>),
+    Insert(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    // 1-based line numbers; a zero count means the hunk only inserts or
+    // only deletes, in which case the start is the line before the change.
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub function: String,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    pub fn header(&self) -> String {
+        let range = |start: usize, count: usize| match count {
+            1 => format!("{}", start),
+            _ => format!("{},{}", start, count),
+        };
+        let header = format!("@@ -{} +{} @@", range(self.old_start, self.old_count), range(self.new_start, self.new_count));
+        if self.function.is_empty() {
+            header
+        } else {
+            format!("{} {}", header, self.function)
+        }
+    }
+}
+
+// Git's default hunk header context: the closest preceding line that starts
+// with a letter, `_` or `$`, cut to 80 bytes.
+fn function_line(lines: &[&[u8]], before: usize) -> String {
+    for line in lines[..before].iter().rev() {
+        if let Some(&first) = line.first() {
+            if first.is_ascii_alphabetic() || first == b'_' || first == b'$' {
+                let line = &line[..line.len().min(80)];
+                return String::from_utf8_lossy(line).trim_end().to_string();
+            }
+        }
+    }
+    String::new()
+}
+
+pub fn hunks(old: &[u8], new: &[u8], context: usize) -> Vec<Hunk> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let edits = diff(&old_lines, &new_lines);
+
+    // Line counts consumed before each edit.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for edit in &edits {
+        positions.push((o, n));
+        match edit {
+            Edit::Equal { .. } => {
+                o += 1;
+                n += 1;
+            }
+            Edit::Delete { .. } => o += 1,
+            Edit::Insert { .. } => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let changes: Vec<usize> = edits.iter().enumerate().filter(|(_, e)| !matches!(e, Edit::Equal { .. })).map(|(i, _)| i).collect();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1] - changes[j] - 1 <= 2 * context {
+            j += 1;
+        }
+        let start = changes[i].saturating_sub(context);
+        let end = (changes[j] + context + 1).min(edits.len());
+
+        let lines: Vec<HunkLine> = edits[start..end]
+            .iter()
+            .map(|edit| match *edit {
+                Edit::Equal { old, .. } => HunkLine::Context(old_lines[old].to_vec()),
+                Edit::Delete { old } => HunkLine::Delete(old_lines[old].to_vec()),
+                Edit::Insert { new } => HunkLine::Insert(new_lines[new].to_vec()),
+            })
+            .collect();
+
+        let (old_before, new_before) = positions[start];
+        let old_count = positions[end].0 - old_before;
+        let new_count = positions[end].1 - new_before;
+        result.push(Hunk {
+            old_start: if old_count == 0 { old_before } else { old_before + 1 },
+            old_count,
+            new_start: if new_count == 0 { new_before } else { new_before + 1 },
+            new_count,
+            function: function_line(&old_lines, old_before),
+            lines,
+        });
+        i = j + 1;
+    }
+    result
+}
+
+pub fn write_hunks(out: &mut dyn Write, hunks: &[Hunk]) -> io::Result<()> {
+    for hunk in hunks {
+        writeln!(out, "{}", hunk.header())?;
+        for line in &hunk.lines {
+            let (prefix, content) = match line {
+                HunkLine::Context(content) => (' ', content),
+                HunkLine::Delete(content) => ('-', content),
+                HunkLine::Insert(content) => ('+', content),
+            };
+            write!(out, "{}", prefix)?;
+            out.write_all(content)?;
+            if !content.ends_with(b"\n") {
+                write!(out, "\n\\ No newline at end of file\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub mode: FileMode,
+    pub sha: String,
+}
+
+// One changed path. Renames carry both paths and the similarity score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub old_path: String,
+    pub new_path: String,
+    pub old: Option<DiffEntry>,
+    pub new: Option<DiffEntry>,
+    pub similarity: Option<u32>,
+}
+
+impl FileChange {
+    pub fn is_rename(&self) -> bool {
+        self.similarity.is_some()
+    }
+}
+
+pub fn tree_entries(repo: &Repository, tree_sha: &str) -> anyhow::Result<BTreeMap<String, DiffEntry>> {
+    Ok(flatten_tree(repo, tree_sha)?
+        .into_iter()
+        .map(|leaf| (leaf.path, DiffEntry { mode: leaf.mode, sha: leaf.sha }))
+        .collect())
+}
+
+pub fn index_entries(index: &GitIndex) -> BTreeMap<String, DiffEntry> {
+    index
+        .entries
+        .iter()
+        .filter(|entry| entry.stage == 0)
+        .map(|entry| (entry.name.clone(), DiffEntry { mode: entry.mode, sha: entry.sha.clone() }))
+        .collect()
+}
+
+// The worktree side of the tracked files, hashed as they would be staged.
+// Files whose stat data matches the index reuse the index entry and missing
+// files are left out.
+pub fn worktree_entries(repo: &Repository, index: &GitIndex) -> anyhow::Result<BTreeMap<String, DiffEntry>> {
+    let mut entries = BTreeMap::new();
+    for entry in index.entries.iter().filter(|entry| entry.stage == 0) {
+        let Ok(metadata) = std::fs::symlink_metadata(repo.worktree_file(&entry.name)) else {
+            continue;
+        };
+        let (mode, sha) = if entry.mode == FileMode::Gitlink || entry.stat_matches(&metadata) {
+            (entry.mode, entry.sha.clone())
+        } else {
+            (file_mode(&metadata), hash_data(ObjectType::Blob, &read_worktree_file(repo, &entry.name)?))
+        };
+        entries.insert(entry.name.clone(), DiffEntry { mode, sha });
+    }
+    Ok(entries)
+}
+
+pub fn compare_entries(old: &BTreeMap<String, DiffEntry>, new: &BTreeMap<String, DiffEntry>) -> Vec<FileChange> {
+    let paths: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    paths
+        .into_iter()
+        .filter(|path| old.get(*path) != new.get(*path))
+        .map(|path| FileChange {
+            old_path: path.clone(),
+            new_path: path.clone(),
+            old: old.get(path).cloned(),
+            new: new.get(path).cloned(),
+            similarity: None,
+        })
+        .collect()
+}
+
+// One path that differs between two trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added { path: String, entry: DiffEntry },
+    Deleted { path: String, entry: DiffEntry },
+    // Content or mode changed; see `mode_changed`.
+    Modified { path: String, old: DiffEntry, new: DiffEntry },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Deleted { path, .. } | Change::Modified { path, .. } => path,
+        }
+    }
+
+    pub fn mode_changed(&self) -> bool {
+        matches!(self, Change::Modified { old, new, .. } if old.mode != new.mode)
+    }
+}
+
+impl From<Change> for FileChange {
+    fn from(change: Change) -> FileChange {
+        let (path, old, new) = match change {
+            Change::Added { path, entry } => (path, None, Some(entry)),
+            Change::Deleted { path, entry } => (path, Some(entry), None),
+            Change::Modified { path, old, new } => (path, Some(old), Some(new)),
+        };
+        FileChange { old_path: path.clone(), new_path: path, old, new, similarity: None }
+    }
+}
+
+// Every non-tree path that differs between two trees, sorted by path.
+// Subtrees with the same SHA on both sides are skipped without being read.
+pub fn diff_trees(repo: &Repository, old_tree: &str, new_tree: &str) -> anyhow::Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    diff_subtrees(repo, Some(old_tree), Some(new_tree), "", &mut changes)?;
+    changes.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(changes)
+}
+
+fn diff_subtrees(repo: &Repository, old: Option<&str>, new: Option<&str>, prefix: &str, changes: &mut Vec<Change>) -> anyhow::Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    let leaves = |tree: Option<&str>| -> anyhow::Result<BTreeMap<String, GitTreeLeaf>> {
+        Ok(match tree {
+            Some(sha) => read_tree(repo, sha)?.leaves.into_iter().map(|leaf| (leaf.path.clone(), leaf)).collect(),
+            None => BTreeMap::new(),
+        })
+    };
+    let old_leaves = leaves(old)?;
+    let new_leaves = leaves(new)?;
+    let names: BTreeSet<&String> = old_leaves.keys().chain(new_leaves.keys()).collect();
+
+    for name in names {
+        let path = format!("{}{}", prefix, name);
+        let old_leaf = old_leaves.get(name);
+        let new_leaf = new_leaves.get(name);
+        let old_tree = old_leaf.filter(|leaf| leaf.is_tree()).map(|leaf| leaf.sha.as_str());
+        let new_tree = new_leaf.filter(|leaf| leaf.is_tree()).map(|leaf| leaf.sha.as_str());
+        if old_tree.is_some() || new_tree.is_some() {
+            diff_subtrees(repo, old_tree, new_tree, &format!("{}/", path), changes)?;
+        }
+
+        let entry = |leaf: &GitTreeLeaf| DiffEntry { mode: leaf.mode, sha: leaf.sha.clone() };
+        let old_file = old_leaf.filter(|leaf| !leaf.is_tree()).map(entry);
+        let new_file = new_leaf.filter(|leaf| !leaf.is_tree()).map(entry);
+        match (old_file, new_file) {
+            (Some(old), Some(new)) if old != new => changes.push(Change::Modified { path, old, new }),
+            (Some(entry), None) => changes.push(Change::Deleted { path, entry }),
+            (None, Some(entry)) => changes.push(Change::Added { path, entry }),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Percentage of content shared by two versions, measured as the bytes of the
+// lines they have in common over the larger size. Like git's own estimate
+// this counts matching lines regardless of order, which keeps it linear in
+// the size of both versions.
+pub fn similarity(old: &[u8], new: &[u8]) -> u32 {
+    let larger = old.len().max(new.len());
+    if larger == 0 {
+        return 100;
+    }
+    let mut counts: HashMap<&[u8], usize> = HashMap::new();
+    for line in split_lines(old) {
+        *counts.entry(line).or_default() += 1;
+    }
+    let mut common = 0;
+    for line in split_lines(new) {
+        if let Some(count) = counts.get_mut(line).filter(|count| **count > 0) {
+            *count -= 1;
+            common += line.len();
+        }
+    }
+    (common * 100 / larger) as u32
+}
+
+// Beyond this many candidate pairs only exact renames are detected.
+const RENAME_LIMIT: usize = 1000 * 1000;
+
+// Blobs larger than this (git's default core.bigFileThreshold) are only
+// paired with identical content.
+const RENAME_SIZE_LIMIT: usize = 512 * 1024 * 1024;
+
+// Pairs deleted paths with added ones whose content is identical or at least
+// `threshold` percent similar, replacing each pair with a single rename.
+pub fn detect_renames<F>(changes: Vec<FileChange>, threshold: u32, mut load: F) -> anyhow::Result<Vec<FileChange>>
+where
+    F: FnMut(&str) -> anyhow::Result<Vec<u8>>,
+{
+    let (mut deleted, rest): (Vec<FileChange>, Vec<FileChange>) = changes.into_iter().partition(|c| c.new.is_none());
+    let (mut added, mut result): (Vec<FileChange>, Vec<FileChange>) = rest.into_iter().partition(|c| c.old.is_none());
+
+    let mut pairs: Vec<(u32, usize, usize)> = Vec::new();
+    for (d, del) in deleted.iter().enumerate() {
+        for (a, add) in added.iter().enumerate() {
+            if del.old.as_ref().map(|e| &e.sha) == add.new.as_ref().map(|e| &e.sha) {
+                pairs.push((100, d, a));
+            }
+        }
+    }
+
+    if threshold < 100 && deleted.len() * added.len() <= RENAME_LIMIT {
+        let mut contents: HashMap<String, Vec<u8>> = HashMap::new();
+        for change in deleted.iter().filter_map(|c| c.old.as_ref()).chain(added.iter().filter_map(|c| c.new.as_ref())) {
+            if !contents.contains_key(&change.sha) {
+                contents.insert(change.sha.clone(), load(&change.sha)?);
+            }
+        }
+        for (d, del) in deleted.iter().enumerate() {
+            for (a, add) in added.iter().enumerate() {
+                let old = &contents[&del.old.as_ref().unwrap().sha];
+                let new = &contents[&add.new.as_ref().unwrap().sha];
+                let (smaller, larger) = (old.len().min(new.len()), old.len().max(new.len()));
+                // Sizes this far apart can never reach the threshold.
+                if larger > RENAME_SIZE_LIMIT || (smaller as u64) * 100 < (larger as u64) * threshold as u64 {
+                    continue;
+                }
+                let score = similarity(old, new);
+                if score >= threshold && score < 100 {
+                    pairs.push((score, d, a));
+                }
+            }
+        }
+    }
+
+    pairs.sort_by(|x, y| y.0.cmp(&x.0).then(x.1.cmp(&y.1)).then(x.2.cmp(&y.2)));
+    let mut used_deleted = vec![false; deleted.len()];
+    let mut used_added = vec![false; added.len()];
+    for (score, d, a) in pairs {
+        if used_deleted[d] || used_added[a] {
+            continue;
+        }
+        used_deleted[d] = true;
+        used_added[a] = true;
+        result.push(FileChange {
+            old_path: deleted[d].old_path.clone(),
+            new_path: added[a].new_path.clone(),
+            old: deleted[d].old.take(),
+            new: added[a].new.take(),
+            similarity: Some(score),
+        });
+    }
+
+    result.extend(deleted.into_iter().zip(used_deleted).filter(|(_, used)| !used).map(|(c, _)| c));
+    result.extend(added.into_iter().zip(used_added).filter(|(_, used)| !used).map(|(c, _)| c));
+    result.sort_by(|a, b| a.new_path.cmp(&b.new_path));
+    Ok(result)
+}
+
+fn short(sha: Option<&DiffEntry>) -> &str {
+    sha.map(|e| &e.sha[..7]).unwrap_or("0000000")
+}
+
+// Writes a `diff --git` patch for one change given both sides' contents.
+pub fn write_patch(out: &mut dyn Write, change: &FileChange, old: &[u8], new: &[u8]) -> io::Result<()> {
+    writeln!(out, "diff --git a/{} b/{}", change.old_path, change.new_path)?;
+    let old_entry = change.old.as_ref();
+    let new_entry = change.new.as_ref();
+
+    let mut index_mode = None;
+    match (old_entry, new_entry) {
+        (None, Some(new_entry)) => writeln!(out, "new file mode {}", new_entry.mode)?,
+        (Some(old_entry), None) => writeln!(out, "deleted file mode {}", old_entry.mode)?,
+        (Some(old_entry), Some(new_entry)) => {
+            if old_entry.mode != new_entry.mode {
+                writeln!(out, "old mode {}", old_entry.mode)?;
+                writeln!(out, "new mode {}", new_entry.mode)?;
+            } else {
+                index_mode = Some(new_entry.mode);
+            }
+        }
+        (None, None) => {}
+    }
+    if let Some(score) = change.similarity {
+        writeln!(out, "similarity index {}%", score)?;
+        writeln!(out, "rename from {}", change.old_path)?;
+        writeln!(out, "rename to {}", change.new_path)?;
+    }
+    if old_entry.map(|e| &e.sha) == new_entry.map(|e| &e.sha) {
+        return Ok(());
+    }
+
+    match index_mode {
+        Some(mode) => writeln!(out, "index {}..{} {}", short(old_entry), short(new_entry), mode)?,
+        None => writeln!(out, "index {}..{}", short(old_entry), short(new_entry))?,
+    }
+
+    let old_name = if old_entry.is_some() { format!("a/{}", change.old_path) } else { "/dev/null".to_string() };
+    let new_name = if new_entry.is_some() { format!("b/{}", change.new_path) } else { "/dev/null".to_string() };
+    if is_binary(old) || is_binary(new) {
+        return writeln!(out, "Binary files {} and {} differ", old_name, new_name);
+    }
+    let hunks = hunks(old, new, 3);
+    if !hunks.is_empty() {
+        writeln!(out, "--- {}", old_name)?;
+        writeln!(out, "+++ {}", new_name)?;
+        write_hunks(out, &hunks)?;
+    }
+    Ok(())
+}
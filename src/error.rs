@@ -0,0 +1,71 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::object::{ObjectType, ObjectWriteError};
+
+
+// Errors from the core object and repository functions, so callers can tell
+// a missing object from a corrupt one without matching on message text.
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("Object {0} not found")]
+    ObjectNotFound(String),
+    // Writing an object that is already stored.
+    #[error("Object {0} already exists")]
+    ObjectExists(String),
+    // An object that exists but can't be parsed, as `<sha>: <reason>`, or
+    // just the reason where the parser wasn't given a SHA.
+    #[error("Malformed object {0}")]
+    Malformed(String),
+    // An object read as one type that turned out to be another.
+    #[error("Object {sha} is a {actual}, not a {expected}")]
+    WrongType { sha: String, expected: ObjectType, actual: ObjectType },
+    #[error("Ambiguous reference {name}: candidates are:\n - {}", candidates.join("\n - "))]
+    AmbiguousPrefix { name: String, candidates: Vec<String> },
+    // A revision that can't be resolved for a reason other than a missing
+    // object, such as a bad reflog index.
+    #[error("{0}")]
+    BadRevision(String),
+    #[error("not a git repository (or any of the parent directories): .git")]
+    NotARepository,
+    #[error("{0}")]
+    UnsupportedFormat(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    // Anything else a lower layer failed with, kept as it was.
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<ObjectWriteError> for GitError {
+    fn from(e: ObjectWriteError) -> GitError {
+        match e {
+            ObjectWriteError::AlreadyExists(sha) => GitError::ObjectExists(sha),
+            ObjectWriteError::Io { path, source } => GitError::Io(io::Error::new(source.kind(), format!("Failed to write object file {:?}: {}", path, source))),
+        }
+    }
+}
+
+impl GitError {
+    // Names the object a parser failed on, which the parser itself can't.
+    pub(crate) fn in_object(self, sha: &str) -> GitError {
+        match self {
+            GitError::Malformed(reason) => GitError::Malformed(format!("{}: {}", sha, reason)),
+            e => e,
+        }
+    }
+}
+
+// Recovers the GitError or I/O error behind a failure passed up through
+// anyhow, keeping any other error as it is.
+pub(crate) fn from_anyhow(e: anyhow::Error) -> GitError {
+    let e = match e.downcast::<GitError>() {
+        Ok(e) => return e,
+        Err(e) => e,
+    };
+    match e.downcast::<io::Error>() {
+        Ok(e) => GitError::Io(e),
+        Err(e) => GitError::Other(e),
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::repository::Repository;
+
+
+// The external program used for signatures, from gpg.program.
+fn program(repo: &Repository) -> String {
+    repo.config_get("gpg", "program").unwrap_or_else(|| "gpg".to_string())
+}
+
+// Hands a detached signature and the payload it signs to the configured
+// verifier, the way git does: the signature goes through a temporary file
+// and the payload through stdin. Returns whether the verifier accepted it.
+pub fn verify_signature(repo: &Repository, payload: &[u8], signature: &str) -> Result<bool> {
+    let signature_file = std::env::temp_dir().join(format!("git_rs-signature-{}", std::process::id()));
+    fs::write(&signature_file, signature)?;
+
+    let program = program(repo);
+    let child = Command::new(&program)
+        .arg("--status-fd=1")
+        .arg("--verify")
+        .arg(&signature_file)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", program));
+    let result = child.and_then(|mut child| {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(payload)?;
+        }
+        Ok(child.wait_with_output()?)
+    });
+    let _ = fs::remove_file(&signature_file);
+
+    let output = result?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    Ok(output.status.success() && status.contains("[GNUPG:] GOODSIG"))
+}
+
+// Produces a detached, armored signature over `payload` with the configured
+// program, invoked like git does (`--status-fd=2 -bsau <key>`). The program
+// must report SIG_CREATED for the signature to be accepted.
+pub fn sign_payload(repo: &Repository, payload: &[u8], key: &str) -> Result<String> {
+    let program = program(repo);
+    let mut child = Command::new(&program)
+        .arg("--status-fd=2")
+        .arg("-bsau")
+        .arg(key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload)?;
+    }
+    let output = child.wait_with_output()?;
+    let status = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !status.contains("[GNUPG:] SIG_CREATED ") {
+        bail!("gpg failed to sign the data");
+    }
+    String::from_utf8(output.stdout).context("gpg produced a signature that is not valid UTF-8")
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::repository::Repository;
+
+
+// Matches `text` against a shell glob. With `pathname` set, `*` and `?` do
+// not cross `/` and `**` matches any number of directories, as in gitignore.
+pub fn wildmatch(pattern: &str, text: &str, pathname: bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text, pathname)
+}
+
+fn match_from(p: &[char], t: &[char], pathname: bool) -> bool {
+    let (Some(&first), rest) = (p.first(), p.get(1..).unwrap_or(&[])) else {
+        return t.is_empty();
+    };
+
+    match first {
+        '*' if pathname && rest.first() == Some(&'*') => {
+            let after = &rest[1..];
+            // "**/" also matches zero directories.
+            if after.first() == Some(&'/') && match_from(&after[1..], t, pathname) {
+                return true;
+            }
+            (0..=t.len()).any(|i| match_from(after, &t[i..], pathname))
+        }
+        '*' => {
+            for i in 0..=t.len() {
+                if match_from(rest, &t[i..], pathname) {
+                    return true;
+                }
+                if pathname && t.get(i) == Some(&'/') {
+                    return false;
+                }
+            }
+            false
+        }
+        '?' => match t.first() {
+            Some('/') if pathname => false,
+            Some(_) => match_from(rest, &t[1..], pathname),
+            None => false,
+        },
+        '[' => {
+            let Some(&c) = t.first() else {
+                return false;
+            };
+            let Some(close) = rest.iter().skip(1).position(|&ch| ch == ']').map(|i| i + 1) else {
+                return c == '[' && match_from(rest, &t[1..], pathname);
+            };
+            let (negated, class) = match rest[0] {
+                '!' | '^' => (true, &rest[1..close]),
+                _ => (false, &rest[..close]),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && !(pathname && c == '/') && match_from(&rest[close + 1..], &t[1..], pathname)
+        }
+        '\\' if !rest.is_empty() => t.first() == Some(&rest[0]) && match_from(&rest[1..], &t[1..], pathname),
+        _ => t.first() == Some(&first) && match_from(rest, &t[1..], pathname),
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line = if line.ends_with("\\ ") { line } else { line.trim_end() };
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        Some(Rule { pattern: line.trim_start_matches('/').to_string(), negated, dir_only, anchored })
+    }
+
+    // `path` is relative to the directory holding the rule.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            wildmatch(&self.pattern, path, true)
+        } else {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            wildmatch(&self.pattern, name, true)
+        }
+    }
+}
+
+
+// Gitignore rules for a worktree, read lazily per directory.
+pub struct IgnoreRules<'a> {
+    repo: &'a Repository,
+    global: Vec<Rule>,
+    per_dir: HashMap<String, Vec<Rule>>,
+}
+
+impl<'a> IgnoreRules<'a> {
+    pub fn new(repo: &'a Repository) -> IgnoreRules<'a> {
+        let mut global = Vec::new();
+        let mut sources = vec![repo.repo_path("info").join("exclude")];
+        if let Some(file) = repo.config_get("core", "excludesfile") {
+            let home = std::env::var("HOME").unwrap_or_default();
+            sources.insert(0, file.replacen('~', &home, 1).into());
+        }
+        for source in sources {
+            if let Ok(content) = fs::read_to_string(source) {
+                global.extend(content.lines().filter_map(Rule::parse));
+            }
+        }
+        IgnoreRules { repo, global, per_dir: HashMap::new() }
+    }
+
+    fn rules_for(&mut self, dir: &str) -> &Vec<Rule> {
+        let repo = self.repo;
+        self.per_dir.entry(dir.to_string()).or_insert_with(|| {
+            fs::read_to_string(repo.worktree_file(dir).join(".gitignore"))
+                .map(|content| content.lines().filter_map(Rule::parse).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    // Decides a single path without looking at its parents; the last
+    // matching rule wins and deeper .gitignore files take precedence.
+    fn check_one(&mut self, path: &str, is_dir: bool) -> Option<bool> {
+        let mut dirs = vec![String::new()];
+        let mut prefix = String::new();
+        for part in path.split('/').collect::<Vec<_>>().iter().rev().skip(1).rev() {
+            prefix = if prefix.is_empty() { part.to_string() } else { format!("{}/{}", prefix, part) };
+            dirs.push(prefix.clone());
+        }
+
+        for dir in dirs.iter().rev() {
+            let relative = if dir.is_empty() { path } else { &path[dir.len() + 1..] };
+            if let Some(rule) = self.rules_for(dir).iter().rev().find(|rule| rule.matches(relative, is_dir)) {
+                return Some(!rule.negated);
+            }
+        }
+        self.global.iter().rev().find(|rule| rule.matches(path, is_dir)).map(|rule| !rule.negated)
+    }
+
+    pub fn is_ignored(&mut self, path: &str, is_dir: bool) -> bool {
+        // A file inside an ignored directory can never be re-included.
+        let parts: Vec<&str> = path.split('/').collect();
+        for i in 1..parts.len() {
+            if self.check_one(&parts[..i].join("/"), true) == Some(true) {
+                return true;
+            }
+        }
+        self.check_one(path, is_dir).unwrap_or(false)
+    }
+}
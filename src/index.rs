@@ -0,0 +1,294 @@
+use std::fs::{self, File, Metadata};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use sha1::{Digest, Sha1};
+
+use crate::object::{flatten_tree, hex, unhex, FileMode, GitObject, GitTree, GitTreeLeaf};
+use crate::repository::Repository;
+use crate::utils::{decode_path, encode_path};
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitIndexEntry {
+    pub ctime: (u32, u32),
+    pub mtime: (u32, u32),
+    pub dev: u32,
+    pub ino: u32,
+    pub mode: FileMode,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u32,
+    pub sha: String,
+    pub assume_valid: bool,
+    pub stage: u8,
+    pub name: String,
+}
+
+impl GitIndexEntry {
+    pub fn new(name: String, mode: FileMode, sha: String, metadata: Option<&Metadata>) -> GitIndexEntry {
+        let mut entry = GitIndexEntry {
+            ctime: (0, 0),
+            mtime: (0, 0),
+            dev: 0,
+            ino: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            size: 0,
+            sha,
+            assume_valid: false,
+            stage: 0,
+            name,
+        };
+        if let Some(metadata) = metadata {
+            entry.update_stat(metadata);
+        }
+        entry
+    }
+
+    #[cfg(unix)]
+    pub fn update_stat(&mut self, metadata: &Metadata) {
+        use std::os::unix::fs::MetadataExt;
+        self.ctime = (metadata.ctime() as u32, metadata.ctime_nsec() as u32);
+        self.mtime = (metadata.mtime() as u32, metadata.mtime_nsec() as u32);
+        self.dev = metadata.dev() as u32;
+        self.ino = metadata.ino() as u32;
+        self.uid = metadata.uid();
+        self.gid = metadata.gid();
+        self.size = metadata.len() as u32;
+    }
+
+    #[cfg(not(unix))]
+    pub fn update_stat(&mut self, metadata: &Metadata) {
+        let since_epoch = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| (d.as_secs() as u32, d.subsec_nanos()))
+                .unwrap_or((0, 0))
+        };
+        self.ctime = since_epoch(metadata.created());
+        self.mtime = since_epoch(metadata.modified());
+        self.size = metadata.len() as u32;
+    }
+
+    // True when the file's stat data still matches what was recorded, which
+    // lets callers skip rehashing unchanged files.
+    pub fn stat_matches(&self, metadata: &Metadata) -> bool {
+        let mut current = self.clone();
+        current.update_stat(metadata);
+        current.mtime == self.mtime && current.size == self.size && current.ino == self.ino && current.ctime == self.ctime
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct GitIndex {
+    pub version: u32,
+    pub entries: Vec<GitIndexEntry>,
+}
+
+impl Default for GitIndex {
+    fn default() -> Self {
+        GitIndex { version: 2, entries: Vec::new() }
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+impl GitIndex {
+    // GIT_INDEX_FILE, when set, points at an index to use instead of the
+    // repository's own.
+    pub fn path(repo: &Repository) -> PathBuf {
+        match std::env::var_os("GIT_INDEX_FILE") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => repo.repo_path("index"),
+        }
+    }
+
+    pub fn read(repo: &Repository) -> Result<GitIndex> {
+        GitIndex::read_from(&GitIndex::path(repo))
+    }
+
+    // Reads the index at `path`; a missing file is an empty index.
+    pub fn read_from(path: &Path) -> Result<GitIndex> {
+        if !path.exists() {
+            return Ok(GitIndex::default());
+        }
+        GitIndex::parse(&fs::read(path).with_context(|| format!("Failed to read index {:?}", path))?)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<GitIndex> {
+        if data.len() < 32 || &data[..4] != b"DIRC" {
+            bail!("Invalid index file signature");
+        }
+        let (content, checksum) = data.split_at(data.len() - 20);
+        if Sha1::digest(content).as_slice() != checksum {
+            bail!("Index file checksum mismatch");
+        }
+
+        let version = read_u32(data, 4);
+        if version != 2 && version != 3 {
+            bail!("Unsupported index version {}", version);
+        }
+        let count = read_u32(data, 8);
+
+        let mut entries = Vec::with_capacity(count as usize);
+        let mut pos = 12;
+        for _ in 0..count {
+            if pos + 62 > content.len() {
+                bail!("Truncated index entry");
+            }
+            let flags = u16::from_be_bytes([data[pos + 60], data[pos + 61]]);
+            let mut name_start = pos + 62;
+            if flags & 0x4000 != 0 {
+                // Extended flags (version 3) are not used by anything here.
+                name_start += 2;
+            }
+            let name_end = content[name_start..].iter().position(|&b| b == 0).map(|n| name_start + n).context("Unterminated index entry name")?;
+
+            entries.push(GitIndexEntry {
+                ctime: (read_u32(data, pos), read_u32(data, pos + 4)),
+                mtime: (read_u32(data, pos + 8), read_u32(data, pos + 12)),
+                dev: read_u32(data, pos + 16),
+                ino: read_u32(data, pos + 20),
+                mode: FileMode::from_bits(read_u32(data, pos + 24)).with_context(|| format!("Invalid mode {:o} in index entry", read_u32(data, pos + 24)))?,
+                uid: read_u32(data, pos + 28),
+                gid: read_u32(data, pos + 32),
+                size: read_u32(data, pos + 36),
+                sha: hex(&data[pos + 40..pos + 60]),
+                assume_valid: flags & 0x8000 != 0,
+                stage: ((flags >> 12) & 0x3) as u8,
+                name: decode_path(&data[name_start..name_end]),
+            });
+
+            // Entries are NUL padded to a multiple of eight bytes.
+            let length = name_end - pos;
+            pos += (length + 8) & !7;
+        }
+
+        Ok(GitIndex { version, entries })
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"DIRC");
+        out.extend_from_slice(&2u32.to_be_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+
+        for entry in &self.entries {
+            let start = out.len();
+            for value in [entry.ctime.0, entry.ctime.1, entry.mtime.0, entry.mtime.1, entry.dev, entry.ino, entry.mode.bits(), entry.uid, entry.gid, entry.size] {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            out.extend_from_slice(&unhex(&entry.sha)?);
+
+            let name = encode_path(&entry.name);
+            let mut flags = name.len().min(0xfff) as u16;
+            flags |= (entry.stage as u16 & 0x3) << 12;
+            if entry.assume_valid {
+                flags |= 0x8000;
+            }
+            out.extend_from_slice(&flags.to_be_bytes());
+            out.extend_from_slice(&name);
+
+            let length = out.len() - start;
+            out.resize(start + ((length + 8) & !7), 0);
+        }
+
+        let checksum = Sha1::digest(&out);
+        out.extend_from_slice(&checksum);
+        Ok(out)
+    }
+
+    pub fn write(&mut self, repo: &Repository) -> Result<()> {
+        self.write_to(&GitIndex::path(repo))
+    }
+
+    // Writes the index to `path` through `<path>.lock`, so readers never see
+    // it half written.
+    pub fn write_to(&mut self, path: &Path) -> Result<()> {
+        self.sort();
+        let data = self.serialize()?;
+
+        let mut lock = path.as_os_str().to_owned();
+        lock.push(".lock");
+        let lock = PathBuf::from(lock);
+        let mut file = File::create(&lock).with_context(|| format!("Failed to create {:?}", lock))?;
+        file.write_all(&data)?;
+        drop(file);
+        fs::rename(&lock, path)?;
+        Ok(())
+    }
+
+    pub fn sort(&mut self) {
+        self.entries.sort_by(|a, b| encode_path(&a.name).cmp(&encode_path(&b.name)).then(a.stage.cmp(&b.stage)));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&GitIndexEntry> {
+        self.entries.iter().find(|entry| entry.name == name && entry.stage == 0)
+    }
+
+    // Inserts an entry, replacing any existing entries for the same path.
+    pub fn add(&mut self, entry: GitIndexEntry) {
+        self.entries.retain(|existing| existing.name != entry.name);
+        self.entries.push(entry);
+        self.sort();
+    }
+
+    // Builds an index holding exactly the entries of a tree, with empty stat
+    // data since nothing has been checked out.
+    pub fn from_tree(repo: &Repository, tree_sha: &str) -> Result<GitIndex> {
+        let mut index = GitIndex::default();
+        for leaf in flatten_tree(repo, tree_sha)? {
+            index.entries.push(GitIndexEntry::new(leaf.path, leaf.mode, leaf.sha, None));
+        }
+        index.sort();
+        Ok(index)
+    }
+
+    // Writes the index out as a hierarchy of tree objects and returns the
+    // SHA of the root tree.
+    pub fn write_tree(&self, repo: &Repository) -> Result<String> {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.stage != 0) {
+            bail!("{}: unmerged (stage {})", entry.name, entry.stage);
+        }
+        let entries: Vec<(&str, &GitIndexEntry)> = self.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+        write_subtree(repo, &entries)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.name != name);
+        before != self.entries.len()
+    }
+}
+
+// `entries` are sorted and their paths are relative to the tree being built.
+fn write_subtree(repo: &Repository, entries: &[(&str, &GitIndexEntry)]) -> Result<String> {
+    let mut leaves = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        let (path, entry) = entries[i];
+        match path.split_once('/') {
+            None => {
+                leaves.push(GitTreeLeaf { mode: entry.mode, path: path.to_string(), sha: entry.sha.clone() });
+                i += 1;
+            }
+            Some((dir, _)) => {
+                let prefix = format!("{}/", dir);
+                let children: Vec<(&str, &GitIndexEntry)> = entries[i..]
+                    .iter()
+                    .take_while(|(path, _)| path.starts_with(&prefix))
+                    .map(|(path, entry)| (&path[prefix.len()..], *entry))
+                    .collect();
+                i += children.len();
+                leaves.push(GitTreeLeaf { mode: FileMode::Tree, path: dir.to_string(), sha: write_subtree(repo, &children)? });
+            }
+        }
+    }
+    Ok(GitTree::from_leaves(leaves).write(repo)?)
+}
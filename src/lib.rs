@@ -0,0 +1,17 @@
+pub mod attributes;
+pub mod convert;
+pub mod diff;
+pub mod error;
+pub mod gpg;
+pub mod ignore;
+pub mod index;
+pub mod merge;
+pub mod object;
+pub mod pack;
+pub mod patch;
+pub mod progress;
+pub mod refs;
+pub mod repository;
+pub mod utils;
+pub mod walker;
+pub mod worktree;
@@ -1,76 +1,82 @@
 use std::env;
 use std::path::PathBuf;
-use crate::repository::find_repo;
-use crate::utils::adjust_canonicalization;
+use std::process::exit;
+use git_rs::repository;
+use git_rs::utils::{adjust_canonicalization, path_from_os};
 
-mod repository;
-mod utils;
+mod commands;
 
 fn main() {
 
-    let mut args: Vec<String> = env::args().collect();
+    // Pathspecs may name files that are not valid UTF-8.
+    let mut args: Vec<String> = env::args_os().map(|arg| path_from_os(&arg)).collect();
 
     args.remove(0);
 
+    while args.first().is_some_and(|arg| arg == "-q" || arg == "--quiet") {
+        git_rs::progress::set_quiet(true);
+        args.remove(0);
+    }
+
     if args.is_empty() {
         println!("git_rs");
         return;
     }
 
-    let mut repo = repository::Repository::new(find_repo(env::current_dir().unwrap()).unwrap());
-
-    match &*args[0] {
-        "add" => {
-
-        }
-        "cat-file" => {
-            
-        }
-        "check-ignore" => {
-            
-        }
-        "checkout" => {
-            
-        }
-        "commit" => {
-            
-        }
-        "hash-object" => {
-            
-        }
+    let result = match &*args[0] {
+        "add" => commands::add(&args[1..]),
+        "apply" => commands::apply(&args[1..]),
+        "blame" => commands::blame(&args[1..]),
+        "cat-file" => commands::cat_file(&args[1..]),
+        "check-ignore" => Ok(()),
+        "checkout" => commands::checkout(&args[1..]),
+        "cherry-pick" => commands::cherry_pick(&args[1..]),
+        "commit" => commands::commit(&args[1..]),
+        "commit-tree" => commands::commit_tree(&args[1..]),
+        "diff" => commands::diff(&args[1..]),
+        "for-each-ref" => commands::for_each_ref(&args[1..]),
+        "gc" => commands::gc(&args[1..]),
+        "hash-object" => commands::hash_object(&args[1..]),
         "init" => {
             let mut r = repository::Repository::new(PathBuf::from(if args.len() == 1 { "." } else { &*args[1] }));
-            match r.create() {
-                Ok(_) => println!("Initialized empty Git repository in {}", adjust_canonicalization(&r.gitdir)),
-                Err(e) => eprintln!("Error: {}", e)
-            }
-        }
-        "log" => {
-            
-        }
-        "ls-files" => {
-            
-        }
-        "ls-tree" => {
-            
-        }
-        "rev-parse" => {
-            
-        }
-        "rm" => {
-            
-        }
-        "show-ref" => {
-            
-        }
-        "status" => {
-            
-        }
-        "tag" => {
-            
-        }
+            r.create().map(|reinitialized| {
+                if reinitialized {
+                    println!("Reinitialized existing Git repository in {}", adjust_canonicalization(&r.gitdir));
+                } else {
+                    println!("Initialized empty Git repository in {}", adjust_canonicalization(&r.gitdir));
+                }
+            }).map_err(anyhow::Error::msg)
+        }
+        "log" => commands::log(&args[1..]),
+        "ls-files" => commands::ls_files(&args[1..]),
+        "ls-tree" => commands::ls_tree(&args[1..]),
+        "merge" => commands::merge(&args[1..]),
+        "merge-file" => commands::merge_file(&args[1..]),
+        "mktree" => commands::mktree(&args[1..]),
+        "prune" => commands::prune(&args[1..]),
+        "read-tree" => commands::read_tree(&args[1..]),
+        "reflog" => commands::reflog(&args[1..]),
+        "restore" => commands::restore(&args[1..]),
+        "rev-list" => commands::rev_list(&args[1..]),
+        "rev-parse" => commands::rev_parse(&args[1..]),
+        "rm" => Ok(()),
+        "show-ref" => commands::show_ref(&args[1..]),
+        "stash" => commands::stash(&args[1..]),
+        "status" => commands::status(&args[1..]),
+        "symbolic-ref" => commands::symbolic_ref(&args[1..]),
+        "tag" => commands::tag(&args[1..]),
+        "update-index" => commands::update_index(&args[1..]),
+        "verify-commit" => commands::verify_commit(&args[1..]),
+        "verify-tag" => commands::verify_tag(&args[1..]),
+        "write-tree" => commands::write_tree(&args[1..]),
         _ => {
             println!("Invalid argument provided!");
+            Ok(())
         }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        exit(1);
     }
 }
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+
+use crate::convert::is_binary;
+use crate::diff::{diff, split_lines, Edit};
+use crate::index::{GitIndex, GitIndexEntry};
+use crate::object::{flatten_tree, read_blob, FileMode, GitBlob, GitObject, GitTreeLeaf};
+use crate::repository::Repository;
+
+
+// For each line of `base`, the line of `other` it was matched with.
+fn matched_lines(base: &[&[u8]], other: &[&[u8]]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    for edit in diff(base, other) {
+        if let Edit::Equal { old, new } = edit {
+            matches[old] = Some(new);
+        }
+    }
+    matches
+}
+
+fn push_lines(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    for line in lines {
+        out.extend_from_slice(line);
+    }
+}
+
+fn push_marker(out: &mut Vec<u8>, marker: &str) {
+    if out.last().is_some_and(|&b| b != b'\n') {
+        out.push(b'\n');
+    }
+    out.extend_from_slice(marker.as_bytes());
+    out.push(b'\n');
+}
+
+// Merges the changes both sides made to `base` line by line. Regions where
+// the sides disagree are written between conflict markers; lines they share
+// at either end of such a region are kept outside the markers, like git's
+// default conflict style. Returns the merged content and the number of
+// conflicting regions, zero when it merged cleanly.
+pub fn merge_content(base: &[u8], ours: &[u8], theirs: &[u8], ours_label: &str, theirs_label: &str) -> (Vec<u8>, usize) {
+    let base = split_lines(base);
+    let ours = split_lines(ours);
+    let theirs = split_lines(theirs);
+    let in_ours = matched_lines(&base, &ours);
+    let in_theirs = matched_lines(&base, &theirs);
+
+    let mut out = Vec::new();
+    let mut conflicts = 0;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // The next base line both sides kept is where they are in step again.
+        let sync = (i..base.len()).find(|&n| in_ours[n].is_some() && in_theirs[n].is_some());
+        let (end_base, end_ours, end_theirs) = match sync {
+            Some(n) => (n, in_ours[n].unwrap(), in_theirs[n].unwrap()),
+            None => (base.len(), ours.len(), theirs.len()),
+        };
+
+        let old = &base[i..end_base];
+        let a = &ours[j..end_ours];
+        let b = &theirs[k..end_theirs];
+        if a == old {
+            push_lines(&mut out, b);
+        } else if b == old || a == b {
+            push_lines(&mut out, a);
+        } else {
+            conflicts += 1;
+            let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+            let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+            push_lines(&mut out, &a[..prefix]);
+            push_marker(&mut out, &format!("<<<<<<< {}", ours_label));
+            push_lines(&mut out, &a[prefix..a.len() - suffix]);
+            push_marker(&mut out, "=======");
+            push_lines(&mut out, &b[prefix..b.len() - suffix]);
+            push_marker(&mut out, &format!(">>>>>>> {}", theirs_label));
+            push_lines(&mut out, &a[a.len() - suffix..]);
+        }
+
+        let Some(n) = sync else {
+            break;
+        };
+        out.extend_from_slice(base[n]);
+        i = n + 1;
+        j = end_ours + 1;
+        k = end_theirs + 1;
+    }
+    (out, conflicts)
+}
+
+
+// The outcome of merging two trees into the index. Conflicted paths get
+// their stage 1-3 entries and, where a file can be left in the worktree for
+// the user to resolve, the content to write there.
+#[derive(Debug, Default)]
+pub struct TreeMerge {
+    pub index: GitIndex,
+    pub conflicts: Vec<String>,
+    pub conflict_files: BTreeMap<String, Vec<u8>>,
+    pub messages: Vec<String>,
+}
+
+fn stage_entry(leaf: &GitTreeLeaf, stage: u8) -> GitIndexEntry {
+    let mut entry = GitIndexEntry::new(leaf.path.clone(), leaf.mode, leaf.sha.clone(), None);
+    entry.stage = stage;
+    entry
+}
+
+fn is_regular(leaf: &GitTreeLeaf) -> bool {
+    leaf.mode == FileMode::Regular || leaf.mode == FileMode::Executable
+}
+
+// Three-way merges the trees `ours` and `theirs` against their common
+// ancestor `base`, which is None for unrelated histories.
+pub fn merge_trees(repo: &Repository, base: Option<&str>, ours: &str, theirs: &str, ours_label: &str, theirs_label: &str) -> Result<TreeMerge> {
+    let leaves = |tree: Option<&str>| -> Result<BTreeMap<String, GitTreeLeaf>> {
+        Ok(match tree {
+            Some(tree) => flatten_tree(repo, tree)?.into_iter().map(|leaf| (leaf.path.clone(), leaf)).collect(),
+            None => BTreeMap::new(),
+        })
+    };
+    let base = leaves(base)?;
+    let ours = leaves(Some(ours))?;
+    let theirs = leaves(Some(theirs))?;
+    let paths: BTreeSet<&String> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+
+    let mut result = TreeMerge::default();
+    for path in paths {
+        let (o, a, b) = (base.get(path), ours.get(path), theirs.get(path));
+        // A path only one side touched takes that side's version.
+        if a == b || b == o || a == o {
+            let taken = if a == o { b } else { a };
+            result.index.entries.extend(taken.map(|leaf| stage_entry(leaf, 0)));
+            continue;
+        }
+
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                result.messages.push(format!("Auto-merging {}", path));
+                let kind = if o.is_some() { "content" } else { "add/add" };
+                let mergeable = is_regular(a) && is_regular(b) && o.is_none_or(is_regular);
+                let contents = if mergeable {
+                    let base_data = match o {
+                        Some(o) => read_blob(repo, &o.sha)?.raw_data,
+                        None => Vec::new(),
+                    };
+                    let (ours_data, theirs_data) = (read_blob(repo, &a.sha)?.raw_data, read_blob(repo, &b.sha)?.raw_data);
+                    if is_binary(&base_data) || is_binary(&ours_data) || is_binary(&theirs_data) {
+                        result.messages.push(format!("warning: Cannot merge binary files: {} ({} vs. {})", path, ours_label, theirs_label));
+                        None
+                    } else {
+                        Some(merge_content(&base_data, &ours_data, &theirs_data, ours_label, theirs_label))
+                    }
+                } else {
+                    None
+                };
+
+                // Theirs only wins the mode when ours left it alone.
+                let mode = if o.is_some_and(|o| o.mode == a.mode) { b.mode } else { a.mode };
+                match contents {
+                    Some((merged, 0)) => {
+                        let sha = GitBlob::new(merged).write(repo)?;
+                        result.index.entries.push(stage_entry(&GitTreeLeaf { mode, path: path.clone(), sha }, 0));
+                    }
+                    contents => {
+                        result.messages.push(format!("CONFLICT ({}): Merge conflict in {}", kind, path));
+                        let merged = match contents {
+                            Some((merged, _)) => merged,
+                            None => read_blob(repo, &a.sha)?.raw_data,
+                        };
+                        result.conflict_files.insert(path.clone(), merged);
+                        result.conflicts.push(path.clone());
+                        if let Some(o) = o {
+                            result.index.entries.push(stage_entry(o, 1));
+                        }
+                        result.index.entries.push(stage_entry(a, 2));
+                        result.index.entries.push(stage_entry(b, 3));
+                    }
+                }
+            }
+            (Some(a), None) => {
+                result.messages.push(format!("CONFLICT (modify/delete): {} deleted in {} and modified in {}.  Version {} of {} left in tree.", path, theirs_label, ours_label, ours_label, path));
+                result.conflicts.push(path.clone());
+                result.index.entries.extend(o.map(|o| stage_entry(o, 1)));
+                result.index.entries.push(stage_entry(a, 2));
+            }
+            (None, Some(b)) => {
+                result.messages.push(format!("CONFLICT (modify/delete): {} deleted in {} and modified in {}.  Version {} of {} left in tree.", path, ours_label, theirs_label, theirs_label, path));
+                result.conflicts.push(path.clone());
+                result.conflict_files.insert(path.clone(), read_blob(repo, &b.sha)?.raw_data);
+                result.index.entries.extend(o.map(|o| stage_entry(o, 1)));
+                result.index.entries.push(stage_entry(b, 3));
+            }
+            (None, None) => {}
+        }
+    }
+    result.index.sort();
+    Ok(result)
+}
@@ -0,0 +1,889 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+use crate::error::GitError;
+use crate::pack::{read_packed, read_packed_header};
+use crate::repository::Repository;
+use crate::utils::{decode_path, display_path, encode_path};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectType {
+    Blob,
+    Tree,
+    Commit,
+    Tag,
+}
+
+impl ObjectType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Blob => "blob",
+            ObjectType::Tree => "tree",
+            ObjectType::Commit => "commit",
+            ObjectType::Tag => "tag",
+        }
+    }
+}
+
+impl FromStr for ObjectType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "blob" => Ok(ObjectType::Blob),
+            "tree" => Ok(ObjectType::Tree),
+            "commit" => Ok(ObjectType::Commit),
+            "tag" => Ok(ObjectType::Tag),
+            _ => bail!("Unknown type {}", s),
+        }
+    }
+}
+
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+pub trait GitObject {
+    fn object_type(&self) -> ObjectType;
+
+    fn serialize(&self) -> Result<Vec<u8>, GitError>;
+
+    // Human readable form used by `cat-file -p`.
+    fn pretty(&self) -> Result<Vec<u8>, GitError> {
+        self.serialize()
+    }
+
+    fn hash(&self) -> Result<String, GitError> {
+        Ok(hash_data(self.object_type(), &self.serialize()?))
+    }
+
+    fn write(&self, repo: &Repository) -> Result<String, GitError> {
+        write_data(repo, self.object_type(), &self.serialize()?)
+    }
+}
+
+fn header(type_name: &str, len: usize) -> Vec<u8> {
+    format!("{} {}\0", type_name, len).into_bytes()
+}
+
+pub fn hash_data(type_: ObjectType, data: &[u8]) -> String {
+    hash_literally(type_.as_str(), data)
+}
+
+// Hashes `data` under any type name, like `hash-object --literally`. The
+// header and payload go to the hasher separately so the payload is never
+// copied.
+pub fn hash_literally(type_name: &str, data: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(header(type_name, data.len()));
+    hasher.update(data);
+    hex(&hasher.finalize())
+}
+
+#[derive(Debug, Error)]
+pub enum ObjectWriteError {
+    // The object is already in the store, which callers usually treat as success.
+    #[error("Object {0} already exists")]
+    AlreadyExists(String),
+    #[error("Failed to write object file {path:?}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+}
+
+// Stores `data` as a new loose object, failing with `AlreadyExists` if the
+// object is already present, whether loose, packed or in an alternate.
+pub fn store_object(repo: &Repository, type_: ObjectType, data: &[u8]) -> std::result::Result<String, ObjectWriteError> {
+    store_literally(repo, type_.as_str(), data)
+}
+
+fn store_literally(repo: &Repository, type_name: &str, data: &[u8]) -> std::result::Result<String, ObjectWriteError> {
+    let sha = hash_literally(type_name, data);
+    if repo.object_exists(&sha) {
+        return Err(ObjectWriteError::AlreadyExists(sha));
+    }
+
+    let path = repo.object_path(&sha);
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| ObjectWriteError::Io { path, source }
+    };
+    let dir = path.parent().expect("object paths always have a parent");
+    fs::create_dir_all(dir).map_err(io_error(dir))?;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&header(type_name, data.len())).map_err(io_error(&path))?;
+    encoder.write_all(data).map_err(io_error(&path))?;
+    let compressed = encoder.finish().map_err(io_error(&path))?;
+
+    let mut file = match File::options().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(ObjectWriteError::AlreadyExists(sha)),
+        Err(e) => return Err(io_error(&path)(e)),
+    };
+    file.write_all(&compressed).map_err(io_error(&path))?;
+    Ok(sha)
+}
+
+// Writes `data` as a loose object of `type_` and returns its SHA.
+pub fn write_data(repo: &Repository, type_: ObjectType, data: &[u8]) -> Result<String, GitError> {
+    write_literally(repo, type_.as_str(), data)
+}
+
+// Writes `data` as a loose object under any type name, even one no reader
+// understands.
+pub fn write_literally(repo: &Repository, type_name: &str, data: &[u8]) -> Result<String, GitError> {
+    match store_literally(repo, type_name, data) {
+        Ok(sha) | Err(ObjectWriteError::AlreadyExists(sha)) => Ok(sha),
+        Err(e) => Err(e.into()),
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct GitBlob {
+    pub raw_data: Vec<u8>,
+}
+
+impl GitBlob {
+    pub fn new(raw_data: Vec<u8>) -> GitBlob {
+        GitBlob { raw_data }
+    }
+}
+
+impl GitObject for GitBlob {
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Blob
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, GitError> {
+        Ok(self.raw_data.clone())
+    }
+
+    fn hash(&self) -> Result<String, GitError> {
+        Ok(hash_data(ObjectType::Blob, &self.raw_data))
+    }
+
+    fn write(&self, repo: &Repository) -> Result<String, GitError> {
+        write_data(repo, ObjectType::Blob, &self.raw_data)
+    }
+}
+
+
+fn malformed(reason: impl Into<String>) -> GitError {
+    GitError::Malformed(reason.into())
+}
+
+
+// Key-value list with message, the shared format of commits and tags.
+#[derive(Debug, Clone, Default)]
+pub struct Kvlm {
+    pub headers: Vec<(String, String)>,
+    pub message: String,
+}
+
+impl Kvlm {
+    pub fn parse(raw: &[u8]) -> Result<Kvlm, GitError> {
+        let text = std::str::from_utf8(raw).map_err(|_| malformed("not valid UTF-8"))?;
+        let mut kvlm = Kvlm::default();
+        let mut rest = text;
+
+        loop {
+            let Some(nl) = rest.find('\n') else {
+                return Err(malformed("missing message"));
+            };
+            if nl == 0 {
+                kvlm.message = rest[1..].to_string();
+                return Ok(kvlm);
+            }
+            let line = &rest[..nl];
+            rest = &rest[nl + 1..];
+
+            if let Some(continuation) = line.strip_prefix(' ') {
+                let Some(last) = kvlm.headers.last_mut() else {
+                    return Err(malformed("continuation line without a header"));
+                };
+                last.1.push('\n');
+                last.1.push_str(continuation);
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').ok_or_else(|| malformed(format!("bad header line '{}'", line)))?;
+            kvlm.headers.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = String::new();
+        for (key, value) in &self.headers {
+            out.push_str(key);
+            out.push(' ');
+            out.push_str(&value.replace('\n', "\n "));
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(&self.message);
+        out.into_bytes()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.headers.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+    }
+
+    // Removes a `gpgsig` header, returning the bytes it signed and the
+    // signature itself.
+    pub fn split_signature_header(&self) -> Option<(Vec<u8>, String)> {
+        let signature = self.get("gpgsig")?.to_string();
+        let mut payload = self.clone();
+        payload.headers.retain(|(key, _)| key != "gpgsig");
+        Some((payload.serialize(), signature))
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub time: i64,
+    pub offset: String,
+}
+
+impl Signature {
+    pub fn parse(s: &str) -> Result<Signature, GitError> {
+        let bad = || malformed(format!("bad signature '{}'", s));
+        let open = s.find('<').ok_or_else(bad)?;
+        let close = s.rfind('>').ok_or_else(bad)?;
+        if close < open {
+            return Err(bad());
+        }
+        let mut date = s[close + 1..].split_whitespace();
+        let time = date.next().unwrap_or("0").parse().map_err(|_| malformed(format!("bad timestamp in '{}'", s)))?;
+        let offset = date.next().unwrap_or("+0000").to_string();
+
+        Ok(Signature {
+            name: s[..open].trim_end().to_string(),
+            email: s[open + 1..close].to_string(),
+            time,
+            offset,
+        })
+    }
+
+    // Offset from UTC in seconds, e.g. "+0130" -> 5400.
+    pub fn offset_seconds(&self) -> i32 {
+        let sign = if self.offset.starts_with('-') { -1 } else { 1 };
+        let digits = self.offset.trim_start_matches(['+', '-']);
+        let hours: i32 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+        let minutes: i32 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+        sign * (hours * 3600 + minutes * 60)
+    }
+
+    // Builds the signature for `role` ("AUTHOR" or "COMMITTER") from the
+    // GIT_<role>_* environment variables, falling back to user.name and
+    // user.email and the current time.
+    pub fn identity(repo: &Repository, role: &str) -> Result<Signature> {
+        let name = std::env::var(format!("GIT_{}_NAME", role)).ok().or_else(|| repo.config_get("user", "name"));
+        let email = std::env::var(format!("GIT_{}_EMAIL", role)).ok().or_else(|| repo.config_get("user", "email"));
+        let (Some(name), Some(email)) = (name, email) else {
+            bail!("{} identity unknown\n\n*** Please tell me who you are.\n\nRun\n\n  git config user.email \"you@example.com\"\n  git config user.name \"Your Name\"", if role == "AUTHOR" { "Author" } else { "Committer" });
+        };
+
+        let (time, offset) = match std::env::var(format!("GIT_{}_DATE", role)) {
+            Ok(date) => {
+                let mut parts = date.trim_start_matches('@').split_whitespace();
+                let time = parts.next().unwrap_or("").parse().with_context(|| format!("Invalid date '{}'", date))?;
+                (time, parts.next().unwrap_or("+0000").to_string())
+            }
+            Err(_) => {
+                let now = chrono::Local::now();
+                let seconds = now.offset().local_minus_utc();
+                let sign = if seconds < 0 { '-' } else { '+' };
+                (now.timestamp(), format!("{}{:02}{:02}", sign, seconds.abs() / 3600, seconds.abs() % 3600 / 60))
+            }
+        };
+
+        Ok(Signature { name, email, time, offset })
+    }
+
+    pub fn format_date(&self, format: &str) -> String {
+        let offset = chrono::FixedOffset::east_opt(self.offset_seconds()).unwrap_or(chrono::FixedOffset::east_opt(0).unwrap());
+        match chrono::DateTime::from_timestamp(self.time, 0) {
+            Some(date) => date.with_timezone(&offset).format(format).to_string(),
+            None => self.time.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}> {} {}", self.name, self.email, self.time, self.offset)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct GitCommit {
+    pub kvlm: Kvlm,
+}
+
+impl GitCommit {
+    pub fn deserialize(raw: &[u8]) -> Result<GitCommit, GitError> {
+        Ok(GitCommit { kvlm: Kvlm::parse(raw)? })
+    }
+
+    pub fn build(tree: &str, parents: &[String], author: &Signature, committer: &Signature, message: &str) -> GitCommit {
+        let mut kvlm = Kvlm::default();
+        kvlm.headers.push(("tree".to_string(), tree.to_string()));
+        for parent in parents {
+            kvlm.headers.push(("parent".to_string(), parent.clone()));
+        }
+        kvlm.headers.push(("author".to_string(), author.to_string()));
+        kvlm.headers.push(("committer".to_string(), committer.to_string()));
+        kvlm.message = message.to_string();
+        GitCommit { kvlm }
+    }
+
+    pub fn tree(&self) -> Result<&str, GitError> {
+        self.kvlm.get("tree").ok_or_else(|| malformed("commit has no tree"))
+    }
+
+    pub fn parents(&self) -> Vec<&str> {
+        self.kvlm.get_all("parent")
+    }
+
+    pub fn author(&self) -> Result<Signature, GitError> {
+        Signature::parse(self.kvlm.get("author").ok_or_else(|| malformed("commit has no author"))?)
+    }
+
+    pub fn committer(&self) -> Result<Signature, GitError> {
+        Signature::parse(self.kvlm.get("committer").ok_or_else(|| malformed("commit has no committer"))?)
+    }
+
+    // The signed payload and signature of a signed commit.
+    pub fn signature(&self) -> Option<(Vec<u8>, String)> {
+        self.kvlm.split_signature_header()
+    }
+
+    // Adds a detached signature over the commit as it stands as a `gpgsig`
+    // header. Serialization folds its lines onto continuation lines.
+    pub fn add_signature(&mut self, signature: &str) {
+        self.kvlm.headers.push(("gpgsig".to_string(), signature.trim_end_matches('\n').to_string()));
+    }
+}
+
+impl GitObject for GitCommit {
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Commit
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, GitError> {
+        Ok(self.kvlm.serialize())
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct GitTag {
+    pub kvlm: Kvlm,
+}
+
+impl GitTag {
+    pub fn deserialize(raw: &[u8]) -> Result<GitTag, GitError> {
+        Ok(GitTag { kvlm: Kvlm::parse(raw)? })
+    }
+
+    pub fn build(object: &str, type_: ObjectType, name: &str, tagger: &Signature, message: &str) -> GitTag {
+        let mut kvlm = Kvlm::default();
+        kvlm.headers.push(("object".to_string(), object.to_string()));
+        kvlm.headers.push(("type".to_string(), type_.to_string()));
+        kvlm.headers.push(("tag".to_string(), name.to_string()));
+        kvlm.headers.push(("tagger".to_string(), tagger.to_string()));
+        kvlm.message = message.to_string();
+        GitTag { kvlm }
+    }
+
+    pub fn object(&self) -> Result<&str, GitError> {
+        self.kvlm.get("object").ok_or_else(|| malformed("tag has no object"))
+    }
+
+    pub fn tagger(&self) -> Result<Signature, GitError> {
+        Signature::parse(self.kvlm.get("tagger").ok_or_else(|| malformed("tag has no tagger"))?)
+    }
+
+    // Splits off the tag's signature, returning the signed payload and the
+    // signature. Git appends it to the message; a `gpgsig` header is also
+    // accepted.
+    pub fn signature(&self) -> Option<(Vec<u8>, String)> {
+        if let Some(signed) = self.kvlm.split_signature_header() {
+            return Some(signed);
+        }
+
+        let start = ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----", "-----BEGIN SIGNED MESSAGE-----"]
+            .iter()
+            .filter_map(|marker| self.kvlm.message.find(marker))
+            .filter(|&i| i == 0 || self.kvlm.message.as_bytes()[i - 1] == b'\n')
+            .min()?;
+        let mut payload = self.kvlm.clone();
+        payload.message.truncate(start);
+        Some((payload.serialize(), self.kvlm.message[start..].to_string()))
+    }
+}
+
+impl GitObject for GitTag {
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Tag
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, GitError> {
+        Ok(self.kvlm.serialize())
+    }
+}
+
+
+// The kinds of entry a tree or the index can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileMode {
+    Regular,
+    Executable,
+    Symlink,
+    Tree,
+    Gitlink,
+}
+
+impl FileMode {
+    pub fn bits(self) -> u32 {
+        match self {
+            FileMode::Regular => 0o100644,
+            FileMode::Executable => 0o100755,
+            FileMode::Symlink => 0o120000,
+            FileMode::Tree => 0o40000,
+            FileMode::Gitlink => 0o160000,
+        }
+    }
+
+    // Like git, any regular file mode counts as executable or not by its
+    // owner execute bit, so old trees with modes like 100664 still read.
+    pub fn from_bits(bits: u32) -> Option<FileMode> {
+        match bits & 0o170000 {
+            0o100000 if bits & 0o100 != 0 => Some(FileMode::Executable),
+            0o100000 => Some(FileMode::Regular),
+            0o120000 => Some(FileMode::Symlink),
+            0o040000 => Some(FileMode::Tree),
+            0o160000 => Some(FileMode::Gitlink),
+            _ => None,
+        }
+    }
+
+    pub fn object_type(self) -> ObjectType {
+        match self {
+            FileMode::Tree => ObjectType::Tree,
+            FileMode::Gitlink => ObjectType::Commit,
+            _ => ObjectType::Blob,
+        }
+    }
+}
+
+impl FromStr for FileMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        u32::from_str_radix(s, 8).ok().and_then(FileMode::from_bits).ok_or_else(|| anyhow!("Invalid file mode '{}'", s))
+    }
+}
+
+// Octal without leading zeros, as trees store it; pad with `{:0>6}` for
+// the six-digit form ls-tree shows.
+impl fmt::Display for FileMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{:o}", self.bits()))
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitTreeLeaf {
+    pub mode: FileMode,
+    pub path: String,
+    pub sha: String,
+}
+
+impl GitTreeLeaf {
+    pub fn is_tree(&self) -> bool {
+        self.mode == FileMode::Tree
+    }
+
+    pub fn object_type(&self) -> ObjectType {
+        self.mode.object_type()
+    }
+
+    // Git sorts tree entries as if directories had a trailing slash.
+    fn sort_key(&self) -> Vec<u8> {
+        let mut key = encode_path(&self.path);
+        if self.is_tree() {
+            key.push(b'/');
+        }
+        key
+    }
+}
+
+impl fmt::Display for GitTreeLeaf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:0>6} {} {}\t{}", self.mode, self.object_type(), self.sha, display_path(&self.path))
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct GitTree {
+    pub leaves: Vec<GitTreeLeaf>,
+}
+
+impl GitTree {
+    pub fn from_leaves(mut leaves: Vec<GitTreeLeaf>) -> GitTree {
+        leaves.sort_by_key(|leaf| leaf.sort_key());
+        GitTree { leaves }
+    }
+
+    pub fn deserialize(raw: &[u8]) -> Result<GitTree, GitError> {
+        let mut leaves = Vec::new();
+        let mut pos = 0;
+
+        while pos < raw.len() {
+            let space = raw[pos..].iter().position(|&b| b == b' ').ok_or_else(|| malformed("bad tree entry"))? + pos;
+            let null = raw[space..].iter().position(|&b| b == 0).ok_or_else(|| malformed("bad tree entry"))? + space;
+            if null + 21 > raw.len() {
+                return Err(malformed("truncated tree entry"));
+            }
+
+            let mode = String::from_utf8_lossy(&raw[pos..space]);
+            leaves.push(GitTreeLeaf {
+                mode: mode.parse().map_err(|_| malformed(format!("invalid file mode '{}'", mode)))?,
+                path: decode_path(&raw[space + 1..null]),
+                sha: hex(&raw[null + 1..null + 21]),
+            });
+            pos = null + 21;
+        }
+
+        Ok(GitTree { leaves })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&GitTreeLeaf> {
+        self.leaves.iter().find(|leaf| leaf.path == name)
+    }
+}
+
+impl GitObject for GitTree {
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Tree
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, GitError> {
+        let mut out = Vec::new();
+        for leaf in &self.leaves {
+            let sha = match unhex(&leaf.sha) {
+                Ok(sha) if sha.len() == 20 => sha,
+                _ => return Err(malformed(format!("entry '{}' has invalid object name '{}'", leaf.path, leaf.sha))),
+            };
+            out.extend_from_slice(leaf.mode.to_string().as_bytes());
+            out.push(b' ');
+            out.extend_from_slice(&encode_path(&leaf.path));
+            out.push(0);
+            out.extend_from_slice(&sha);
+        }
+        Ok(out)
+    }
+
+    fn pretty(&self) -> Result<Vec<u8>, GitError> {
+        Ok(self.leaves.iter().map(|leaf| format!("{}\n", leaf)).collect::<String>().into_bytes())
+    }
+}
+
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn unhex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        bail!("Invalid hex string '{}'", s);
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex string '{}'", s)))
+        .collect()
+}
+
+// The decompressed contents of a loose object, header included.
+pub fn read_loose(repo: &Repository, sha: &str) -> Result<Vec<u8>, GitError> {
+    let path = repo.find_object_file(sha).ok_or_else(|| GitError::ObjectNotFound(sha.to_string()))?;
+    let file = File::open(&path)?;
+    repo.count_object_read();
+
+    let mut raw = Vec::new();
+    ZlibDecoder::new(file).read_to_end(&mut raw).map_err(|e| GitError::Malformed(format!("{}: failed to decompress: {}", sha, e)))?;
+    Ok(raw)
+}
+
+// Splits `<type> <size>\0` off the front of a decompressed object, returning
+// the type name as written, the declared size and where the payload starts.
+// The type is not checked so that objects of unknown types can be reported.
+pub fn parse_header(raw: &[u8]) -> Result<(String, usize, usize), GitError> {
+    let space = raw.iter().position(|&b| b == b' ').ok_or_else(|| malformed("missing type"))?;
+    let null = raw.iter().position(|&b| b == 0).ok_or_else(|| malformed("missing header terminator"))?;
+    if null < space {
+        return Err(malformed("missing type"));
+    }
+    let type_ = std::str::from_utf8(&raw[..space]).map_err(|_| malformed("bad type"))?.to_string();
+    let size: u64 = std::str::from_utf8(&raw[space + 1..null]).ok().and_then(|size| size.parse().ok()).ok_or_else(|| malformed("bad length"))?;
+    // Sizes past usize::MAX can only come up on 32-bit targets.
+    let size = usize::try_from(size).map_err(|_| malformed(format!("object size {} is too large for this platform", size)))?;
+    Ok((type_, size, null + 1))
+}
+
+// Reads just the `<type> <size>` header of a loose object, inflating no
+// more of it than the header needs. The type is returned as written.
+pub fn read_loose_header(repo: &Repository, sha: &str) -> Result<(String, usize), GitError> {
+    let path = repo.find_object_file(sha).ok_or_else(|| GitError::ObjectNotFound(sha.to_string()))?;
+    read_header_from(sha, File::open(&path)?)
+}
+
+// Reads the header of the object `sha` from its compressed bytes, pulling
+// no more of them from `compressed` than inflating the header takes.
+pub fn read_header_from<R: Read>(sha: &str, compressed: R) -> Result<(String, usize), GitError> {
+    // A header is a type name, a decimal size and a NUL; anything longer
+    // than this is not one.
+    let mut decoder = ZlibDecoder::new(compressed);
+    let mut header = [0; 64];
+    let mut len = 0;
+    while len < header.len() && !header[..len].contains(&0) {
+        let n = decoder.read(&mut header[len..]).map_err(|e| GitError::Malformed(format!("{}: failed to decompress: {}", sha, e)))?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    let (type_, size, _) = parse_header(&header[..len]).map_err(|e| e.in_object(sha))?;
+    Ok((type_, size))
+}
+
+// The type and payload size of an object without reading its payload.
+pub fn read_object_header(repo: &Repository, sha: &str) -> Result<(ObjectType, usize), GitError> {
+    let (type_, size) = match read_loose_header(repo, sha) {
+        Err(GitError::ObjectNotFound(_)) => match repo.find_packed(sha)? {
+            Some((pack, offset)) => return read_packed_header(repo, pack, offset).map_err(|e| e.in_object(sha)),
+            None => return Err(GitError::ObjectNotFound(sha.to_string())),
+        },
+        result => result?,
+    };
+    let type_ = ObjectType::from_str(&type_).map_err(|e| GitError::Malformed(format!("{}: {}", sha, e)))?;
+    Ok((type_, size))
+}
+
+// Reads an object's type and payload, from a loose file or else a pack.
+pub fn read_raw(repo: &Repository, sha: &str) -> Result<(ObjectType, Vec<u8>), GitError> {
+    let mut raw = match read_loose(repo, sha) {
+        Err(GitError::ObjectNotFound(_)) => match repo.find_packed(sha)? {
+            Some((pack, offset)) => return read_packed(repo, pack, offset).map_err(|e| e.in_object(sha)),
+            None => return Err(GitError::ObjectNotFound(sha.to_string())),
+        },
+        result => result?,
+    };
+    let (type_, size, start) = parse_header(&raw).map_err(|e| e.in_object(sha))?;
+    let type_ = ObjectType::from_str(&type_).map_err(|e| GitError::Malformed(format!("{}: {}", sha, e)))?;
+    let actual = raw.len().checked_sub(start).ok_or_else(|| GitError::Malformed(format!("{}: truncated object", sha)))?;
+    if actual < size {
+        return Err(GitError::Malformed(format!("{}: truncated object ({} of {} bytes)", sha, actual, size)));
+    }
+    if actual > size {
+        return Err(GitError::Malformed(format!("{}: bad length", sha)));
+    }
+
+    raw.drain(..start);
+    Ok((type_, raw))
+}
+
+// The SHAs of the loose objects stored under one object directory.
+fn objects_in(objects: &Path) -> Result<Vec<String>> {
+    let mut shas = Vec::new();
+    for dir in fs::read_dir(objects)? {
+        let dir = dir?;
+        let prefix = dir.file_name().to_string_lossy().to_string();
+        if prefix.len() != 2 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        for file in fs::read_dir(dir.path())? {
+            let name = file?.file_name().to_string_lossy().to_string();
+            if name.len() == 38 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+                shas.push(format!("{}{}", prefix, name));
+            }
+        }
+    }
+    shas.sort();
+    Ok(shas)
+}
+
+// The SHAs of all loose objects in the repository's own object directory.
+pub fn loose_objects(repo: &Repository) -> Result<Vec<String>> {
+    objects_in(&repo.repo_path("objects"))
+}
+
+// Every object the repository can read, loose or packed and including those
+// in alternates, sorted and listed once even when stored in more than one
+// place.
+pub fn all_objects(repo: &Repository) -> Result<Vec<String>> {
+    let mut shas = BTreeSet::new();
+    for dir in repo.object_dirs() {
+        if dir.is_dir() {
+            shas.extend(objects_in(&dir)?);
+        }
+    }
+    for pack in repo.packs()? {
+        shas.extend(pack.shas());
+    }
+    Ok(shas.into_iter().collect())
+}
+
+pub fn parse_from_bytes(type_: ObjectType, data: Vec<u8>) -> Result<Box<dyn GitObject>, GitError> {
+    Ok(match type_ {
+        ObjectType::Blob => Box::new(GitBlob::new(data)),
+        ObjectType::Tree => Box::new(GitTree::deserialize(&data)?),
+        ObjectType::Commit => Box::new(GitCommit::deserialize(&data)?),
+        ObjectType::Tag => Box::new(GitTag::deserialize(&data)?),
+    })
+}
+
+fn is_sha(s: &str) -> bool {
+    s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Checks that `data` is a well-formed object of `type_`, the way hash-object
+// does before writing anything.
+pub fn validate(type_: ObjectType, data: &[u8]) -> Result<()> {
+    let invalid = |reason: String| anyhow!("invalid {} object: {}", type_, reason);
+    let unparsable = |e: GitError| match e {
+        GitError::Malformed(reason) => invalid(reason),
+        e => invalid(e.to_string()),
+    };
+    match type_ {
+        ObjectType::Blob => {}
+        ObjectType::Tree => {
+            for leaf in GitTree::deserialize(data).map_err(unparsable)?.leaves {
+                if leaf.path.is_empty() || leaf.path.contains('/') || leaf.path == "." || leaf.path == ".." {
+                    return Err(invalid(format!("bad entry name '{}'", leaf.path)));
+                }
+            }
+        }
+        ObjectType::Commit => {
+            let kvlm = Kvlm::parse(data).map_err(unparsable)?;
+            match kvlm.get("tree") {
+                Some(tree) if is_sha(tree) => {}
+                Some(tree) => return Err(invalid(format!("bad tree '{}'", tree))),
+                None => return Err(invalid("missing tree".to_string())),
+            }
+            if let Some(parent) = kvlm.get_all("parent").into_iter().find(|parent| !is_sha(parent)) {
+                return Err(invalid(format!("bad parent '{}'", parent)));
+            }
+            for key in ["author", "committer"] {
+                let value = kvlm.get(key).ok_or_else(|| invalid(format!("missing {}", key)))?;
+                Signature::parse(value).map_err(|_| invalid(format!("bad {} '{}'", key, value)))?;
+            }
+        }
+        ObjectType::Tag => {
+            let kvlm = Kvlm::parse(data).map_err(unparsable)?;
+            match kvlm.get("object") {
+                Some(object) if is_sha(object) => {}
+                Some(object) => return Err(invalid(format!("bad object '{}'", object))),
+                None => return Err(invalid("missing object".to_string())),
+            }
+            let tagged = kvlm.get("type").ok_or_else(|| invalid("missing type".to_string()))?;
+            ObjectType::from_str(tagged).map_err(|_| invalid(format!("bad type '{}'", tagged)))?;
+            kvlm.get("tag").ok_or_else(|| invalid("missing tag name".to_string()))?;
+            if let Some(tagger) = kvlm.get("tagger") {
+                Signature::parse(tagger).map_err(|_| invalid(format!("bad tagger '{}'", tagger)))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn read_object(repo: &Repository, sha: &str) -> Result<Box<dyn GitObject>, GitError> {
+    repo.read_object(sha)
+}
+
+fn read_expecting(repo: &Repository, sha: &str, expected: ObjectType) -> Result<Vec<u8>, GitError> {
+    let (actual, data) = read_raw(repo, sha)?;
+    if actual != expected {
+        return Err(GitError::WrongType { sha: sha.to_string(), expected, actual });
+    }
+    Ok(data)
+}
+
+pub fn read_blob(repo: &Repository, sha: &str) -> Result<GitBlob, GitError> {
+    Ok(GitBlob::new(read_expecting(repo, sha, ObjectType::Blob)?))
+}
+
+pub fn read_tree(repo: &Repository, sha: &str) -> Result<GitTree, GitError> {
+    GitTree::deserialize(&read_expecting(repo, sha, ObjectType::Tree)?).map_err(|e| e.in_object(sha))
+}
+
+// Recursively lists every non-tree entry below `tree_sha`, with paths
+// relative to that tree.
+pub fn flatten_tree(repo: &Repository, tree_sha: &str) -> Result<Vec<GitTreeLeaf>, GitError> {
+    let mut out = Vec::new();
+    flatten_into(repo, tree_sha, "", &mut out)?;
+    Ok(out)
+}
+
+fn flatten_into(repo: &Repository, tree_sha: &str, prefix: &str, out: &mut Vec<GitTreeLeaf>) -> Result<(), GitError> {
+    for leaf in read_tree(repo, tree_sha)?.leaves {
+        let path = format!("{}{}", prefix, leaf.path);
+        if leaf.is_tree() {
+            flatten_into(repo, &leaf.sha, &format!("{}/", path), out)?;
+        } else {
+            out.push(GitTreeLeaf { path, ..leaf });
+        }
+    }
+    Ok(())
+}
+
+// Descends from `tree_sha` through the `/`-separated components of `path`
+// and returns the entry it names, or None if any component is missing.
+pub fn resolve_path_in_tree(repo: &Repository, tree_sha: &str, path: &str) -> Result<Option<GitTreeLeaf>, GitError> {
+    let mut parts = path.split('/').filter(|part| !part.is_empty()).peekable();
+    let mut tree = read_tree(repo, tree_sha)?;
+
+    while let Some(part) = parts.next() {
+        let Some(leaf) = tree.find(part).cloned() else {
+            return Ok(None);
+        };
+        if parts.peek().is_none() {
+            return Ok(Some(leaf));
+        }
+        if !leaf.is_tree() {
+            return Ok(None);
+        }
+        tree = read_tree(repo, &leaf.sha)?;
+    }
+    Ok(None)
+}
+
+pub fn read_commit(repo: &Repository, sha: &str) -> Result<GitCommit, GitError> {
+    GitCommit::deserialize(&read_expecting(repo, sha, ObjectType::Commit)?).map_err(|e| e.in_object(sha))
+}
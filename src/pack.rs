@@ -0,0 +1,348 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use sha1::{Digest, Sha1};
+
+use crate::error::GitError;
+use crate::object::{hex, read_raw, unhex, ObjectType};
+use crate::progress::Progress;
+use crate::repository::Repository;
+
+
+// Entry types in a pack besides the four object types, which keep their
+// numbers from the object header.
+const OFS_DELTA: u8 = 6;
+const REF_DELTA: u8 = 7;
+
+const IDX_MAGIC: [u8; 8] = [0xff, b't', b'O', b'c', 0, 0, 0, 2];
+
+fn type_number(type_: ObjectType) -> u8 {
+    match type_ {
+        ObjectType::Commit => 1,
+        ObjectType::Tree => 2,
+        ObjectType::Blob => 3,
+        ObjectType::Tag => 4,
+    }
+}
+
+fn type_from_number(n: u8) -> Option<ObjectType> {
+    match n {
+        1 => Some(ObjectType::Commit),
+        2 => Some(ObjectType::Tree),
+        3 => Some(ObjectType::Blob),
+        4 => Some(ObjectType::Tag),
+        _ => None,
+    }
+}
+
+// A version 2 `.idx` file: the SHA of every object in the matching `.pack`,
+// sorted, with the offset of each.
+#[derive(Debug)]
+pub struct PackIndex {
+    pub pack: PathBuf,
+    shas: Vec<[u8; 20]>,
+    offsets: Vec<u64>,
+}
+
+impl PackIndex {
+    pub fn open(idx: &Path) -> Result<PackIndex, GitError> {
+        let data = fs::read(idx)?;
+        let bad = || GitError::Malformed(format!("{}: bad pack index", idx.display()));
+        if data.len() < IDX_MAGIC.len() + 256 * 4 || data[..IDX_MAGIC.len()] != IDX_MAGIC {
+            return Err(bad());
+        }
+        let u32_at = |pos: usize| u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+        let count = u32_at(IDX_MAGIC.len() + 255 * 4) as usize;
+        let shas_start = IDX_MAGIC.len() + 256 * 4;
+        let offsets_start = shas_start + count * 24;
+        let large_start = offsets_start + count * 4;
+        if data.len() < large_start + 40 {
+            return Err(bad());
+        }
+
+        let shas = (0..count).map(|i| data[shas_start + i * 20..shas_start + (i + 1) * 20].try_into().unwrap()).collect();
+        let mut offsets = Vec::with_capacity(count);
+        for i in 0..count {
+            let offset = u32_at(offsets_start + i * 4);
+            // Offsets past 2 GiB live in a table of 8-byte ones.
+            offsets.push(if offset & 0x8000_0000 == 0 {
+                offset as u64
+            } else {
+                let pos = large_start + (offset & 0x7fff_ffff) as usize * 8;
+                u64::from_be_bytes(data.get(pos..pos + 8).ok_or_else(bad)?.try_into().unwrap())
+            });
+        }
+        Ok(PackIndex { pack: idx.with_extension("pack"), shas, offsets })
+    }
+
+    // Where `sha` starts in the pack, if the pack has it.
+    pub fn find(&self, sha: &str) -> Option<u64> {
+        let raw: [u8; 20] = unhex(sha).ok()?.try_into().ok()?;
+        self.shas.binary_search(&raw).ok().map(|i| self.offsets[i])
+    }
+
+    pub fn shas(&self) -> impl Iterator<Item = String> + '_ {
+        self.shas.iter().map(|sha| hex(sha))
+    }
+}
+
+// The indexes of the packs under one `objects/pack` directory. A `.idx`
+// without its `.pack` is left over from an interrupted write and skipped.
+pub fn pack_indexes(dir: &Path) -> Result<Vec<PackIndex>, GitError> {
+    let mut indexes = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(indexes);
+    };
+    let mut paths: Vec<PathBuf> = entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<_, _>>()?;
+    paths.sort();
+    for path in paths {
+        if path.extension().is_some_and(|ext| ext == "idx") && path.with_extension("pack").is_file() {
+            indexes.push(PackIndex::open(&path)?);
+        }
+    }
+    Ok(indexes)
+}
+
+fn read_byte(file: &mut File) -> io::Result<u8> {
+    let mut byte = [0];
+    file.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+// Reads the type and size that start a pack entry. The size is that of the
+// object, or for a delta, of the delta data.
+fn read_entry_header(file: &mut File) -> io::Result<(u8, usize)> {
+    let mut byte = read_byte(file)?;
+    let type_ = (byte >> 4) & 7;
+    let mut size = (byte & 0x0f) as usize;
+    let mut shift = 4;
+    while byte & 0x80 != 0 && shift < usize::BITS {
+        byte = read_byte(file)?;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+    Ok((type_, size))
+}
+
+fn inflate(file: &mut File, size: usize, pack: &Path) -> Result<Vec<u8>, GitError> {
+    let mut data = Vec::with_capacity(size);
+    ZlibDecoder::new(file).take(size as u64).read_to_end(&mut data).map_err(|e| GitError::Malformed(format!("{}: failed to decompress: {}", pack.display(), e)))?;
+    if data.len() != size {
+        return Err(GitError::Malformed(format!("{}: truncated entry", pack.display())));
+    }
+    Ok(data)
+}
+
+// Where the base of an offset delta starts, written as a distance back from
+// the delta's own offset.
+fn read_base_offset(file: &mut File, offset: u64) -> io::Result<Option<u64>> {
+    let mut byte = read_byte(file)?;
+    let mut distance = (byte & 0x7f) as u64;
+    while byte & 0x80 != 0 {
+        byte = read_byte(file)?;
+        distance = match distance.checked_add(1).and_then(|d| d.checked_mul(128)) {
+            Some(d) => d | (byte & 0x7f) as u64,
+            None => return Ok(None),
+        };
+    }
+    Ok(offset.checked_sub(distance))
+}
+
+fn read_at(repo: &Repository, file: &mut File, pack: &Path, offset: u64) -> Result<(ObjectType, Vec<u8>), GitError> {
+    let bad = |what: &str| GitError::Malformed(format!("{}: {} at offset {}", pack.display(), what, offset));
+    file.seek(SeekFrom::Start(offset))?;
+    let (type_, size) = read_entry_header(file)?;
+    let ((base_type, base), delta) = match type_ {
+        OFS_DELTA => {
+            let base_offset = read_base_offset(file, offset)?.ok_or_else(|| bad("bad delta base offset"))?;
+            let delta = inflate(file, size, pack)?;
+            (read_at(repo, file, pack, base_offset)?, delta)
+        }
+        REF_DELTA => {
+            let mut base_sha = [0; 20];
+            file.read_exact(&mut base_sha)?;
+            let delta = inflate(file, size, pack)?;
+            (read_raw(repo, &hex(&base_sha))?, delta)
+        }
+        n => return Ok((type_from_number(n).ok_or_else(|| bad("unknown entry type"))?, inflate(file, size, pack)?)),
+    };
+    Ok((base_type, apply_delta(&base, &delta).ok_or_else(|| bad("bad delta"))?))
+}
+
+// Reads the object stored at `offset` in a pack, resolving any chain of
+// deltas it is stored as.
+pub fn read_packed(repo: &Repository, pack: &PackIndex, offset: u64) -> Result<(ObjectType, Vec<u8>), GitError> {
+    repo.count_object_read();
+    read_at(repo, &mut File::open(&pack.pack)?, &pack.pack, offset)
+}
+
+// The type and size of a packed object. Only deltas need inflating, since
+// their header gives the size of the delta rather than of the object.
+pub fn read_packed_header(repo: &Repository, pack: &PackIndex, offset: u64) -> Result<(ObjectType, usize), GitError> {
+    let mut file = File::open(&pack.pack)?;
+    file.seek(SeekFrom::Start(offset))?;
+    match read_entry_header(&mut file)? {
+        (OFS_DELTA | REF_DELTA, _) => read_packed(repo, pack, offset).map(|(type_, data)| (type_, data.len())),
+        (n, size) => Ok((type_from_number(n).ok_or_else(|| GitError::Malformed(format!("{}: unknown entry type at offset {}", pack.pack.display(), offset)))?, size)),
+    }
+}
+
+// Rebuilds an object from its delta base and a delta: the sizes of both,
+// then instructions to copy ranges of the base or insert new bytes.
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let varint = |pos: &mut usize| {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(*pos)?;
+            *pos += 1;
+            value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    };
+    if varint(&mut pos)? != base.len() {
+        return None;
+    }
+    let size = varint(&mut pos)?;
+
+    let mut out = Vec::with_capacity(size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            let mut offset = 0usize;
+            let mut len = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    len |= (*delta.get(pos)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if len == 0 {
+                len = 0x10000;
+            }
+            out.extend_from_slice(base.get(offset..offset.checked_add(len)?)?);
+        } else if op != 0 {
+            out.extend_from_slice(delta.get(pos..pos + op as usize)?);
+            pos += op as usize;
+        } else {
+            return None;
+        }
+    }
+    (out.len() == size).then_some(out)
+}
+
+// Hashes everything written through it, for the trailing pack checksum.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha1,
+    written: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn entry_header(type_: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![(type_ << 4) | (size & 0x0f) as u8];
+    let mut rest = size >> 4;
+    while rest != 0 {
+        *header.last_mut().unwrap() |= 0x80;
+        header.push((rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    header
+}
+
+// Writes the objects `shas` into a new pack and index under objects/pack,
+// each stored whole at zlib `compression` rather than as a delta. The index
+// is written last, so readers never find a pack that isn't complete.
+// Returns the pack's checksum, which names both files.
+pub fn write_pack(repo: &Repository, shas: &[String], compression: Compression) -> Result<String, GitError> {
+    let dir = repo.repo_path("objects/pack");
+    fs::create_dir_all(&dir)?;
+    let temp = dir.join("tmp_pack");
+    let mut writer = HashingWriter { inner: io::BufWriter::new(File::create(&temp)?), hasher: Sha1::new(), written: 0 };
+    writer.write_all(b"PACK")?;
+    writer.write_all(&2u32.to_be_bytes())?;
+    writer.write_all(&(shas.len() as u32).to_be_bytes())?;
+
+    let mut entries = Vec::with_capacity(shas.len());
+    let mut progress = Progress::new("Writing objects", Some(shas.len()));
+    for sha in shas {
+        progress.tick();
+        let (type_, data) = read_raw(repo, sha)?;
+        // The encoder starts out holding the entry header, so `entry` is
+        // the header followed by the compressed object.
+        let mut encoder = ZlibEncoder::new(entry_header(type_number(type_), data.len()), compression);
+        encoder.write_all(&data)?;
+        let entry = encoder.finish()?;
+
+        let mut crc = Crc::new();
+        crc.update(&entry);
+        let raw: [u8; 20] = unhex(sha).ok().and_then(|raw| raw.try_into().ok()).ok_or_else(|| GitError::Malformed(format!("{}: bad object name", sha)))?;
+        entries.push((raw, crc.sum(), writer.written));
+        writer.write_all(&entry)?;
+    }
+    progress.finish();
+    let HashingWriter { inner, hasher, .. } = writer;
+    let checksum: [u8; 20] = hasher.finalize().into();
+    let mut file = inner.into_inner().map_err(|e| e.into_error())?;
+    file.write_all(&checksum)?;
+    drop(file);
+    let name = hex(&checksum);
+    fs::rename(&temp, dir.join(format!("pack-{}.pack", name)))?;
+
+    entries.sort();
+    let mut idx = IDX_MAGIC.to_vec();
+    for first in 0..=255u8 {
+        idx.extend_from_slice(&(entries.partition_point(|(sha, _, _)| sha[0] <= first) as u32).to_be_bytes());
+    }
+    for (sha, _, _) in &entries {
+        idx.extend_from_slice(sha);
+    }
+    for (_, crc, _) in &entries {
+        idx.extend_from_slice(&crc.to_be_bytes());
+    }
+    let mut large = Vec::new();
+    for &(_, _, offset) in &entries {
+        let small = match u32::try_from(offset) {
+            Ok(offset) if offset < 0x8000_0000 => offset,
+            _ => {
+                large.extend_from_slice(&offset.to_be_bytes());
+                0x8000_0000 | (large.len() / 8 - 1) as u32
+            }
+        };
+        idx.extend_from_slice(&small.to_be_bytes());
+    }
+    idx.extend_from_slice(&large);
+    idx.extend_from_slice(&checksum);
+    let idx_checksum = Sha1::digest(&idx);
+    idx.extend_from_slice(&idx_checksum);
+    fs::write(&temp, idx)?;
+    fs::rename(&temp, dir.join(format!("pack-{}.idx", name)))?;
+    Ok(name)
+}
@@ -0,0 +1,217 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::diff::{split_lines, Hunk, HunkLine};
+use crate::object::FileMode;
+
+
+// The changes a patch makes to one file. A missing old path means the file
+// is created, a missing new path that it is deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub old_mode: Option<FileMode>,
+    pub new_mode: Option<FileMode>,
+    pub hunks: Vec<Hunk>,
+    pub binary: bool,
+}
+
+impl FilePatch {
+    // The path the patch is reported under.
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or("")
+    }
+}
+
+// Strips the `a/` or `b/` prefix from a `---`/`+++` name, mapping /dev/null
+// to None.
+fn patch_path(name: &str) -> Option<String> {
+    let name = name.split('\t').next().unwrap_or(name).trim_end();
+    if name == "/dev/null" {
+        return None;
+    }
+    Some(name.split_once('/').map(|(_, rest)| rest).unwrap_or(name).to_string())
+}
+
+// The two names on a `diff --git a/<old> b/<new>` line. Names with spaces
+// are split in the middle when both halves agree, as they do for anything
+// but a rename.
+fn git_header_paths(rest: &str) -> Option<(String, String)> {
+    let half = rest.len() / 2;
+    if rest.len() % 2 == 1 && rest.as_bytes()[half] == b' ' {
+        let (old, new) = (&rest[..half], &rest[half + 1..]);
+        if old.get(2..) == new.get(2..) {
+            return Some((old[2..].to_string(), new[2..].to_string()));
+        }
+    }
+    let (old, new) = rest.rsplit_once(" b/")?;
+    Some((old.strip_prefix("a/")?.to_string(), new.to_string()))
+}
+
+fn parse_range(range: &str) -> Result<(usize, usize)> {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    Ok((start.parse()?, count.parse()?))
+}
+
+fn parse_hunk_header(line: &str) -> Result<Hunk> {
+    let malformed = || anyhow!("corrupt patch: bad hunk header '{}'", line);
+    let rest = line.strip_prefix("@@ -").ok_or_else(malformed)?;
+    let (ranges, function) = rest.split_once(" @@").ok_or_else(malformed)?;
+    let (old, new) = ranges.split_once(" +").ok_or_else(malformed)?;
+    let (old_start, old_count) = parse_range(old).map_err(|_| malformed())?;
+    let (new_start, new_count) = parse_range(new).map_err(|_| malformed())?;
+    Ok(Hunk { old_start, old_count, new_start, new_count, function: function.trim().to_string(), lines: Vec::new() })
+}
+
+// Parses a unified diff, with or without git's extended headers, into one
+// entry per file.
+pub fn parse_patch(data: &[u8]) -> Result<Vec<FilePatch>> {
+    let lines = split_lines(data);
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = String::from_utf8_lossy(lines[i]);
+        let line = line.trim_end_matches('\n');
+        i += 1;
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old, new) = git_header_paths(rest).ok_or_else(|| anyhow!("corrupt patch: bad git header '{}'", line))?;
+            patches.push(FilePatch { old_path: Some(old), new_path: Some(new), ..FilePatch::default() });
+            continue;
+        }
+        // A plain unified diff starts a new file at its `---` line.
+        if line.starts_with("--- ") && lines.get(i).is_some_and(|next| next.starts_with(b"+++ ")) {
+            let in_git_header = patches.last().is_some_and(|patch| patch.hunks.is_empty() && !patch.binary);
+            if !in_git_header {
+                patches.push(FilePatch::default());
+            }
+        }
+        let Some(patch) = patches.last_mut() else {
+            continue;
+        };
+
+        if let Some(mode) = line.strip_prefix("new file mode ") {
+            patch.old_path = None;
+            patch.new_mode = Some(mode.parse()?);
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            patch.new_path = None;
+            patch.old_mode = Some(mode.parse()?);
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            patch.old_mode = Some(mode.parse()?);
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            patch.new_mode = Some(mode.parse()?);
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            patch.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            patch.new_path = Some(path.to_string());
+        } else if let Some(mode) = line.strip_prefix("index ").and_then(|rest| rest.split_once(' ')).map(|(_, mode)| mode) {
+            patch.old_mode = Some(mode.parse()?);
+            patch.new_mode = patch.old_mode;
+        } else if let Some(name) = line.strip_prefix("--- ") {
+            patch.old_path = patch_path(name);
+        } else if let Some(name) = line.strip_prefix("+++ ") {
+            patch.new_path = patch_path(name);
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            patch.binary = true;
+        } else if line.starts_with("@@ ") {
+            let mut hunk = parse_hunk_header(line)?;
+            let (mut old_left, mut new_left) = (hunk.old_count, hunk.new_count);
+            while old_left > 0 || new_left > 0 {
+                let Some(&body) = lines.get(i) else {
+                    bail!("corrupt patch: hunk for '{}' ends early", patch.path());
+                };
+                i += 1;
+                let content = if body == b"\n" { body.to_vec() } else { body[1..].to_vec() };
+                match body.first() {
+                    // Some editors strip the space off empty context lines.
+                    Some(b' ') | Some(b'\n') => {
+                        hunk.lines.push(HunkLine::Context(content));
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    Some(b'-') => {
+                        hunk.lines.push(HunkLine::Delete(content));
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    Some(b'+') => {
+                        hunk.lines.push(HunkLine::Insert(content));
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    _ => bail!("corrupt patch: unexpected line in hunk for '{}'", patch.path()),
+                }
+                strip_missing_newline(&lines, &mut i, &mut hunk);
+            }
+            patch.hunks.push(hunk);
+        }
+    }
+    Ok(patches)
+}
+
+// `\ No newline at end of file` after a line means it has no newline.
+fn strip_missing_newline(lines: &[&[u8]], i: &mut usize, hunk: &mut Hunk) {
+    if !lines.get(*i).is_some_and(|line| line.starts_with(b"\\")) {
+        return;
+    }
+    *i += 1;
+    if let Some(HunkLine::Context(content) | HunkLine::Delete(content) | HunkLine::Insert(content)) = hunk.lines.last_mut() {
+        if content.ends_with(b"\n") {
+            content.pop();
+        }
+    }
+}
+
+// Applies hunks to `old`. Each hunk is placed where its context and deleted
+// lines match, searching outward from the line it names and carrying the
+// shift over to later hunks. Returns the hunks that found no match.
+pub fn apply_hunks(old: &[u8], hunks: &[Hunk]) -> std::result::Result<Vec<u8>, Vec<usize>> {
+    let lines = split_lines(old);
+    let mut out = Vec::new();
+    let mut failed = Vec::new();
+    let mut pos = 0;
+    let mut offset: isize = 0;
+
+    for (n, hunk) in hunks.iter().enumerate() {
+        let before: Vec<&[u8]> = hunk
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(content) | HunkLine::Delete(content) => Some(content.as_slice()),
+                HunkLine::Insert(_) => None,
+            })
+            .collect();
+        let start = if hunk.old_count == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = (start as isize + offset).max(pos as isize) as usize;
+        let fits = |at: usize| at >= pos && at + before.len() <= lines.len() && lines[at..at + before.len()] == before[..];
+
+        let last = lines.len().saturating_sub(before.len());
+        let found = (0..=lines.len()).find_map(|distance| {
+            let candidates = [expected.checked_add(distance), expected.checked_sub(distance)];
+            candidates.into_iter().flatten().find(|&at| at <= last && fits(at))
+        });
+        let Some(at) = found else {
+            failed.push(n);
+            continue;
+        };
+
+        for line in &lines[pos..at] {
+            out.extend_from_slice(line);
+        }
+        for line in &hunk.lines {
+            match line {
+                HunkLine::Context(content) | HunkLine::Insert(content) => out.extend_from_slice(content),
+                HunkLine::Delete(_) => {}
+            }
+        }
+        pos = at + before.len();
+        offset = at as isize - start as isize;
+    }
+    for line in &lines[pos..] {
+        out.extend_from_slice(line);
+    }
+
+    if failed.is_empty() {
+        Ok(out)
+    } else {
+        Err(failed)
+    }
+}
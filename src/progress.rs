@@ -0,0 +1,71 @@
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Set by the global -q/--quiet flag; silences progress and other feedback
+// written to stderr.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+
+// A counter on stderr that is redrawn in place, like git's
+// "Updating files: 40% (2/5)". It is only shown when stderr is a terminal.
+pub struct Progress {
+    title: String,
+    total: Option<usize>,
+    count: usize,
+    enabled: bool,
+    started: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(title: &str, total: Option<usize>) -> Progress {
+        Progress {
+            title: title.to_string(),
+            total,
+            count: 0,
+            enabled: !is_quiet() && std::io::stderr().is_terminal(),
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.set(self.count + 1);
+    }
+
+    pub fn set(&mut self, count: usize) {
+        self.count = count;
+        // Quick operations show nothing, and redrawing on every step would
+        // cost more than the work itself.
+        if self.enabled && self.started.elapsed() >= Duration::from_secs(1) && self.last_draw.is_none_or(|last| last.elapsed() >= Duration::from_millis(100)) {
+            self.draw("");
+            self.last_draw = Some(Instant::now());
+        }
+    }
+
+    fn draw(&self, suffix: &str) {
+        let line = match self.total {
+            Some(total) if total > 0 => format!("{}: {:>3}% ({}/{}){}", self.title, self.count * 100 / total, self.count, total, suffix),
+            _ => format!("{}: {}{}", self.title, self.count, suffix),
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{}", line);
+        let _ = stderr.flush();
+    }
+
+    pub fn finish(self) {
+        if self.enabled && self.last_draw.is_some() {
+            self.draw(", done.\n");
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::error::{from_anyhow, GitError};
+use crate::object::{read_raw, resolve_path_in_tree, GitCommit, GitTag, ObjectType, Signature};
+use crate::repository::Repository;
+
+
+fn is_hex(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// A subset of git's check-ref-format rules, enough to reject names that
+// would be ambiguous or unsafe as paths.
+pub fn is_valid_ref_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && !name.ends_with('.')
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && name != "@"
+        && !name.split('/').any(|part| part.starts_with('.'))
+        && !name.chars().any(|c| c.is_ascii_control() || " ~^:?*[\\".contains(c))
+}
+
+fn read_packed_ref(repo: &Repository, name: &str) -> Option<String> {
+    let packed = fs::read_to_string(repo.repo_path("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, refname)| *refname == name)
+        .map(|(sha, _)| sha.to_string())
+}
+
+// Follows a ref (loose or packed) down to the SHA it finally points at.
+pub fn resolve_ref(repo: &Repository, name: &str) -> Result<Option<String>> {
+    let mut name = name.to_string();
+    for _ in 0..10 {
+        let path = repo.repo_path(&name);
+        let content = if path.is_file() {
+            fs::read_to_string(&path)?.trim_end().to_string()
+        } else {
+            match read_packed_ref(repo, &name) {
+                Some(sha) => return Ok(Some(sha)),
+                None => return Ok(None),
+            }
+        };
+
+        match content.strip_prefix("ref: ") {
+            Some(target) => name = target.to_string(),
+            None => return Ok(Some(content)),
+        }
+    }
+    bail!("Too many levels of symbolic refs")
+}
+
+fn collect_loose_refs(repo: &Repository, dir: &str, out: &mut Vec<String>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(repo.repo_path(dir)) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = format!("{}/{}", dir, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            collect_loose_refs(repo, &name, out)?;
+        } else if !name.ends_with(".lock") {
+            out.push(name);
+        }
+    }
+    Ok(())
+}
+
+// Every ref under refs/, loose or packed, with the SHA it resolves to,
+// sorted by name. Loose refs shadow packed ones of the same name.
+pub fn list_refs(repo: &Repository) -> Result<Vec<(String, String)>> {
+    let mut names = Vec::new();
+    collect_loose_refs(repo, "refs", &mut names)?;
+    if let Ok(packed) = fs::read_to_string(repo.repo_path("packed-refs")) {
+        names.extend(
+            packed
+                .lines()
+                .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+                .filter_map(|line| line.split_once(' '))
+                .map(|(_, name)| name.to_string()),
+        );
+    }
+    names.sort();
+    names.dedup();
+
+    let mut refs = Vec::new();
+    for name in names {
+        if let Some(sha) = resolve_ref(repo, &name)? {
+            refs.push((name, sha));
+        }
+    }
+    Ok(refs)
+}
+
+// Returns the target of a symbolic ref, or None if the ref holds a SHA.
+pub fn read_symbolic_ref(repo: &Repository, name: &str) -> Result<Option<String>> {
+    let path = repo.repo_path(name);
+    if !path.is_file() {
+        bail!("No such ref {}", name);
+    }
+    let content = fs::read_to_string(&path)?;
+    Ok(content.trim_end().strip_prefix("ref: ").map(|target| target.to_string()))
+}
+
+fn objects_with_prefix(repo: &Repository, prefix: &str) -> Result<Vec<String>> {
+    let prefix = prefix.to_lowercase();
+    let mut found = Vec::new();
+    for objects in repo.object_dirs() {
+        let dir = objects.join(&prefix[..2]);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            let sha = format!("{}{}", &prefix[..2], name);
+            if name.starts_with(&prefix[2..]) && !found.contains(&sha) {
+                found.push(sha);
+            }
+        }
+    }
+    for pack in repo.packs()? {
+        for sha in pack.shas().filter(|sha| sha.starts_with(&prefix)) {
+            if !found.contains(&sha) {
+                found.push(sha);
+            }
+        }
+    }
+    Ok(found)
+}
+
+// The objects `name` could mean. Failures of the name itself, such as a
+// path missing from the tree or a reflog too short, are bad revisions;
+// anything else is passed on as it is.
+fn resolve_name(repo: &Repository, name: &str) -> Result<Vec<String>, GitError> {
+    if let Some((rev, path)) = name.split_once(':').filter(|(rev, _)| !rev.is_empty()) {
+        let tree = find_object(repo, rev, Some(ObjectType::Tree))?;
+        if path.is_empty() {
+            return Ok(vec![tree]);
+        }
+        return match resolve_path_in_tree(repo, &tree, path)? {
+            Some(leaf) => Ok(vec![leaf.sha]),
+            None => Err(GitError::BadRevision(format!("path '{}' does not exist in '{}'", path, rev))),
+        };
+    }
+
+    // `<rev>^{<type>}` peels to the given type and `<rev>^{}` peels tags only.
+    if let Some((rev, wanted)) = name.strip_suffix('}').and_then(|rest| rest.rsplit_once("^{")) {
+        let sha = find_object(repo, rev, None)?;
+        return Ok(vec![match wanted {
+            "" => peel_tags(repo, &sha)?,
+            "object" => sha,
+            _ => peel(repo, &sha, ObjectType::from_str(wanted).map_err(|e| GitError::BadRevision(e.to_string()))?, name)?,
+        }]);
+    }
+
+    // `<ref>@{<n>}` is the value the ref had n updates ago.
+    if let Some((refname, n)) = name.strip_suffix('}').and_then(|rest| rest.rsplit_once("@{")) {
+        let n: usize = n.parse().map_err(|_| GitError::BadRevision(format!("invalid reflog index '{}'", n)))?;
+        let full = reflog_name(repo, refname).map_err(|e| GitError::BadRevision(e.to_string()))?;
+        let entries = read_reflog(repo, &full).map_err(from_anyhow)?;
+        let Some(entry) = entries.iter().rev().nth(n) else {
+            return Err(GitError::BadRevision(format!("log for '{}' only has {} entries", refname, entries.len())));
+        };
+        return Ok(vec![entry.new.clone()]);
+    }
+
+    if name == "HEAD" || name == "@" {
+        return Ok(resolve_ref(repo, "HEAD").map_err(from_anyhow)?.into_iter().collect());
+    }
+
+    let mut candidates = Vec::new();
+    if is_hex(name) && name.len() >= 4 && name.len() <= 40 {
+        candidates.extend(objects_with_prefix(repo, name).map_err(from_anyhow)?);
+    }
+    for refname in [name.to_string(), format!("refs/{}", name), format!("refs/tags/{}", name), format!("refs/heads/{}", name), format!("refs/remotes/{}", name)] {
+        if !refname.starts_with("refs/") {
+            continue;
+        }
+        if let Some(sha) = resolve_ref(repo, &refname).map_err(from_anyhow)? {
+            candidates.push(sha);
+        }
+    }
+
+    candidates.sort();
+    candidates.dedup();
+    Ok(candidates)
+}
+
+// Resolves a name to an object SHA, dereferencing tags and commits until an
+// object of the requested type is reached.
+pub fn find_object(repo: &Repository, name: &str, type_: Option<ObjectType>) -> Result<String, GitError> {
+    let mut candidates = resolve_name(repo, name)?;
+    let sha = match candidates.len() {
+        0 => return Err(GitError::BadRevision(format!("No such reference {}", name))),
+        1 => candidates.remove(0),
+        _ => return Err(GitError::AmbiguousPrefix { name: name.to_string(), candidates }),
+    };
+
+    match type_ {
+        Some(wanted) => peel(repo, &sha, wanted, name),
+        None => Ok(sha),
+    }
+}
+
+// Follows tags, and commits to their trees, until an object of type `wanted`
+// is reached. `name` is only used for the error message.
+pub fn peel(repo: &Repository, sha: &str, wanted: ObjectType, name: &str) -> Result<String, GitError> {
+    let mut sha = sha.to_string();
+    loop {
+        let (actual, data) = read_raw(repo, &sha)?;
+        if actual == wanted {
+            return Ok(sha);
+        }
+        sha = match actual {
+            ObjectType::Tag => GitTag::deserialize(&data).and_then(|tag| Ok(tag.object()?.to_string())),
+            ObjectType::Commit if wanted == ObjectType::Tree => GitCommit::deserialize(&data).and_then(|commit| Ok(commit.tree()?.to_string())),
+            _ => return Err(GitError::BadRevision(format!("{} is a {}, not a {}", name, actual, wanted))),
+        }
+        .map_err(|e| e.in_object(&sha))?;
+    }
+}
+
+fn peel_tags(repo: &Repository, sha: &str) -> Result<String, GitError> {
+    let mut sha = sha.to_string();
+    loop {
+        let (actual, data) = read_raw(repo, &sha)?;
+        if actual != ObjectType::Tag {
+            return Ok(sha);
+        }
+        sha = GitTag::deserialize(&data).and_then(|tag| Ok(tag.object()?.to_string())).map_err(|e| e.in_object(&sha))?;
+    }
+}
+
+// Writes a ref through a lock file that is renamed into place, so readers
+// never observe a partially written ref.
+fn write_ref_content(repo: &Repository, name: &str, content: &str) -> Result<()> {
+    let path = repo.repo_path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let lock = PathBuf::from(format!("{}.lock", path.display()));
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock)
+        .with_context(|| format!("Unable to create '{}'", lock.display()))?;
+    let written = file.write_all(content.as_bytes());
+    drop(file);
+    if let Err(e) = written {
+        fs::remove_file(&lock)?;
+        return Err(e.into());
+    }
+    fs::rename(&lock, &path)?;
+    Ok(())
+}
+
+// Points a ref at `sha`, recording the move in its reflog. Updating the
+// branch HEAD is on is logged for HEAD too, as git does.
+pub fn update_ref(repo: &Repository, name: &str, sha: &str, message: &str) -> Result<()> {
+    let old = resolve_ref(repo, name)?;
+    write_ref_content(repo, name, &format!("{}\n", sha))?;
+    log_ref_update(repo, name, old.as_deref(), sha, message)?;
+    if name != "HEAD" && repo.repo_path("HEAD").is_file() && read_symbolic_ref(repo, "HEAD")?.as_deref() == Some(name) {
+        log_ref_update(repo, "HEAD", old.as_deref(), sha, message)?;
+    }
+    Ok(())
+}
+
+// Moves whatever HEAD points at: the current branch, or HEAD itself when
+// it is detached.
+pub fn update_head(repo: &Repository, sha: &str, message: &str) -> Result<()> {
+    match read_symbolic_ref(repo, "HEAD")? {
+        Some(branch) => update_ref(repo, &branch, sha, message),
+        None => update_ref(repo, "HEAD", sha, message),
+    }
+}
+
+pub fn write_symbolic_ref(repo: &Repository, name: &str, target: &str) -> Result<()> {
+    write_ref_content(repo, name, &format!("ref: {}\n", target))
+}
+
+// Deletes a ref, removing it from packed-refs as well as the loose ref.
+pub fn delete_ref(repo: &Repository, name: &str) -> Result<()> {
+    let path = repo.repo_path(name);
+    let loose = path.is_file();
+    let packed = read_packed_ref(repo, name).is_some();
+    if !loose && !packed {
+        bail!("ref {} does not exist", name);
+    }
+
+    if packed {
+        let content = fs::read_to_string(repo.repo_path("packed-refs"))?;
+        let mut kept = String::new();
+        let mut removed = false;
+        for line in content.lines() {
+            // A `^` line holds the peeled value of the ref just before it.
+            if !line.starts_with('^') {
+                removed = line.split_once(' ').is_some_and(|(_, refname)| refname == name);
+            }
+            if !removed {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+        write_ref_content(repo, "packed-refs", &kept)?;
+    }
+
+    let _ = fs::remove_file(reflog_path(repo, name));
+    if loose {
+        fs::remove_file(&path)?;
+        let refs = repo.repo_path("refs");
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d == refs || fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+    }
+    Ok(())
+}
+
+
+pub const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone)]
+pub struct ReflogEntry {
+    pub old: String,
+    pub new: String,
+    pub committer: Signature,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    pub fn parse(line: &str) -> Result<ReflogEntry> {
+        let (head, message) = line.split_once('\t').unwrap_or((line, ""));
+        let mut fields = head.splitn(3, ' ');
+        let malformed = || anyhow!("Malformed reflog entry '{}'", line);
+        let (Some(old), Some(new), Some(committer)) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(malformed());
+        };
+        let committer = Signature::parse(committer).map_err(|_| malformed())?;
+        Ok(ReflogEntry { old: old.to_string(), new: new.to_string(), committer, message: message.to_string() })
+    }
+}
+
+impl std::fmt::Display for ReflogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}\t{}", self.old, self.new, self.committer, self.message)
+    }
+}
+
+// Where the log of `name` lives. Going through `repo_path` with the whole
+// name keeps a linked worktree's logs/HEAD in its own git dir.
+fn reflog_path(repo: &Repository, name: &str) -> PathBuf {
+    repo.repo_path(format!("logs/{}", name))
+}
+
+// Git only keeps logs for HEAD, branches, remotes and notes unless a log
+// has already been started for the ref.
+fn should_log(repo: &Repository, name: &str) -> bool {
+    if repo.config_get("core", "logallrefupdates").is_some_and(|v| v.eq_ignore_ascii_case("false")) {
+        return false;
+    }
+    name == "HEAD" || ["refs/heads/", "refs/remotes/", "refs/notes/"].iter().any(|prefix| name.starts_with(prefix)) || reflog_path(repo, name).is_file()
+}
+
+// Appends an entry to the reflog of `name`.
+pub fn log_ref_update(repo: &Repository, name: &str, old: Option<&str>, new: &str, message: &str) -> Result<()> {
+    if !should_log(repo, name) {
+        return Ok(());
+    }
+    let entry = ReflogEntry {
+        old: old.unwrap_or(NULL_SHA).to_string(),
+        new: new.to_string(),
+        committer: Signature::identity(repo, "COMMITTER")?,
+        message: message.lines().next().unwrap_or("").to_string(),
+    };
+    let path = reflog_path(repo, name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", entry)?;
+    Ok(())
+}
+
+// The reflog of `name`, oldest entry first. A ref without a log has none.
+pub fn read_reflog(repo: &Repository, name: &str) -> Result<Vec<ReflogEntry>> {
+    let Ok(content) = fs::read_to_string(reflog_path(repo, name)) else {
+        return Ok(Vec::new());
+    };
+    content.lines().filter(|line| !line.is_empty()).map(ReflogEntry::parse).collect()
+}
+
+fn collect_reflogs(repo: &Repository, dir: &str, out: &mut Vec<String>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(repo.repo_path(dir)) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = format!("{}/{}", dir, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            collect_reflogs(repo, &name, out)?;
+        } else {
+            out.push(name);
+        }
+    }
+    Ok(())
+}
+
+// The names of all refs that have a reflog, such as HEAD and refs/heads/main.
+pub fn list_reflogs(repo: &Repository) -> Result<Vec<String>> {
+    let mut logs = Vec::new();
+    collect_reflogs(repo, "logs", &mut logs)?;
+    // The shared logs directory holds the main worktree's HEAD log, which
+    // need not be ours.
+    let mut names: Vec<String> = logs.into_iter().filter_map(|log| log.strip_prefix("logs/").map(String::from)).filter(|name| name != "HEAD").collect();
+    if reflog_path(repo, "HEAD").is_file() {
+        names.push("HEAD".to_string());
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn write_reflog(repo: &Repository, name: &str, entries: &[ReflogEntry]) -> Result<()> {
+    let content: String = entries.iter().map(|entry| format!("{}\n", entry)).collect();
+    write_ref_content(repo, &format!("logs/{}", name), &content)
+}
+
+// Removes the newest entry from a ref's log and points the ref back where
+// the previous entry left it, deleting the ref along with an emptied log.
+// This is how stash entries are dropped.
+pub fn pop_reflog(repo: &Repository, name: &str) -> Result<Option<ReflogEntry>> {
+    let mut entries = read_reflog(repo, name)?;
+    let Some(popped) = entries.pop() else {
+        return Ok(None);
+    };
+    match entries.last() {
+        Some(previous) => {
+            write_reflog(repo, name, &entries)?;
+            write_ref_content(repo, name, &format!("{}\n", previous.new))?;
+        }
+        None => delete_ref(repo, name)?,
+    }
+    Ok(Some(popped))
+}
+
+// Expands a name as used in `<name>@{n}` to the ref whose log it means. An
+// empty name is the current branch.
+pub fn reflog_name(repo: &Repository, name: &str) -> Result<String> {
+    if name.is_empty() {
+        return Ok(read_symbolic_ref(repo, "HEAD")?.unwrap_or_else(|| "HEAD".to_string()));
+    }
+    for candidate in [name.to_string(), format!("refs/{}", name), format!("refs/tags/{}", name), format!("refs/heads/{}", name), format!("refs/remotes/{}", name)] {
+        if (candidate == "HEAD" || candidate.starts_with("refs/")) && reflog_path(repo, &candidate).is_file() {
+            return Ok(candidate);
+        }
+    }
+    bail!("no reflog for '{}'", name)
+}
@@ -1,7 +1,16 @@
-use core::panic;
 use std::{path::PathBuf, fs::File, io::Write};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
 use std::path::Path;
 
+use anyhow::{anyhow, Result};
+
+use crate::error::GitError;
+use crate::object::{parse_from_bytes, read_commit, read_raw, GitObject};
+use crate::pack::{pack_indexes, PackIndex};
+use crate::refs::{read_symbolic_ref, resolve_ref};
+use crate::utils::{path_from_os, path_to_os};
+
 
 pub fn vec_to_pathbuf<T: AsRef<Path>>(paths: Vec<T>) -> PathBuf {
     let mut pathbuf = PathBuf::new();
@@ -12,6 +21,28 @@ pub fn vec_to_pathbuf<T: AsRef<Path>>(paths: Vec<T>) -> PathBuf {
 }
 
 
+// The git directory a `.git` entry stands for: the directory itself, or for
+// a `.git` file as used by linked worktrees and submodules, the directory
+// named by its `gitdir:` line, relative to the file.
+pub fn resolve_gitdir(dotgit: &Path) -> Option<PathBuf> {
+    if dotgit.is_dir() {
+        return Some(dotgit.to_path_buf());
+    }
+    let content = std::fs::read_to_string(dotgit).ok()?;
+    let target = content.lines().next()?.strip_prefix("gitdir:")?.trim();
+    Some(dotgit.parent()?.join(target))
+}
+
+// Whether a path inside the git directory is shared by all worktrees, and
+// so lives in the common directory, rather than being per worktree.
+fn is_shared_path(path: &Path) -> bool {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let first = path.split('/').next().unwrap_or("");
+    let shared = ["branches", "config", "description", "hooks", "info", "logs", "objects", "packed-refs", "refs", "remotes", "shallow", "worktrees"];
+    let private = ["logs/HEAD", "refs/bisect", "refs/worktree", "refs/rewritten"];
+    shared.contains(&first) && !private.iter().any(|p| path == *p || path.starts_with(&format!("{}/", p)))
+}
+
 pub fn find_repo<P: AsRef<Path>>(p: P) -> Option<PathBuf> {
     let p = p.as_ref();
     if p.join(".git").exists() {
@@ -24,92 +55,204 @@ pub fn find_repo<P: AsRef<Path>>(p: P) -> Option<PathBuf> {
 }
 
 
+// Where HEAD points. Branch names are full ref names such as refs/heads/main.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadState {
+    // On a branch that has no commits yet.
+    Unborn { name: String },
+    Detached(String),
+    Branch { name: String, sha: String },
+}
+
+impl HeadState {
+    pub fn sha(&self) -> Option<&str> {
+        match self {
+            HeadState::Unborn { .. } => None,
+            HeadState::Detached(sha) | HeadState::Branch { sha, .. } => Some(sha),
+        }
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        match self {
+            HeadState::Unborn { name } | HeadState::Branch { name, .. } => Some(name),
+            HeadState::Detached(_) => None,
+        }
+    }
+}
+
+
+// What history walks need from a commit: its parents and committer time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitNode {
+    pub parents: Vec<String>,
+    pub time: i64,
+}
+
 #[derive(Debug)]
 pub struct Repository {
     pub worktree: PathBuf,
     pub gitdir: PathBuf,
+    // Where the parts shared between worktrees live; the same as `gitdir`
+    // except in a linked worktree.
+    pub commondir: PathBuf,
     config: configparser::ini::Ini,
-    initialised: bool
+    global_config: configparser::ini::Ini,
+    initialised: bool,
+    // Commits already read by a walk, so later walks in the same process
+    // don't read and parse them again.
+    commit_graph: RefCell<HashMap<String, CommitNode>>,
+    // How many objects have been read in full, for spotting redundant reads.
+    objects_read: Cell<usize>,
+    // The pack indexes of every object directory, read on first use.
+    packs: OnceCell<Vec<PackIndex>>,
 }
 
 impl Repository {
 
     fn repo_path_vec<T: AsRef<Path>>(&self, path: Vec<T>) -> PathBuf {
-        let mut new_path = self.gitdir.clone();
-        for p in path {
-            new_path = new_path.join(p);
+        self.repo_path(vec_to_pathbuf(path))
+    }
+
+    pub fn repo_path<T: AsRef<Path>>(&self, path: T) -> PathBuf {
+        if is_shared_path(path.as_ref()) {
+            self.commondir.join(path)
+        } else {
+            self.gitdir.join(path)
         }
-        new_path
     }
 
-    fn repo_path<T: AsRef<Path>>(&self, path: T) -> PathBuf {
-        let new_path = self.gitdir.clone();
-        new_path.join(path)
+    pub fn object_path(&self, sha: &str) -> PathBuf {
+        self.repo_path_vec(vec!["objects", &sha[..2.min(sha.len())], &sha[2.min(sha.len())..]])
     }
 
-    fn repo_create_file<T: AsRef<Path>>(&self, path: T) -> Result<PathBuf, String> {
-        let path = self.repo_path(path.as_ref());
-        if path.exists() {
-            return Ok(path);
+    // The object directories to search: our own first, then any listed in
+    // objects/info/alternates.
+    pub fn object_dirs(&self) -> Vec<PathBuf> {
+        let objects = self.repo_path("objects");
+        let mut dirs = vec![objects.clone()];
+        if let Ok(content) = std::fs::read_to_string(objects.join("info").join("alternates")) {
+            for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                dirs.push(objects.join(line));
+            }
         }
+        dirs
+    }
 
-        let parent = if path.is_file() {
-            path.parent().unwrap().to_path_buf()
-        } else {
-            path.clone()
-        };
-
-        if !parent.exists() {
-            std::fs::create_dir_all(parent).unwrap();
+    // Where the loose object `sha` is stored, if it is anywhere we can see.
+    pub fn find_object_file(&self, sha: &str) -> Option<PathBuf> {
+        if sha.len() != 40 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
         }
+        self.object_dirs().into_iter().map(|dir| dir.join(&sha[..2]).join(&sha[2..])).find(|path| path.is_file())
+    }
 
-        if path.is_file() {
-            File::create(path.clone()).unwrap();
+    // The packs in our object directory and in alternates. They are listed
+    // once, so a pack written later by this process isn't seen.
+    pub fn packs(&self) -> Result<&[PackIndex], GitError> {
+        if self.packs.get().is_none() {
+            let mut packs = Vec::new();
+            for dir in self.object_dirs() {
+                packs.extend(pack_indexes(&dir.join("pack"))?);
+            }
+            let _ = self.packs.set(packs);
         }
-        Ok(path.clone())
+        Ok(self.packs.get().expect("packs were just listed"))
     }
 
-    fn repo_create_file_vec<T: AsRef<Path>>(&self, path: Vec<T>) -> Result<PathBuf, String> {
-        let path = self.repo_path_vec(path);
-        if path.exists() {
-            return Ok(path);
-        }
+    // The pack holding `sha` and where in it the object starts.
+    pub fn find_packed(&self, sha: &str) -> Result<Option<(&PackIndex, u64)>, GitError> {
+        Ok(self.packs()?.iter().find_map(|pack| pack.find(sha).map(|offset| (pack, offset))))
+    }
 
-        let parent = if path.is_file() {
-            path.parent().unwrap().to_path_buf()
-        } else {
-            path.clone()
-        };
+    // Whether `sha` is stored anywhere we can read it: loose or in a pack,
+    // here or in an alternate. A pack index that can't be read counts as
+    // not having the object.
+    pub fn object_exists(&self, sha: &str) -> bool {
+        self.find_object_file(sha).is_some() || self.find_packed(sha).is_ok_and(|found| found.is_some())
+    }
 
-        if !parent.exists() {
-            std::fs::create_dir_all(parent).unwrap();
+    // The parents and committer time of commit `sha`, read once and then
+    // served from the in-memory commit graph.
+    pub fn commit_node(&self, sha: &str) -> Result<CommitNode> {
+        if let Some(node) = self.commit_graph.borrow().get(sha) {
+            return Ok(node.clone());
         }
+        let commit = read_commit(self, sha)?;
+        let node = CommitNode { parents: commit.parents().into_iter().map(String::from).collect(), time: commit.committer()?.time };
+        self.commit_graph.borrow_mut().insert(sha.to_string(), node.clone());
+        Ok(node)
+    }
 
-        if path.is_file() {
-            File::create(path.clone()).unwrap();
-        }
+    // The number of objects read in full through this repository so far.
+    pub fn objects_read(&self) -> usize {
+        self.objects_read.get()
+    }
+
+    pub(crate) fn count_object_read(&self) {
+        self.objects_read.set(self.objects_read.get() + 1);
+    }
+
+    pub fn head(&self) -> Result<HeadState> {
+        let sha = resolve_ref(self, "HEAD")?;
+        Ok(match (read_symbolic_ref(self, "HEAD")?, sha) {
+            (Some(name), Some(sha)) => HeadState::Branch { name, sha },
+            (Some(name), None) => HeadState::Unborn { name },
+            (None, Some(sha)) => HeadState::Detached(sha),
+            (None, None) => return Err(anyhow!("HEAD is empty")),
+        })
+    }
+
+    // The commit HEAD points at, or None on an unborn branch.
+    pub fn head_commit(&self) -> Result<Option<String>> {
+        Ok(self.head()?.sha().map(String::from))
+    }
+
+    pub fn read_object(&self, sha: &str) -> Result<Box<dyn GitObject>, GitError> {
+        let (type_, data) = read_raw(self, sha)?;
+        parse_from_bytes(type_, data).map_err(|e| e.in_object(sha))
+    }
+
+    // Creates a directory inside the git dir along with any missing parents.
+    fn repo_create_dir<T: AsRef<Path>>(&self, path: T) -> Result<PathBuf, String> {
+        let path = self.repo_path(path.as_ref());
+        std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create directory {:?}: {}", path, e))?;
+        Ok(path)
+    }
+
+    fn repo_create_dir_vec<T: AsRef<Path>>(&self, path: Vec<T>) -> Result<PathBuf, String> {
+        let path = self.repo_path_vec(path);
+        std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create directory {:?}: {}", path, e))?;
         Ok(path)
     }
 
-    pub fn create(&mut self) -> Result<(), String> {
-        if !self.worktree.exists() {
-            std::fs::create_dir(self.worktree.clone()).expect("Failed to create directory");
+    // Creates whatever parts of the repository are missing, leaving existing
+    // files such as the config alone. Returns true if the git directory
+    // already existed.
+    pub fn create(&mut self) -> Result<bool, String> {
+        let existed = self.gitdir.is_dir();
+        std::fs::create_dir_all(&self.worktree).map_err(|e| format!("Failed to create directory {:?}: {}", self.worktree, e))?;
+        self.repo_create_dir("branches")?;
+        self.repo_create_dir("objects")?;
+        self.repo_create_dir_vec(vec!["refs", "tags"])?;
+        self.repo_create_dir_vec(vec!["refs", "heads"])?;
+
+        let defaults = [
+            ("description", "Unnamed repository; edit this file 'description' to name the repository.\n".to_string()),
+            ("HEAD", "ref: refs/heads/master\n".to_string()),
+            ("config", self.default_config().writes()),
+        ];
+        for (name, content) in defaults {
+            let path = self.repo_path(name);
+            if path.exists() {
+                continue;
+            }
+            let mut file = File::create(&path).map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+            file.write_all(content.as_bytes()).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
         }
-        self.repo_create_file("branches")?;
-        self.repo_create_file("objects")?;
-        self.repo_create_file_vec(vec!["refs", "tags"])?;
-        self.repo_create_file_vec(vec!["refs", "heads"])?;
-        
-        let mut description = File::create(self.repo_path("description")).expect("Failed to create description file.");
-        description.write_all("Unnamed repository; edit this file 'description' to name the repository.\n".as_bytes()).expect("Failed to write description file");
-        
-        let mut head = File::create(self.repo_path("HEAD")).unwrap();
-        head.write_all("ref: refs/heads/master\n".as_bytes()).expect("Failed to write HEAD file.");
-
-        let mut config = File::create(self.repo_path("config")).unwrap();
-        config.write_all(self.default_config().writes().as_bytes()).expect("Failed to write config file.");
+
+        let _ = self.read_config();
         self.initialised = true;
-        Ok(())
+        Ok(existed)
     }
 
     fn read_config(&mut self) -> Result<(), String>{
@@ -122,6 +265,24 @@ impl Repository {
         Err(format!("Failed to read config file '{:?}'", cf))
     }
 
+    fn read_global_config(&mut self) {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let xdg = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| home.as_ref().map(|h| h.join(".config")));
+
+        let candidates = [xdg.map(|x| x.join("git").join("config")), home.map(|h| h.join(".gitconfig"))];
+        for path in candidates.into_iter().flatten() {
+            if path.is_file() {
+                let _ = self.global_config.load_and_append(path);
+            }
+        }
+    }
+
+    // Looks a value up in the repository config, falling back to the user's
+    // global config.
+    pub fn config_get(&self, section: &str, key: &str) -> Option<String> {
+        self.config.get(section, key).or_else(|| self.global_config.get(section, key))
+    }
+
     fn default_config(&self) -> configparser::ini::Ini {
         let mut config = configparser::ini::Ini::new();
         config.set("core", "repositoryformatversion", Some("0".to_string()));
@@ -131,18 +292,81 @@ impl Repository {
     }
 
     pub fn new(path: PathBuf) -> Repository {
-        let git_path: PathBuf = path.join(".git");
-        let is_initialised = git_path.exists() && git_path.is_dir();
-        let mut repo = Repository { worktree: path.clone(), gitdir: git_path.clone(), initialised: is_initialised, config: configparser::ini::Ini::new() };
-
-        if repo.read_config().is_ok() {
-             if let Ok(v) = repo.config.getint("core", "repositoryformatversion") {
-                if v.unwrap() != 0 {
-                    panic!("Unsupported repositoryformatversion '{:?}'", v);
+        let git_path: PathBuf = resolve_gitdir(&path.join(".git")).unwrap_or_else(|| path.join(".git"));
+        let common_path = match std::fs::read_to_string(git_path.join("commondir")) {
+            Ok(content) => git_path.join(content.trim()),
+            Err(_) => git_path.clone(),
+        };
+        let is_initialised = git_path.is_dir();
+        let mut repo = Repository { worktree: path.clone(), gitdir: git_path, commondir: common_path, initialised: is_initialised, config: configparser::ini::Ini::new(), global_config: configparser::ini::Ini::new(), commit_graph: RefCell::default(), objects_read: Cell::new(0), packs: OnceCell::new() };
+        repo.read_global_config();
+        let _ = repo.read_config();
+        repo
+    }
+
+    // Checks that the repository's format is one this crate can work with.
+    // Version 1 adds `extensions.*`, every one of which must be understood;
+    // version 0 repositories ignore them like git does.
+    pub fn check_format(&self) -> Result<(), GitError> {
+        let version = match self.config.get("core", "repositoryformatversion") {
+            Some(v) => v.trim().parse::<u32>().map_err(|_| GitError::UnsupportedFormat(format!("bad repositoryformatversion '{}'", v)))?,
+            None => 0,
+        };
+        if version > 1 {
+            return Err(GitError::UnsupportedFormat(format!("Expected git repo version <= 1, found {}", version)));
+        }
+        if version == 0 {
+            return Ok(());
+        }
+
+        let extensions = self.config.get_map_ref().get("extensions").cloned().unwrap_or_default();
+        let mut unknown = Vec::new();
+        for (name, value) in &extensions {
+            match name.as_str() {
+                "noop" => {}
+                "objectformat" => {
+                    let format = value.as_deref().unwrap_or("");
+                    if !format.eq_ignore_ascii_case("sha1") {
+                        return Err(GitError::UnsupportedFormat(format!("unsupported object format '{}'", format)));
+                    }
                 }
-             }
+                _ => unknown.push(name.clone()),
+            }
+        }
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(GitError::UnsupportedFormat(format!("unknown repository extension found:\n\t{}", unknown.join("\n\t"))));
         }
+        Ok(())
+    }
 
-        repo
+    // The location on disk of a worktree-relative path from the index or a tree.
+    pub fn worktree_file(&self, path: &str) -> PathBuf {
+        self.worktree.join(path_to_os(path))
+    }
+
+    // Turns a path given on the command line into a path relative to the
+    // worktree root, using forward slashes like the index and trees do.
+    pub fn worktree_path<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let mut absolute = PathBuf::new();
+        for component in std::env::current_dir()?.join(path).components() {
+            match component {
+                std::path::Component::ParentDir => {
+                    absolute.pop();
+                }
+                std::path::Component::CurDir => {}
+                other => absolute.push(other),
+            }
+        }
+        let relative = absolute.strip_prefix(&self.worktree).map_err(|_| anyhow!("{:?} is outside repository", absolute))?;
+        let parts: Vec<String> = relative.components().map(|c| path_from_os(c.as_os_str())).collect();
+        Ok(parts.join("/"))
+    }
+
+    pub fn find<P: AsRef<Path>>(path: P) -> Result<Repository, GitError> {
+        let worktree = find_repo(path.as_ref()).ok_or(GitError::NotARepository)?;
+        let repo = Repository::new(worktree);
+        repo.check_format()?;
+        Ok(repo)
     }
 }
@@ -1,4 +1,5 @@
-use std::path::Path;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
 #[cfg(not(target_os = "windows"))]
 pub fn adjust_canonicalization<P: AsRef<Path>>(p: &P) -> String {
@@ -15,3 +16,100 @@ pub fn adjust_canonicalization<P: AsRef<Path>>(p: &P) -> String {
         p
     }
 }
+
+// Paths in trees and the index are raw bytes that need not be UTF-8. They are
+// kept as `String`s with every byte of an invalid sequence mapped to a
+// private use character, so that encoding them again gives back the exact
+// bytes that were read. Real characters in that range are escaped byte by
+// byte as well, so they can't be mistaken for an escape.
+const ESCAPE_BASE: u32 = 0x10ff00;
+
+fn escape_bytes(out: &mut String, bytes: &[u8]) {
+    out.extend(bytes.iter().map(|&b| char::from_u32(ESCAPE_BASE | b as u32).unwrap()));
+}
+
+pub fn decode_path(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match escaped_byte(c) {
+                Some(_) => escape_bytes(&mut out, c.encode_utf8(&mut [0; 4]).as_bytes()),
+                None => out.push(c),
+            }
+        }
+        escape_bytes(&mut out, chunk.invalid());
+    }
+    out
+}
+
+fn escaped_byte(c: char) -> Option<u8> {
+    (c as u32).checked_sub(ESCAPE_BASE).filter(|&b| b >= 0x80).map(|b| b as u8)
+}
+
+pub fn encode_path(path: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(path.len());
+    for c in path.chars() {
+        match escaped_byte(c) {
+            Some(b) => out.push(b),
+            None => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    out
+}
+
+// Replaces escaped bytes for printing.
+pub fn display_path(path: &str) -> String {
+    path.chars().map(|c| if escaped_byte(c).is_some() { char::REPLACEMENT_CHARACTER } else { c }).collect()
+}
+
+// Quotes a path the way git does when it holds control characters, quotes,
+// backslashes or any byte outside ASCII: wrapped in double quotes with C
+// escapes, non-ASCII bytes in octal. Short status output also quotes paths
+// with spaces, which `quote_spaces` asks for. Other paths are printed as
+// they are.
+pub fn quote_path(path: &str, quote_spaces: bool) -> String {
+    let bytes = encode_path(path);
+    if !bytes.iter().any(|&b| !(0x20..0x7f).contains(&b) || b == b'"' || b == b'\\' || (quote_spaces && b == b' ')) {
+        return path.to_string();
+    }
+    let mut out = String::from("\"");
+    for b in bytes {
+        match b {
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            b'\t' => out.push_str("\\t"),
+            b'\n' => out.push_str("\\n"),
+            0x0b => out.push_str("\\v"),
+            0x0c => out.push_str("\\f"),
+            b'\r' => out.push_str("\\r"),
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b if !(0x20..0x7f).contains(&b) => out.push_str(&format!("\\{:03o}", b)),
+            b => out.push(b as char),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(unix)]
+pub fn path_to_os(path: &str) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(&encode_path(path)))
+}
+
+#[cfg(not(unix))]
+pub fn path_to_os(path: &str) -> PathBuf {
+    PathBuf::from(display_path(path))
+}
+
+#[cfg(unix)]
+pub fn path_from_os(name: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    decode_path(name.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn path_from_os(name: &OsStr) -> String {
+    name.to_string_lossy().to_string()
+}
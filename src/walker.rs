@@ -0,0 +1,149 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::object::{read_commit, read_raw, GitCommit, GitTag, GitTree, ObjectType};
+use crate::repository::Repository;
+
+
+// Walks commit history from a set of starting points, newest committer date
+// first, yielding each reachable commit exactly once.
+pub struct CommitWalker<'a> {
+    repo: &'a Repository,
+    queue: BinaryHeap<(i64, String)>,
+    visited: HashSet<String>,
+    // Distance from the nearest starting point, which counts as depth 1.
+    depth: HashMap<String, usize>,
+    max_depth: Option<usize>,
+    boundary: HashSet<String>,
+}
+
+impl<'a> CommitWalker<'a> {
+    pub fn new(repo: &'a Repository, starts: &[String]) -> Result<CommitWalker<'a>> {
+        let mut walker = CommitWalker {
+            repo,
+            queue: BinaryHeap::new(),
+            visited: HashSet::new(),
+            depth: HashMap::new(),
+            max_depth: None,
+            boundary: HashSet::new(),
+        };
+        for sha in starts {
+            walker.push(sha, 1)?;
+        }
+        Ok(walker)
+    }
+
+    // Stops following parents once a path is `max_depth` commits long, like
+    // a shallow clone of that depth.
+    pub fn with_max_depth(mut self, max_depth: usize) -> CommitWalker<'a> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    // Whether `sha` was yielded with its parents cut off by the depth limit.
+    pub fn is_boundary(&self, sha: &str) -> bool {
+        self.boundary.contains(sha)
+    }
+
+    fn push(&mut self, sha: &str, depth: usize) -> Result<()> {
+        if self.visited.insert(sha.to_string()) {
+            self.queue.push((self.repo.commit_node(sha)?.time, sha.to_string()));
+            self.depth.insert(sha.to_string(), depth);
+        } else if let Some(queued) = self.depth.get_mut(sha) {
+            // Reached again by a shorter path before being yielded.
+            *queued = (*queued).min(depth);
+        }
+        Ok(())
+    }
+
+    // Steps to the next commit using only the commit graph.
+    pub fn next_sha(&mut self) -> Result<Option<String>> {
+        let Some((_, sha)) = self.queue.pop() else {
+            return Ok(None);
+        };
+        let depth = self.depth.remove(&sha).unwrap_or(1);
+        let node = self.repo.commit_node(&sha)?;
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            if !node.parents.is_empty() {
+                self.boundary.insert(sha.clone());
+            }
+        } else {
+            for parent in &node.parents {
+                self.push(parent, depth + 1)?;
+            }
+        }
+        Ok(Some(sha))
+    }
+
+    fn advance(&mut self) -> Result<Option<(String, GitCommit)>> {
+        let Some(sha) = self.next_sha()? else {
+            return Ok(None);
+        };
+        let commit = read_commit(self.repo, &sha)?;
+        Ok(Some((sha, commit)))
+    }
+
+    // Walks the same commits without reading them, for callers that only
+    // need their SHAs.
+    pub fn shas(mut self) -> impl Iterator<Item = Result<String>> + 'a {
+        std::iter::from_fn(move || self.next_sha().transpose())
+    }
+}
+
+impl Iterator for CommitWalker<'_> {
+    type Item = Result<(String, GitCommit)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance().transpose()
+    }
+}
+
+
+// A best common ancestor of two commits: the newest commit reachable from
+// `b` that is also reachable from `a`.
+pub fn merge_base(repo: &Repository, a: &str, b: &str) -> Result<Option<String>> {
+    let ancestors: HashSet<String> = CommitWalker::new(repo, &[a.to_string()])?.shas().collect::<Result<_>>()?;
+    for sha in CommitWalker::new(repo, &[b.to_string()])?.shas() {
+        let sha = sha?;
+        if ancestors.contains(&sha) {
+            return Ok(Some(sha));
+        }
+    }
+    Ok(None)
+}
+
+// Every object reachable from `roots`: tags, commits with their parents, and
+// trees with everything below them. Submodule commits are not followed.
+pub fn reachable_objects(repo: &Repository, roots: &[String]) -> Result<HashSet<String>> {
+    let mut seen = HashSet::new();
+    let mut pending: Vec<String> = roots.to_vec();
+    while let Some(sha) = pending.pop() {
+        if !seen.insert(sha.clone()) {
+            continue;
+        }
+        let (type_, data) = read_raw(repo, &sha)?;
+        match type_ {
+            ObjectType::Blob => {}
+            ObjectType::Tag => pending.push(GitTag::deserialize(&data)?.object()?.to_string()),
+            ObjectType::Commit => {
+                let commit = GitCommit::deserialize(&data)?;
+                pending.push(commit.tree()?.to_string());
+                pending.extend(commit.parents().into_iter().map(String::from));
+            }
+            ObjectType::Tree => {
+                for leaf in GitTree::deserialize(&data)?.leaves {
+                    match leaf.object_type() {
+                        ObjectType::Commit => {}
+                        // Blobs need no reading, only marking.
+                        ObjectType::Blob => {
+                            seen.insert(leaf.sha);
+                        }
+                        _ => pending.push(leaf.sha),
+                    }
+                }
+            }
+        }
+    }
+    Ok(seen)
+}
@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, Metadata};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::convert::{to_git, to_worktree};
+use crate::index::{GitIndex, GitIndexEntry};
+use crate::object::{flatten_tree, read_blob, read_commit, FileMode, GitBlob, GitObject};
+use crate::progress::Progress;
+use crate::repository::Repository;
+use crate::utils::{encode_path, path_from_os};
+
+
+#[cfg(unix)]
+pub fn file_mode(metadata: &Metadata) -> FileMode {
+    use std::os::unix::fs::PermissionsExt;
+    if metadata.file_type().is_symlink() {
+        FileMode::Symlink
+    } else if metadata.permissions().mode() & 0o111 != 0 {
+        FileMode::Executable
+    } else {
+        FileMode::Regular
+    }
+}
+
+#[cfg(not(unix))]
+pub fn file_mode(metadata: &Metadata) -> FileMode {
+    if metadata.file_type().is_symlink() {
+        FileMode::Symlink
+    } else {
+        FileMode::Regular
+    }
+}
+
+// Whether `path` is `spec` itself or lies below it; an empty spec (the
+// worktree root) matches everything.
+pub fn path_matches(path: &str, spec: &str) -> bool {
+    spec.is_empty() || path == spec || path.strip_prefix(spec).is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Reads a worktree file the way it would be stored in a blob: symlinks are
+// stored as their target and regular files go through the clean conversions.
+pub fn read_worktree_file(repo: &Repository, path: &str) -> Result<Vec<u8>> {
+    let full = repo.worktree_file(path);
+    let metadata = fs::symlink_metadata(&full).with_context(|| format!("Failed to stat '{}'", path))?;
+    if metadata.file_type().is_symlink() {
+        return Ok(encode_path(&path_from_os(fs::read_link(&full)?.as_os_str())));
+    }
+    to_git(repo, path, fs::read(&full).with_context(|| format!("Failed to read '{}'", path))?)
+}
+
+// Stages a worktree file: writes its blob and returns the matching index entry.
+pub fn stage_file(repo: &Repository, path: &str) -> Result<GitIndexEntry> {
+    let metadata = fs::symlink_metadata(repo.worktree_file(path))?;
+    let sha = GitBlob::new(read_worktree_file(repo, path)?).write(repo)?;
+    Ok(GitIndexEntry::new(path.to_string(), file_mode(&metadata), sha, Some(&metadata)))
+}
+
+// Whether the worktree copy of an indexed file differs from the index.
+pub fn is_modified(repo: &Repository, entry: &GitIndexEntry) -> Result<bool> {
+    let Ok(metadata) = fs::symlink_metadata(repo.worktree_file(&entry.name)) else {
+        return Ok(true);
+    };
+    if entry.stat_matches(&metadata) {
+        return Ok(false);
+    }
+    Ok(GitBlob::new(read_worktree_file(repo, &entry.name)?).hash()? != entry.sha)
+}
+
+#[cfg(unix)]
+fn write_symlink(target: &[u8], full: &Path) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(target), full)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_symlink(target: &[u8], full: &Path) -> Result<()> {
+    fs::write(full, target)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_executable(full: &Path, executable: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(full)?.permissions();
+    let mode = permissions.mode();
+    permissions.set_mode(if executable { mode | 0o111 } else { mode & !0o111 });
+    fs::set_permissions(full, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_full: &Path, _executable: bool) -> Result<()> {
+    Ok(())
+}
+
+// Writes a blob out to the worktree and returns the stat data of the result.
+pub fn write_worktree_file(repo: &Repository, path: &str, mode: FileMode, sha: &str) -> Result<Metadata> {
+    let data = match mode {
+        FileMode::Gitlink => Vec::new(),
+        FileMode::Symlink => read_blob(repo, sha)?.raw_data,
+        _ => to_worktree(repo, path, read_blob(repo, sha)?.raw_data)?,
+    };
+    write_worktree_data(repo, path, mode, &data)
+}
+
+// Writes `data` to the worktree as is: the target of a symlink or the
+// content of a file. Gitlinks become empty directories.
+pub fn write_worktree_data(repo: &Repository, path: &str, mode: FileMode, data: &[u8]) -> Result<Metadata> {
+    let full = repo.worktree_file(path);
+    if let Ok(existing) = fs::symlink_metadata(&full) {
+        if existing.is_dir() && mode != FileMode::Gitlink {
+            fs::remove_dir_all(&full)?;
+        } else if !existing.is_dir() {
+            fs::remove_file(&full)?;
+        }
+    }
+    if let Some(parent) = full.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match mode {
+        FileMode::Gitlink => fs::create_dir_all(&full)?,
+        FileMode::Symlink => write_symlink(data, &full)?,
+        _ => {
+            fs::write(&full, data)?;
+            set_executable(&full, mode == FileMode::Executable)?;
+        }
+    }
+    Ok(fs::symlink_metadata(&full)?)
+}
+
+// Lists the files below `dir` (relative to the worktree), skipping `.git`.
+pub fn list_worktree_files(repo: &Repository, dir: &str) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_string()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(repo.worktree_file(&dir))? {
+            let entry = entry?;
+            let name = path_from_os(&entry.file_name());
+            if name == ".git" {
+                continue;
+            }
+            let path = if dir.is_empty() { name } else { format!("{}/{}", dir, name) };
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort_by_key(|path| encode_path(path));
+    Ok(files)
+}
+
+// Removes a file and any directories left empty by its removal.
+pub fn remove_worktree_file(repo: &Repository, path: &str) -> Result<()> {
+    let full = repo.worktree_file(path);
+    if fs::symlink_metadata(&full).is_ok() {
+        fs::remove_file(&full)?;
+    }
+    let mut dir = full.parent();
+    while let Some(d) = dir {
+        if d == repo.worktree || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+    Ok(())
+}
+
+// Paths that differ only by case can't all exist on a case-insensitive
+// filesystem. With `core.ignorecase` set that is an error, otherwise a
+// warning that only one of each group makes it into the worktree there.
+pub fn check_case_collisions<'a>(repo: &Repository, paths: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut folded: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for path in paths {
+        folded.entry(path.to_lowercase()).or_default().insert(path);
+    }
+    let collisions: Vec<String> = folded.into_values().filter(|group| group.len() > 1).flatten().map(|path| format!("  '{}'", path)).collect();
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    if repo.config_get("core", "ignorecase").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+        bail!("the following paths collide on this case-insensitive filesystem:\n\n{}", collisions.join("\n"));
+    }
+    eprintln!(
+        "warning: the following paths have collided (e.g. case-sensitive paths\non a case-insensitive filesystem) and only one from the same\ncolliding group is in the working tree:\n\n{}",
+        collisions.join("\n")
+    );
+    Ok(())
+}
+
+// Switches the worktree and index from HEAD's tree over to `tree_sha`. Only
+// paths that differ between the two trees are touched; other index entries
+// and worktree files are carried over as they are, staged or not. Paths
+// that do change must have no local changes, or nothing is written.
+pub fn checkout_tree(repo: &Repository, tree_sha: &str) -> Result<()> {
+    let head: HashMap<String, (FileMode, String)> = match repo.head_commit()? {
+        Some(sha) => flatten_tree(repo, read_commit(repo, &sha)?.tree()?)?.into_iter().map(|leaf| (leaf.path, (leaf.mode, leaf.sha))).collect(),
+        None => HashMap::new(),
+    };
+    let target_leaves = flatten_tree(repo, tree_sha)?;
+    let target: HashMap<&str, (FileMode, &str)> = target_leaves.iter().map(|leaf| (leaf.path.as_str(), (leaf.mode, leaf.sha.as_str()))).collect();
+    let mut index = GitIndex::read(repo)?;
+
+    let mut changing: Vec<&str> = head.keys().map(String::as_str).chain(target.keys().copied())
+        .filter(|path| head.get(*path).map(|(mode, sha)| (*mode, sha.as_str())) != target.get(path).copied())
+        .collect();
+    changing.sort_by_key(|path| encode_path(path));
+    changing.dedup();
+
+    let mut modified = Vec::new();
+    let mut untracked = Vec::new();
+    for &path in &changing {
+        let from = head.get(path).map(|(mode, sha)| (*mode, sha.as_str()));
+        let to = target.get(path).copied();
+        let unmerged = index.entries.iter().any(|entry| entry.name == path && entry.stage != 0);
+        let entry = index.get(path);
+        let staged = entry.map(|entry| (entry.mode, entry.sha.as_str()));
+        if unmerged || (staged != from && staged != to) {
+            modified.push(path);
+        } else if let Some(entry) = entry {
+            // A file deleted from the worktree has nothing left to lose.
+            if fs::symlink_metadata(repo.worktree_file(path)).is_ok() && is_modified(repo, entry)? {
+                modified.push(path);
+            }
+        } else if let Some((_, sha)) = to {
+            // Directories in the way are left to the write to replace.
+            if fs::symlink_metadata(repo.worktree_file(path)).is_ok_and(|metadata| !metadata.is_dir()) && GitBlob::new(read_worktree_file(repo, path).unwrap_or_default()).hash()? != sha {
+                untracked.push(path);
+            }
+        }
+    }
+    if !modified.is_empty() {
+        bail!("Your local changes to the following files would be overwritten by checkout:\n\t{}\nPlease commit your changes or stash them before you switch branches.", modified.join("\n\t"));
+    }
+    if !untracked.is_empty() {
+        bail!("The following untracked working tree files would be overwritten by checkout:\n\t{}\nPlease move or remove them before you switch branches.", untracked.join("\n\t"));
+    }
+    check_case_collisions(repo, target_leaves.iter().map(|leaf| leaf.path.as_str()))?;
+
+    let mut progress = Progress::new("Updating files", Some(changing.len()));
+    for &path in &changing {
+        progress.tick();
+        let staged = index.get(path).map(|entry| (entry.mode, entry.sha.as_str()));
+        match target.get(path).copied() {
+            // Already staged as it will be, so the worktree is left alone.
+            to if staged == to => {}
+            Some((mode, sha)) => {
+                let metadata = write_worktree_file(repo, path, mode, sha)?;
+                index.add(GitIndexEntry::new(path.to_string(), mode, sha.to_string(), Some(&metadata)));
+            }
+            None => {
+                remove_worktree_file(repo, path)?;
+                index.remove(path);
+            }
+        }
+    }
+    progress.finish();
+    index.write(repo)
+}
+
+// Makes the index and worktree match `tree_sha` exactly, throwing local
+// changes away.
+pub fn reset_tree(repo: &Repository, tree_sha: &str) -> Result<()> {
+    let old_index = GitIndex::read(repo)?;
+    let old: HashMap<&str, &GitIndexEntry> = old_index.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+    let target = flatten_tree(repo, tree_sha)?;
+    let new: HashMap<&str, &str> = target.iter().map(|leaf| (leaf.path.as_str(), leaf.sha.as_str())).collect();
+    check_case_collisions(repo, target.iter().map(|leaf| leaf.path.as_str()))?;
+
+    for entry in &old_index.entries {
+        if !new.contains_key(entry.name.as_str()) {
+            remove_worktree_file(repo, &entry.name)?;
+        }
+    }
+
+    let mut index = GitIndex { version: old_index.version, entries: Vec::new() };
+    let mut progress = Progress::new("Updating files", Some(target.len()));
+    for leaf in &target {
+        progress.tick();
+        let mode = leaf.mode;
+        let entry = match old.get(leaf.path.as_str()) {
+            Some(existing) if existing.sha == leaf.sha && existing.mode == mode && !is_modified(repo, existing)? => (*existing).clone(),
+            _ => {
+                let metadata = write_worktree_file(repo, &leaf.path, mode, &leaf.sha)?;
+                GitIndexEntry::new(leaf.path.clone(), mode, leaf.sha.clone(), Some(&metadata))
+            }
+        };
+        index.entries.push(entry);
+    }
+    progress.finish();
+    index.write(repo)
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+#[test]
+fn applying_a_diff_to_the_old_version_gives_the_new_one() {
+    let repo = TestRepo::new();
+    repo.write("modified.txt", "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n");
+    repo.write("deleted.txt", "going away\n");
+    let old = repo.commit_all("old");
+
+    repo.write("modified.txt", "zero\none\ntwo\nthree\nFOUR\nfive\nsix\nseven\n");
+    std::fs::remove_file(repo.path("deleted.txt")).unwrap();
+    repo.write("dir/added.txt", "brand new\n");
+    repo.git_rs(&["add", "deleted.txt"]);
+    let new = repo.commit_all("new");
+    let patch = repo.git_rs(&["diff", &old, &new]);
+
+    repo.git_rs(&["checkout", &old]);
+    assert_eq!(repo.read_string("deleted.txt"), "going away\n");
+    repo.git_rs_with_stdin(&["apply", "--check"], &patch);
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "");
+
+    repo.git_rs_with_stdin(&["apply"], &patch);
+    assert_eq!(repo.read_string("modified.txt"), "zero\none\ntwo\nthree\nFOUR\nfive\nsix\nseven\n");
+    assert_eq!(repo.read_string("dir/added.txt"), "brand new\n");
+    assert!(!repo.path("deleted.txt").exists());
+
+    // Staging the result gives exactly the new commit's tree.
+    repo.git_rs(&["read-tree", &old]);
+    repo.git_rs_with_stdin(&["apply", "--cached"], &patch);
+    assert_eq!(repo.git_rs(&["write-tree"]).trim_end(), repo.rev_parse(&format!("{}^{{tree}}", new)));
+
+    // The patch doesn't apply a second time.
+    let output = repo.run_with_stdin(&["apply", "--cached"], &patch);
+    assert!(!output.status.success());
+    assert_eq!(stderr(&output), "Error: deleted.txt: does not exist in index\n");
+}
+
+#[test]
+fn later_sections_for_a_file_apply_on_top_of_earlier_ones() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "one\ntwo\nthree\n");
+    let first = repo.commit_all("first");
+    repo.write("file.txt", "one\nTWO\nthree\n");
+    let second = repo.commit_all("second");
+    repo.write("file.txt", "one\nTWO\nthree\nfour\n");
+    let third = repo.commit_all("third");
+    // The second section's context only exists once the first is applied.
+    let patch = repo.git_rs(&["diff", &first, &second]) + &repo.git_rs(&["diff", &second, &third]);
+
+    repo.git_rs(&["checkout", &first]);
+    repo.git_rs_with_stdin(&["apply", "--check"], &patch);
+    repo.git_rs_with_stdin(&["apply"], &patch);
+    assert_eq!(repo.read_string("file.txt"), "one\nTWO\nthree\nfour\n");
+
+    repo.git_rs(&["read-tree", &first]);
+    repo.git_rs_with_stdin(&["apply", "--cached"], &patch);
+    assert_eq!(repo.git_rs(&["write-tree"]).trim_end(), repo.rev_parse(&format!("{}^{{tree}}", third)));
+}
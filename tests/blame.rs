@@ -0,0 +1,73 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+// The commit and line number blamed for each line, with the line itself.
+fn blamed(output: &str) -> Vec<(String, usize, String)> {
+    output
+        .lines()
+        .map(|line| {
+            let (id, rest) = line.split_once(" (").unwrap();
+            let (info, text) = rest.split_once(") ").unwrap();
+            let number = info.rsplit(' ').next().unwrap().parse().unwrap();
+            (id.to_string(), number, text.to_string())
+        })
+        .collect()
+}
+
+fn repo_with_history() -> (TestRepo, [String; 3]) {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "a\nb\nc\n");
+    let first = repo.commit_all("first");
+    repo.write("other.txt", "unrelated\n");
+    repo.commit_all("unrelated");
+    repo.write("file.txt", "a\nB\nc\nd\n");
+    let second = repo.commit_all("second");
+    repo.write("file.txt", "top\na\nB\nc\nd\n");
+    let third = repo.commit_all("third");
+    (repo, [first, second, third])
+}
+
+#[test]
+fn lines_are_blamed_on_the_commit_that_last_changed_them() {
+    let (repo, [first, second, third]) = repo_with_history();
+    let root = format!("^{}", &first[..7]);
+    let expected = [(&third[..8], 1, "top"), (&root, 2, "a"), (&second[..8], 3, "B"), (&root, 4, "c"), (&second[..8], 5, "d")];
+    let output = repo.git_rs(&["blame", "file.txt"]);
+    assert_eq!(blamed(&output), expected.map(|(id, n, text)| (id.to_string(), n, text.to_string())), "{}", output);
+    assert!(output.starts_with(&format!("{} (A U Thor 2023-11-14 22:13:", &third[..8])), "{}", output);
+
+    // Blaming an older revision stops at its version of the file.
+    let ids: Vec<String> = blamed(&repo.git_rs(&["blame", &second, "--", "file.txt"])).into_iter().map(|(id, _, _)| id).collect();
+    assert_eq!(ids, [&root, &second[..8], &root, &second[..8]]);
+}
+
+#[test]
+fn line_ranges_limit_the_lines_shown() {
+    let (repo, _) = repo_with_history();
+    let numbers = |range: &str| blamed(&repo.git_rs(&["blame", "-L", range, "file.txt"])).into_iter().map(|(_, n, _)| n).collect::<Vec<_>>();
+
+    assert_eq!(numbers("2,3"), [2, 3]);
+    assert_eq!(numbers("2,+2"), [2, 3]);
+    assert_eq!(numbers("3,-2"), [2, 3]);
+    assert_eq!(numbers("3,2"), [2, 3]);
+    assert_eq!(numbers("4"), [4, 5]);
+    assert_eq!(numbers(",2"), [1, 2]);
+    assert_eq!(numbers("5"), [5]);
+    // An end past the last line stops there.
+    assert_eq!(numbers("4,100"), [4, 5]);
+    assert_eq!(numbers("4,+100"), [4, 5]);
+    assert_eq!(blamed(&repo.git_rs(&["blame", "-L1,1", "-L", "5,5", "file.txt"])).into_iter().map(|(_, n, text)| (n, text)).collect::<Vec<_>>(), [(1, "top".to_string()), (5, "d".to_string())]);
+
+    for range in ["6", "100,200"] {
+        let output = repo.run(&["blame", "-L", range, "file.txt"]);
+        assert!(!output.status.success());
+        assert_eq!(stderr(&output), "Error: file file.txt has only 5 lines\n");
+    }
+    for range in ["0,2", "a,b", "2,+0", ""] {
+        let output = repo.run(&["blame", "-L", range, "file.txt"]);
+        assert!(!output.status.success());
+        assert_eq!(stderr(&output), format!("Error: invalid -L range '{}'\n", range));
+    }
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{stderr, write_raw_object, TestRepo};
+
+
+#[test]
+fn cat_file_pretty_prints_each_object_type() {
+    let repo = TestRepo::new();
+    let blob = repo.hash_object("blob", "hello\n");
+    let subtree = repo.git_rs_with_stdin(&["mktree"], format!("100644 blob {}\tlib.rs\n", blob)).trim_end().to_string();
+    let tree = repo.git_rs_with_stdin(&["mktree"], format!("100755 blob {0}\trun.sh\n040000 tree {1}\tsrc\n100644 blob {0}\tREADME\n", blob, subtree)).trim_end().to_string();
+    let commit_text = format!("tree {}\nauthor A U Thor <author@example.com> 1700000000 +0100\ncommitter C O Mitter <committer@example.com> 1700000000 +0100\n\nInitial commit\n\nWith a body.\n", tree);
+    let commit = repo.hash_object("commit", &commit_text);
+    let tag_text = format!("object {}\ntype commit\ntag v1.0\ntagger C O Mitter <committer@example.com> 1700000000 +0000\n\nRelease 1.0\n", commit);
+    let tag = repo.hash_object("tag", &tag_text);
+
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &blob]), "hello\n");
+    assert_eq!(
+        repo.git_rs(&["cat-file", "-p", &tree]),
+        format!("100644 blob {0}\tREADME\n100755 blob {0}\trun.sh\n040000 tree {1}\tsrc\n", blob, subtree)
+    );
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &commit]), commit_text);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &tag]), tag_text);
+}
+
+#[test]
+fn cat_file_reports_objects_of_unknown_type() {
+    let repo = TestRepo::new();
+    let raw = b"bogus 12\0hello world\n";
+    let sha = write_raw_object(&repo, raw);
+
+    assert_eq!(repo.git_rs(&["cat-file", "-t", "--allow-unknown-type", &sha]), "bogus\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-s", "--allow-unknown-type", &sha]), "12\n");
+    assert!(!repo.run(&["cat-file", "-t", &sha]).status.success());
+    assert!(!repo.run(&["cat-file", "-p", &sha]).status.success());
+    assert_eq!(repo.run(&["cat-file", "--raw", &sha]).stdout, raw);
+}
+
+#[test]
+fn batch_all_objects_lists_each_object_once() {
+    let repo = TestRepo::new();
+    repo.write("file", "hello\n");
+    let commit = repo.commit_all("initial");
+    let tree = repo.rev_parse("HEAD^{tree}");
+    let blob = repo.rev_parse("HEAD:file");
+
+    // The same blob stored again in an alternate must not be listed twice.
+    let alternate = TestRepo::new();
+    assert_eq!(alternate.hash_object("blob", "hello\n"), blob);
+    repo.write(".git/objects/info/alternates", format!("{}\n", alternate.path(".git/objects").display()));
+
+    let listed = repo.git_rs(&["cat-file", "--batch-check", "--batch-all-objects"]);
+    let commit_size = repo.git_rs(&["cat-file", "-s", &commit]);
+    let mut expected = vec![format!("{} blob 6", blob), format!("{} tree 32", tree), format!("{} commit {}", commit, commit_size.trim_end())];
+    expected.sort();
+    assert_eq!(listed.lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn batch_all_objects_lists_packed_objects_and_keeps_going_past_bad_ones() {
+    let repo = TestRepo::new();
+    repo.write("file", "hello\n");
+    let commit = repo.commit_all("initial");
+    let tree = repo.rev_parse("HEAD^{tree}");
+    let blob = repo.rev_parse("HEAD:file");
+    repo.git_rs(&["gc", "--force"]);
+
+    // A loose copy of a packed object is still one object.
+    assert_eq!(write_raw_object(&repo, b"blob 6\0hello\n"), blob);
+    let bogus = write_raw_object(&repo, b"bogus 12\0hello world\n");
+    let corrupt = write_raw_object(&repo, b"no header at all");
+
+    let output = repo.run(&["cat-file", "--batch-check", "--batch-all-objects"]);
+    assert!(output.status.success());
+    let commit_size = repo.git_rs(&["cat-file", "-s", &commit]);
+    let mut expected = vec![format!("{} blob 6", blob), format!("{} tree 32", tree), format!("{} commit {}", commit, commit_size.trim_end()), format!("{} bogus 12", bogus)];
+    expected.sort();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().collect::<Vec<_>>(), expected);
+    assert!(stderr(&output).starts_with(&format!("error: Malformed object {}: ", corrupt)), "{}", stderr(&output));
+}
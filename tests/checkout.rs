@@ -0,0 +1,107 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+#[test]
+fn checkout_paths_from_a_commit_reverts_only_those_paths() {
+    let repo = TestRepo::new();
+    repo.write("reverted", "committed\n");
+    repo.write("dir/kept", "committed\n");
+    repo.write("dir/staged", "committed\n");
+    repo.commit_all("initial");
+
+    repo.write("reverted", "modified\n");
+    repo.write("dir/kept", "modified\n");
+    repo.write("dir/staged", "staged\n");
+    repo.git_rs(&["add", "reverted", "dir/staged"]);
+
+    repo.git_rs(&["checkout", "HEAD", "--", "reverted"]);
+    assert_eq!(repo.read_string("reverted"), "committed\n");
+    assert_eq!(repo.read_string("dir/kept"), "modified\n");
+    assert_eq!(repo.read_string("dir/staged"), "staged\n");
+    // The index entry is reset along with the file.
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), " M dir/kept\nM  dir/staged\n");
+}
+
+#[test]
+fn checkout_paths_from_an_older_commit_stages_that_version() {
+    let repo = TestRepo::new();
+    repo.write("file", "first\n");
+    repo.write("other", "first\n");
+    let first = repo.commit_all("first");
+    repo.write("file", "second\n");
+    repo.write("other", "second\n");
+    repo.commit_all("second");
+
+    repo.git_rs(&["checkout", &first, "--", "file"]);
+    assert_eq!(repo.read_string("file"), "first\n");
+    assert_eq!(repo.read_string("other"), "second\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "M  file\n");
+    assert!(!repo.run(&["checkout", "HEAD", "--", "missing"]).status.success());
+}
+
+#[test]
+fn switching_branches_refuses_to_overwrite_local_changes() {
+    let repo = TestRepo::new();
+    repo.write("shared", "base\n");
+    repo.write("staged", "base\n");
+    repo.commit_all("base");
+    repo.git_rs(&["checkout", "-b", "other"]);
+    repo.write("shared", "other\n");
+    repo.write("staged", "other\n");
+    repo.write("added", "other\n");
+    repo.commit_all("other");
+    repo.git_rs(&["checkout", "master"]);
+
+    // A staged change with a clean worktree is still a local change.
+    repo.write("staged", "staged\n");
+    repo.git_rs(&["add", "staged"]);
+    repo.write("added", "untracked\n");
+    let output = repo.run(&["checkout", "other"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("would be overwritten by checkout:\n\tstaged\n"), "{}", stderr(&output));
+    assert_eq!(repo.rev_parse("HEAD"), repo.rev_parse("master"));
+    assert_eq!(repo.read_string("shared"), "base\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "M  staged\n?? added\n");
+
+    repo.git_rs(&["checkout", "HEAD", "--", "staged"]);
+    let output = repo.run(&["checkout", "other"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("untracked working tree files would be overwritten by checkout:\n\tadded\n"), "{}", stderr(&output));
+    assert_eq!(repo.read_string("added"), "untracked\n");
+
+    // Untracked content that matches the target is fine to adopt.
+    repo.write("added", "other\n");
+    repo.git_rs(&["checkout", "other"]);
+    assert_eq!(repo.read_string("shared"), "other\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn switching_branches_carries_local_changes_over() {
+    let repo = TestRepo::new();
+    repo.write("modified", "base\n");
+    repo.write("staged", "base\n");
+    repo.write("changes", "base\n");
+    repo.commit_all("base");
+    repo.git_rs(&["checkout", "-b", "other"]);
+    repo.write("changes", "other\n");
+    repo.commit_all("other");
+    repo.git_rs(&["checkout", "master"]);
+
+    repo.write("modified", "unstaged edit\n");
+    repo.write("staged", "staged edit\n");
+    repo.git_rs(&["add", "staged"]);
+
+    repo.git_rs(&["checkout", "-b", "feature"]);
+    assert_eq!(repo.git_rs(&["symbolic-ref", "HEAD"]), "refs/heads/feature\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), " M modified\nM  staged\n");
+
+    repo.git_rs(&["checkout", "other"]);
+    assert_eq!(repo.git_rs(&["symbolic-ref", "HEAD"]), "refs/heads/other\n");
+    assert_eq!(repo.read_string("changes"), "other\n");
+    assert_eq!(repo.read_string("modified"), "unstaged edit\n");
+    assert_eq!(repo.read_string("staged"), "staged edit\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), " M modified\nM  staged\n");
+}
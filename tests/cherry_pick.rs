@@ -0,0 +1,43 @@
+mod common;
+
+use common::TestRepo;
+
+
+fn commit_field(repo: &TestRepo, rev: &str, field: &str) -> Vec<String> {
+    let commit = repo.git_rs(&["cat-file", "-p", rev]);
+    commit.lines().take_while(|line| !line.is_empty()).filter_map(|line| line.strip_prefix(&format!("{} ", field))).map(String::from).collect()
+}
+
+#[test]
+fn cherry_pick_applies_one_change_onto_a_divergent_branch() {
+    let repo = TestRepo::new();
+    repo.write("picked", "one\ntwo\nthree\n");
+    repo.write("ours", "base\n");
+    repo.commit_all("base");
+
+    repo.git_rs(&["checkout", "-b", "feature"]);
+    repo.write("picked", "one\nTWO\nthree\n");
+    repo.write("unpicked", "left behind\n");
+    repo.commit_all("unrelated");
+    repo.write("picked", "one\nTWO\nthree\nfour\n");
+    let picked = repo.commit_all("add four");
+
+    repo.git_rs(&["checkout", "master"]);
+    repo.write("ours", "changed on master\n");
+    let master = repo.commit_all("diverge");
+
+    repo.git_rs(&["cherry-pick", &picked]);
+    let head = repo.rev_parse("HEAD");
+    assert_ne!(head, picked);
+    assert_eq!(commit_field(&repo, "HEAD", "parent"), [master]);
+    assert_eq!(commit_field(&repo, "HEAD", "author"), commit_field(&repo, &picked, "author"));
+    assert_ne!(commit_field(&repo, "HEAD", "committer"), commit_field(&repo, &picked, "committer"));
+    assert!(repo.git_rs(&["cat-file", "-p", "HEAD"]).ends_with("\n\nadd four\n"));
+
+    // Only the picked commit's own change comes along.
+    assert_eq!(repo.read_string("picked"), "one\ntwo\nthree\nfour\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:picked"]), "one\ntwo\nthree\nfour\n");
+    assert_eq!(repo.read_string("ours"), "changed on master\n");
+    assert!(!repo.path("unpicked").exists());
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "");
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn commit_tree_writes_a_commit_without_touching_head_or_the_index() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "one\n");
+    let first = repo.commit_all("first");
+    repo.write("file.txt", "two\n");
+    let second = repo.commit_all("second");
+    let tree = repo.rev_parse("HEAD^{tree}");
+    repo.write("file.txt", "staged\n");
+    repo.git_rs(&["add", "file.txt"]);
+    let index = repo.read(".git/index");
+
+    // Parents keep the order given, and a repeated one is only listed once.
+    let sha = repo.git_rs_with_stdin(&["commit-tree", &tree, "-p", &second, "-p", &first, "-p", &second], "from stdin\n").trim_end().to_string();
+    let commit = repo.git_rs(&["cat-file", "-p", &sha]);
+    let headers: Vec<&str> = commit.lines().take(3).collect();
+    assert_eq!(headers, [format!("tree {}", tree), format!("parent {}", second), format!("parent {}", first)]);
+    assert!(commit.lines().nth(3).unwrap().starts_with("author A U Thor <author@example.com> "), "{}", commit);
+    assert!(commit.ends_with("\n\nfrom stdin\n"), "{}", commit);
+
+    assert_eq!(repo.rev_parse("HEAD"), second);
+    assert_eq!(repo.read(".git/index"), index);
+    assert_eq!(repo.read_string("file.txt"), "staged\n");
+
+    // Each -m is its own paragraph and stdin is not read.
+    let sha = repo.git_rs_with_stdin(&["commit-tree", "-m", "subject", "-m", "body", &tree], "ignored\n").trim_end().to_string();
+    let commit = repo.git_rs(&["cat-file", "-p", &sha]);
+    assert!(!commit.contains("parent "), "{}", commit);
+    assert!(commit.ends_with("\n\nsubject\n\nbody\n"), "{}", commit);
+    assert_eq!(repo.rev_parse("HEAD"), second);
+}
@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use git_rs::repository::Repository;
+use sha1::{Digest, Sha1};
+
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// A scratch repository under the system temp dir, removed again on drop.
+// Commands run with a fixed identity, an empty HOME and a clock that moves
+// one second per command, so object SHAs and history order are stable.
+pub struct TestRepo {
+    pub root: PathBuf,
+    pub dir: PathBuf,
+    clock: Cell<i64>,
+}
+
+impl TestRepo {
+    pub fn new() -> TestRepo {
+        let repo = TestRepo::empty();
+        repo.git_rs(&["init"]);
+        repo
+    }
+
+    // A directory with a HOME but no repository in it.
+    pub fn empty() -> TestRepo {
+        let root = std::env::temp_dir().join(format!("git_rs-test-{}-{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("repo");
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(root.join("home")).unwrap();
+        TestRepo { root: root.canonicalize().unwrap(), dir: dir.canonicalize().unwrap(), clock: Cell::new(1_700_000_000) }
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    pub fn write(&self, path: &str, contents: impl AsRef<[u8]>) {
+        let full = self.path(path);
+        fs::create_dir_all(full.parent().unwrap()).unwrap();
+        fs::write(full, contents).unwrap();
+    }
+
+    pub fn read(&self, path: &str) -> Vec<u8> {
+        fs::read(self.path(path)).unwrap()
+    }
+
+    pub fn read_string(&self, path: &str) -> String {
+        String::from_utf8(self.read(path)).unwrap()
+    }
+
+    pub fn append_config(&self, text: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(self.path(".git/config")).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    pub fn open(&self) -> Repository {
+        Repository::new(self.dir.clone())
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        self.command_in(&self.dir, args)
+    }
+
+    pub fn command_in(&self, cwd: &Path, args: &[&str]) -> Command {
+        let time = self.clock.get() + 1;
+        self.clock.set(time);
+        let date = format!("{} +0000", time);
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_git_rs"));
+        command
+            .args(args)
+            .current_dir(cwd)
+            .env("HOME", self.root.join("home"))
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("GIT_INDEX_FILE")
+            .env("GIT_AUTHOR_NAME", "A U Thor")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_NAME", "C O Mitter")
+            .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+            .env("GIT_COMMITTER_DATE", &date);
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    pub fn run_with_stdin(&self, args: &[&str], stdin: impl AsRef<[u8]>) -> Output {
+        let mut child = self.command(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+        // A command that fails early may exit without reading its input.
+        let _ = child.stdin.take().unwrap().write_all(stdin.as_ref());
+        child.wait_with_output().unwrap()
+    }
+
+    // Runs a command that must succeed and returns its stdout.
+    pub fn git_rs(&self, args: &[&str]) -> String {
+        success(self.run(args), args)
+    }
+
+    pub fn git_rs_with_stdin(&self, args: &[&str], stdin: impl AsRef<[u8]>) -> String {
+        success(self.run_with_stdin(args, stdin), args)
+    }
+
+    // Stages everything and commits it, returning the new commit's SHA.
+    pub fn commit_all(&self, message: &str) -> String {
+        self.git_rs(&["add", "."]);
+        self.git_rs(&["commit", "-m", message]);
+        self.rev_parse("HEAD")
+    }
+
+    pub fn rev_parse(&self, rev: &str) -> String {
+        self.git_rs(&["rev-parse", rev]).trim_end().to_string()
+    }
+
+    pub fn hash_object(&self, type_: &str, contents: impl AsRef<[u8]>) -> String {
+        self.git_rs_with_stdin(&["hash-object", "-t", type_, "-w", "--stdin"], contents).trim_end().to_string()
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// Writes a loose object by hand, so its header can say anything.
+pub fn write_raw_object(repo: &TestRepo, raw: &[u8]) -> String {
+    let sha: String = Sha1::digest(raw).iter().map(|b| format!("{:02x}", b)).collect();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(raw).unwrap();
+    let path = repo.path(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..]));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, encoder.finish().unwrap()).unwrap();
+    sha
+}
+
+pub fn success(output: Output, args: &[&str]) -> String {
+    assert!(output.status.success(), "git_rs {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
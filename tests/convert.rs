@@ -0,0 +1,66 @@
+mod common;
+
+use std::fs;
+
+use common::TestRepo;
+
+
+const CRLF_TEXT: &[u8] = b"one\r\ntwo\r\n";
+const BINARY: &[u8] = b"bin\0ary\r\n";
+
+// Commits a CRLF text file and a binary file under `autocrlf`, then checks
+// them out again, returning the stored blobs and the checked-out bytes.
+fn round_trip(autocrlf: &str) -> ([Vec<u8>; 2], [Vec<u8>; 2]) {
+    let repo = TestRepo::new();
+    repo.append_config(&format!("[core]\n\tautocrlf = {}\n", autocrlf));
+    repo.write("text.txt", CRLF_TEXT);
+    repo.write("data.bin", BINARY);
+    repo.commit_all("initial");
+
+    let stored = ["text.txt", "data.bin"].map(|path| repo.git_rs(&["cat-file", "-p", &format!("HEAD:{}", path)]).into_bytes());
+    for path in ["text.txt", "data.bin"] {
+        fs::remove_file(repo.path(path)).unwrap();
+    }
+    repo.git_rs(&["checkout", "HEAD", "--", "text.txt", "data.bin"]);
+    (stored, [repo.read("text.txt"), repo.read("data.bin")])
+}
+
+#[test]
+fn autocrlf_true_normalizes_on_add_and_restores_crlf_on_checkout() {
+    let (stored, checked_out) = round_trip("true");
+    assert_eq!(stored, [b"one\ntwo\n".to_vec(), BINARY.to_vec()]);
+    assert_eq!(checked_out, [CRLF_TEXT.to_vec(), BINARY.to_vec()]);
+}
+
+#[test]
+fn autocrlf_input_normalizes_on_add_only() {
+    let (stored, checked_out) = round_trip("input");
+    assert_eq!(stored, [b"one\ntwo\n".to_vec(), BINARY.to_vec()]);
+    assert_eq!(checked_out, [b"one\ntwo\n".to_vec(), BINARY.to_vec()]);
+}
+
+#[test]
+fn autocrlf_false_leaves_content_untouched() {
+    let (stored, checked_out) = round_trip("false");
+    assert_eq!(stored, [CRLF_TEXT.to_vec(), BINARY.to_vec()]);
+    assert_eq!(checked_out, [CRLF_TEXT.to_vec(), BINARY.to_vec()]);
+}
+
+#[cfg(unix)]
+#[test]
+fn clean_and_smudge_filters_run_on_add_and_checkout() {
+    let repo = TestRepo::new();
+    repo.append_config("[filter \"upper\"]\n\tclean = tr a-z A-Z\n\tsmudge = tr A-Z a-z\n");
+    repo.write(".gitattributes", "*.txt filter=upper\n");
+    repo.write("text.txt", "hello world\n");
+    repo.write("other.md", "left alone\n");
+    repo.commit_all("initial");
+
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:text.txt"]), "HELLO WORLD\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:other.md"]), "left alone\n");
+    assert_eq!(repo.git_rs(&["cat-file", "--filters", "HEAD:text.txt"]), "hello world\n");
+
+    fs::remove_file(repo.path("text.txt")).unwrap();
+    repo.git_rs(&["checkout", "HEAD", "--", "text.txt"]);
+    assert_eq!(repo.read_string("text.txt"), "hello world\n");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::fs;
+
+use common::TestRepo;
+use git_rs::diff::{diff_trees, similarity, Change, DiffEntry};
+use git_rs::object::FileMode;
+
+
+fn numbered_lines(count: usize) -> String {
+    (1..=count).map(|n| format!("{}\n", n)).collect()
+}
+
+#[test]
+fn renamed_files_are_reported_as_renames() {
+    let repo = TestRepo::new();
+    repo.write("old.txt", numbered_lines(20));
+    repo.commit_all("initial");
+
+    std::fs::remove_file(repo.path("old.txt")).unwrap();
+    repo.write("new.txt", numbered_lines(21));
+    repo.git_rs(&["add", "old.txt", "new.txt"]);
+
+    assert!(repo.git_rs(&["status"]).contains("\trenamed:    old.txt -> new.txt\n"));
+    let diff = repo.git_rs(&["diff", "--cached", "-M"]);
+    assert!(diff.starts_with("diff --git a/old.txt b/new.txt\nsimilarity index 94%\nrename from old.txt\nrename to new.txt\n"), "{}", diff);
+
+    let without = repo.git_rs(&["status", "--no-renames"]);
+    assert!(without.contains("\tdeleted:    old.txt\n") && without.contains("\tnew file:   new.txt\n"), "{}", without);
+}
+
+#[test]
+fn similarity_of_large_unrelated_files_is_cheap() {
+    // An edit script between these would need memory quadratic in their size.
+    let old: String = (0..200_000).map(|n| format!("old {}\n", n)).collect();
+    let new: String = (0..200_000).map(|n| format!("new {}\n", n)).collect();
+    assert_eq!(similarity(old.as_bytes(), new.as_bytes()), 0);
+
+    let half: String = (0..200_000).map(|n| if n % 2 == 0 { format!("old {}\n", n) } else { format!("new {}\n", n) }).collect();
+    assert_eq!(similarity(old.as_bytes(), half.as_bytes()), 50);
+}
+
+#[test]
+fn diff_cached_shows_a_staged_one_line_change() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "one\ntwo\nthree\n");
+    repo.commit_all("initial");
+    let old = repo.git_rs(&["hash-object", "file.txt"]);
+    repo.write("file.txt", "one\nTWO\nthree\n");
+    repo.git_rs(&["add", "file.txt"]);
+    let new = repo.git_rs(&["hash-object", "file.txt"]);
+
+    assert_eq!(
+        repo.git_rs(&["diff", "--cached"]),
+        format!(
+            "diff --git a/file.txt b/file.txt\nindex {}..{} 100644\n--- a/file.txt\n+++ b/file.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n",
+            &old[..7],
+            &new[..7]
+        )
+    );
+    assert_eq!(repo.git_rs(&["diff"]), "");
+}
+
+#[test]
+fn diff_trees_reports_one_nested_change() {
+    let repo = TestRepo::new();
+    repo.write("src/deep/nested.rs", "old\n");
+    repo.write("src/other.rs", "same\n");
+    repo.write("docs/guide.md", "same\n");
+    repo.commit_all("first");
+    let old_tree = repo.rev_parse("HEAD^{tree}");
+    let old_blob = repo.rev_parse("HEAD:src/deep/nested.rs");
+    repo.write("src/deep/nested.rs", "new\n");
+    repo.commit_all("second");
+    let new_tree = repo.rev_parse("HEAD^{tree}");
+    let new_blob = repo.rev_parse("HEAD:src/deep/nested.rs");
+
+    // docs/ is the same tree on both sides, so it must not even be read.
+    let docs = repo.rev_parse("HEAD:docs");
+    fs::remove_file(repo.path(&format!(".git/objects/{}/{}", &docs[..2], &docs[2..]))).unwrap();
+
+    let changes = diff_trees(&repo.open(), &old_tree, &new_tree).unwrap();
+    assert_eq!(changes, [Change::Modified {
+        path: "src/deep/nested.rs".to_string(),
+        old: DiffEntry { mode: FileMode::Regular, sha: old_blob },
+        new: DiffEntry { mode: FileMode::Regular, sha: new_blob },
+    }]);
+    assert!(!changes[0].mode_changed());
+    assert!(diff_trees(&repo.open(), &new_tree, &new_tree).unwrap().is_empty());
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::fs;
+
+use common::TestRepo;
+
+
+fn object_file(repo: &TestRepo, sha: &str) -> Vec<u8> {
+    repo.read(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..]))
+}
+
+// A commit, its tree and blob, plus two dangling blobs: five loose objects.
+fn repo_with_dangling_objects() -> (TestRepo, String, [String; 2]) {
+    let repo = TestRepo::new();
+    repo.write("file", "kept\n");
+    let head = repo.commit_all("initial");
+    let dangling = ["first dangling\n", "second dangling\n"].map(|contents| repo.hash_object("blob", contents));
+    (repo, head, dangling)
+}
+
+#[test]
+fn gc_does_nothing_at_or_below_the_threshold() {
+    let (repo, head, dangling) = repo_with_dangling_objects();
+    let before = object_file(&repo, &head);
+
+    repo.append_config("[gc]\n\tauto = 5\n");
+    repo.git_rs(&["gc", "--aggressive", "--prune=now"]);
+    assert!(dangling.iter().all(|sha| repo.open().object_exists(sha)));
+    assert_eq!(object_file(&repo, &head), before);
+
+    // Zero turns automatic collection off however many objects there are.
+    repo.append_config("[gc]\n\tauto = 0\n");
+    repo.git_rs(&["gc", "--prune=now"]);
+    assert!(dangling.iter().all(|sha| repo.open().object_exists(sha)));
+}
+
+// The pack and index gc wrote; there must be exactly one of each.
+fn pack_files(repo: &TestRepo) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(repo.path(".git/objects/pack")).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+    names.sort();
+    names
+}
+
+// The zlib header of the first entry in the pack, after the 12-byte pack
+// header and the entry's own type and size bytes.
+fn first_entry_zlib_header(repo: &TestRepo) -> [u8; 2] {
+    let pack = repo.read(&format!(".git/objects/pack/{}", pack_files(repo)[1]));
+    let start = 12 + pack[12..].iter().position(|b| b & 0x80 == 0).unwrap() + 1;
+    [pack[start], pack[start + 1]]
+}
+
+fn exists(repo: &TestRepo, sha: &str) -> bool {
+    repo.run(&["cat-file", "-e", sha]).status.success()
+}
+
+#[test]
+fn gc_prunes_and_packs_above_the_threshold() {
+    let (repo, head, dangling) = repo_with_dangling_objects();
+    let tree = repo.rev_parse("HEAD^{tree}");
+    let blob = repo.rev_parse("HEAD:file");
+
+    repo.append_config("[gc]\n\tauto = 4\n");
+    repo.git_rs(&["gc", "--prune=now"]);
+    assert!(dangling.iter().all(|sha| !exists(&repo, sha)));
+    let files = pack_files(&repo);
+    assert_eq!(files.len(), 2);
+    assert!(files[0].starts_with("pack-") && files[0].ends_with(".idx") && files[1].ends_with(".pack"));
+
+    // The reachable objects now live only in the pack.
+    for sha in [&head, &tree, &blob] {
+        assert!(!repo.path(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..])).exists());
+        assert!(exists(&repo, sha));
+    }
+    assert_eq!(first_entry_zlib_header(&repo), [0x78, 0x9c]);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:file"]), "kept\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-s", &head[..7]]), repo.git_rs(&["cat-file", "-s", &head]));
+    assert_eq!(repo.git_rs(&["cat-file", "--raw", &blob]), "blob 5\0kept\n");
+    assert_eq!(repo.git_rs(&["log", "--format=%s"]), "initial\n");
+
+    // A second gc repacks into a pack of the same objects, and new commits
+    // on top of packed history work as usual.
+    repo.write("file", "changed\n");
+    repo.commit_all("second");
+    repo.git_rs(&["gc", "--force", "--aggressive"]);
+    assert_eq!(pack_files(&repo).len(), 2);
+    assert_eq!(first_entry_zlib_header(&repo), [0x78, 0xda]);
+    assert_eq!(repo.git_rs(&["log", "--format=%s"]), "second\ninitial\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &format!("{}:file", head)]), "kept\n");
+}
+
+#[test]
+fn gc_keeps_objects_reachable_from_unexpired_reflog_entries() {
+    let repo = TestRepo::new();
+    repo.write("file", "base\n");
+    let base = repo.commit_all("base");
+    repo.git_rs(&["checkout", &base]);
+    repo.write("file", "only in the reflog\n");
+    let detached = repo.commit_all("detached");
+    repo.git_rs(&["checkout", "master"]);
+
+    // Pruning everything unreachable right away must still leave what the
+    // reflog points at.
+    repo.append_config("[gc]\n\treflogExpire = never\n");
+    repo.git_rs(&["gc", "--force", "--prune=now"]);
+    assert!(exists(&repo, &detached));
+    assert_eq!(repo.rev_parse("HEAD@{1}"), detached);
+
+    // The test clock is years in the past, so the default 90 days expires
+    // every entry and the commit goes with them.
+    let config = repo.read_string(".git/config").replace("\treflogExpire = never\n", "");
+    repo.write(".git/config", config);
+    repo.git_rs(&["gc", "--force", "--prune=now"]);
+    assert!(!exists(&repo, &detached));
+    assert!(exists(&repo, &base));
+}
+
+#[test]
+fn gc_force_ignores_the_threshold() {
+    let (repo, _, dangling) = repo_with_dangling_objects();
+    repo.git_rs(&["gc", "--force", "--prune=now"]);
+    assert!(dangling.iter().all(|sha| !exists(&repo, sha)));
+}
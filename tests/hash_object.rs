@@ -0,0 +1,39 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+fn hash_error(repo: &TestRepo, type_: &str, contents: &str) -> String {
+    let output = repo.run_with_stdin(&["hash-object", "-t", type_, "--stdin"], contents);
+    assert!(!output.status.success());
+    let message = stderr(&output);
+    assert!(!message.contains("panicked"), "{}", message);
+    message
+}
+
+#[test]
+fn hash_object_rejects_malformed_structured_objects() {
+    let repo = TestRepo::new();
+    assert_eq!(hash_error(&repo, "commit", "tree 123\n"), "Error: invalid commit object: missing message\n");
+    assert!(hash_error(&repo, "commit", "author nobody\n\nmessage\n").starts_with("Error: invalid commit object: "));
+    assert!(hash_error(&repo, "tree", "garbage").starts_with("Error: invalid tree object: "));
+    assert_eq!(hash_error(&repo, "tag", "object x\n"), "Error: invalid tag object: missing message\n");
+}
+
+#[test]
+fn hash_object_rejects_unknown_types_without_literally() {
+    let repo = TestRepo::new();
+    assert_eq!(hash_error(&repo, "bogus", "payload"), "Error: invalid object type 'bogus'\n");
+}
+
+#[test]
+fn hash_object_writes_unknown_types_literally() {
+    let repo = TestRepo::new();
+    let sha = repo.git_rs_with_stdin(&["hash-object", "-t", "bogus", "--literally", "-w", "--stdin"], "payload").trim_end().to_string();
+
+    // The SHA git computes for `bogus 7\0payload`.
+    assert_eq!(sha, "23c861afb869b9fb4e81dc8df7779b4f888c262b");
+    assert_eq!(repo.git_rs_with_stdin(&["hash-object", "-t", "bogus", "--literally", "--stdin"], "payload").trim_end(), sha);
+    assert_eq!(repo.git_rs(&["cat-file", "-t", "--allow-unknown-type", &sha]), "bogus\n");
+    assert_eq!(repo.git_rs(&["cat-file", "--raw", &sha]), "bogus 7\0payload");
+}
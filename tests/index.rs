@@ -0,0 +1,80 @@
+mod common;
+
+use common::{stderr, success, TestRepo};
+
+
+#[test]
+fn update_index_cacheinfo_stages_an_existing_blob() {
+    let repo = TestRepo::new();
+    let sha = repo.hash_object("blob", "stored only in the object database\n");
+
+    repo.git_rs(&["update-index", "--add", "--cacheinfo", &format!("100644,{},docs/notes.txt", sha)]);
+    assert_eq!(repo.git_rs(&["ls-files", "-s"]), format!("100644 {} 0\tdocs/notes.txt\n", sha));
+    assert!(!repo.path("docs/notes.txt").exists());
+
+    let missing = "0123456789012345678901234567890123456789";
+    assert!(!repo.run(&["update-index", "--add", "--cacheinfo", &format!("100644,{},other", missing)]).status.success());
+}
+
+#[test]
+fn ls_files_debug_prints_the_staged_size() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "twelve bytes");
+    repo.git_rs(&["add", "file.txt"]);
+
+    let debug = repo.git_rs(&["ls-files", "--debug"]);
+    assert!(debug.starts_with("file.txt\n  ctime: "), "{}", debug);
+    let size = debug.lines().find_map(|line| line.trim_start().strip_prefix("size: ")).unwrap();
+    assert_eq!(size.split('\t').next().unwrap(), "12");
+}
+
+#[test]
+fn git_index_file_leaves_the_default_index_alone() {
+    let repo = TestRepo::new();
+    repo.write("tracked", "committed\n");
+    repo.commit_all("initial");
+    let index = repo.read(".git/index");
+    let modified = std::fs::metadata(repo.path(".git/index")).unwrap().modified().unwrap();
+
+    let alternate = repo.root.join("alternate-index");
+    let with_alternate = |args: &[&str]| success(repo.command(args).env("GIT_INDEX_FILE", &alternate).output().unwrap(), args);
+    repo.write("new", "only in the alternate index\n");
+    with_alternate(&["read-tree", "HEAD"]);
+    with_alternate(&["add", "new"]);
+    assert_eq!(with_alternate(&["ls-files"]), "new\ntracked\n");
+    assert_eq!(with_alternate(&["status", "--porcelain"]), "A  new\n");
+    let tree = with_alternate(&["write-tree"]).trim_end().to_string();
+    let names: Vec<String> = repo.git_rs(&["ls-tree", &tree]).lines().map(|line| line.split_once('\t').unwrap().1.to_string()).collect();
+    assert_eq!(names, ["new", "tracked"]);
+
+    assert!(alternate.is_file());
+    assert_eq!(repo.read(".git/index"), index);
+    assert_eq!(std::fs::metadata(repo.path(".git/index")).unwrap().modified().unwrap(), modified);
+    assert_eq!(repo.git_rs(&["ls-files"]), "tracked\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "?? new\n");
+}
+
+#[test]
+fn staging_case_variant_paths_warns_about_the_collision() {
+    let repo = TestRepo::new();
+    repo.write("README", "upper\n");
+    repo.write("readme", "lower\n");
+    repo.write("other", "fine\n");
+
+    let output = repo.run(&["add", "."]);
+    assert!(output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "warning: the following paths have collided (e.g. case-sensitive paths\non a case-insensitive filesystem) and only one from the same\ncolliding group is in the working tree:\n\n  'README'\n  'readme'\n"
+    );
+    assert_eq!(repo.git_rs(&["ls-files"]), "README\nother\nreadme\n");
+
+    // core.ignorecase turns the warning into an error.
+    let tree = repo.git_rs(&["write-tree"]).trim_end().to_string();
+    std::fs::remove_file(repo.path(".git/index")).unwrap();
+    repo.append_config("[core]\n\tignorecase = true\n");
+    let output = repo.run(&["read-tree", &tree]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("collide on this case-insensitive filesystem"), "{}", stderr(&output));
+    assert!(!repo.path(".git/index").exists());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::TestRepo;
+use git_rs::walker::{merge_base, CommitWalker};
+
+
+#[test]
+fn log_with_paths_lists_only_commits_touching_them() {
+    let repo = TestRepo::new();
+    repo.write("a.txt", "a1\n");
+    repo.write("b.txt", "b1\n");
+    repo.write("dir/sub/x.txt", "x1\n");
+    repo.commit_all("add everything");
+    repo.write("b.txt", "b2\n");
+    repo.commit_all("change b");
+    repo.write("a.txt", "a2\n");
+    repo.commit_all("change a");
+    repo.write("dir/sub/x.txt", "x2\n");
+    repo.commit_all("change x");
+
+    assert_eq!(repo.git_rs(&["log", "--format=%s", "--", "a.txt"]), "change a\nadd everything\n");
+    assert_eq!(repo.git_rs(&["log", "--format=%s", "--", "dir"]), "change x\nadd everything\n");
+    assert_eq!(repo.git_rs(&["log", "--format=%s", "HEAD", "--", "b.txt", "a.txt"]), "change a\nchange b\nadd everything\n");
+}
+
+#[test]
+fn log_format_expands_placeholders_per_commit() {
+    let repo = TestRepo::new();
+    repo.write("file", "one\n");
+    let first = repo.commit_all("first commit");
+    repo.write("file", "two\n");
+    let second = repo.commit_all("second commit\n\nWith a body.");
+
+    assert_eq!(repo.git_rs(&["log", "--format=%h %s"]), format!("{} second commit\n{} first commit\n", &second[..7], &first[..7]));
+    assert_eq!(repo.git_rs(&["log", "--pretty=format:%H%n%an <%ae>%n%b", &second]), format!("{}\nA U Thor <author@example.com>\nWith a body.\n\n{}\nA U Thor <author@example.com>\n", second, first));
+}
+
+#[test]
+fn depth_limits_history_and_marks_the_boundary() {
+    let repo = TestRepo::new();
+    let commits: Vec<String> = (1..=10)
+        .map(|n| {
+            repo.write("file", format!("{}\n", n));
+            repo.commit_all(&format!("commit {}", n))
+        })
+        .collect();
+    let (c10, c9, c8) = (&commits[9], &commits[8], &commits[7]);
+
+    let git = repo.open();
+    let mut walker = CommitWalker::new(&git, std::slice::from_ref(c10)).unwrap().with_max_depth(3);
+    let mut yielded = Vec::new();
+    while let Some(sha) = walker.next_sha().unwrap() {
+        yielded.push(sha);
+    }
+    assert_eq!(yielded, [c10.clone(), c9.clone(), c8.clone()]);
+    assert!(walker.is_boundary(c8) && !walker.is_boundary(c9));
+
+    assert_eq!(repo.git_rs(&["rev-list", "--depth=3", "HEAD"]), format!("{}\n{}\n{}\n", c10, c9, c8));
+    assert_eq!(repo.git_rs(&["rev-list", "--depth=3", "--boundary", "HEAD"]), format!("{}\n{}\n{}\n-{}\n", c10, c9, c8, commits[6]));
+    assert_eq!(repo.git_rs(&["rev-list", "--boundary", "HEAD"]).lines().count(), 10);
+    assert_eq!(repo.git_rs(&["log", "--depth=3", "--format=%s"]), "commit 10\ncommit 9\ncommit 8\n");
+    let log = repo.git_rs(&["log", "--depth=3"]);
+    assert!(log.contains(&format!("commit {} (grafted)\n", c8)));
+    assert!(log.contains(&format!("commit {}\n", c9)));
+}
+
+#[test]
+fn merge_base_reuses_commits_read_by_earlier_walks() {
+    let repo = TestRepo::new();
+    repo.write("file", "0\n");
+    let base = repo.commit_all("base");
+    for i in 1..=20 {
+        repo.write("file", format!("{}\n", i));
+        repo.commit_all("ours");
+    }
+    let ours = repo.rev_parse("HEAD");
+    repo.git_rs(&["checkout", "-b", "feature", &base]);
+    for i in 1..=20 {
+        repo.write("other", format!("{}\n", i));
+        repo.commit_all("theirs");
+    }
+    let theirs = repo.rev_parse("HEAD");
+
+    let git = repo.open();
+    assert_eq!(merge_base(&git, &ours, &theirs).unwrap(), Some(base.clone()));
+    let first = git.objects_read();
+    assert!(first >= 41, "read only {} objects", first);
+
+    assert_eq!(merge_base(&git, &ours, &theirs).unwrap(), Some(base));
+    let second = git.objects_read() - first;
+    assert!(second * 10 <= first, "first walk read {} objects, second read {}", first, second);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+// Two branches that both changed `file` since their common base.
+fn diverged(repo: &TestRepo, ours: &str, theirs: &str) -> (String, String) {
+    repo.write("file", "base\n");
+    repo.write("other", "untouched\n");
+    repo.commit_all("base");
+    repo.git_rs(&["checkout", "-b", "feature"]);
+    repo.write("file", theirs);
+    let theirs = repo.commit_all("theirs");
+    repo.git_rs(&["checkout", "master"]);
+    repo.write("file", ours);
+    let ours = repo.commit_all("ours");
+    (ours, theirs)
+}
+
+fn parents(repo: &TestRepo, rev: &str) -> Vec<String> {
+    let commit = repo.git_rs(&["cat-file", "-p", rev]);
+    commit.lines().filter_map(|line| line.strip_prefix("parent ")).map(String::from).collect()
+}
+
+#[test]
+fn resolving_a_conflict_and_committing_makes_a_merge_commit() {
+    let repo = TestRepo::new();
+    let (ours, theirs) = diverged(&repo, "ours\n", "theirs\n");
+
+    let output = repo.run(&["merge", "feature"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("CONFLICT (content): Merge conflict in file"));
+    assert_eq!(repo.read_string(".git/MERGE_HEAD"), format!("{}\n", theirs));
+    assert!(repo.read_string(".git/MERGE_MSG").starts_with("Merge branch 'feature'\n"));
+    let stages: Vec<String> = repo.git_rs(&["ls-files", "--stage", "file"]).lines().map(|line| line.split_once('\t').unwrap().0.rsplit(' ').next().unwrap().to_string()).collect();
+    assert_eq!(stages, ["1", "2", "3"]);
+    assert!(repo.read_string("file").contains("<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n"));
+
+    let refused = repo.run(&["commit"]);
+    assert!(!refused.status.success());
+    assert!(stderr(&refused).contains("unmerged files"));
+
+    repo.write("file", "resolved\n");
+    repo.git_rs(&["add", "file"]);
+    repo.git_rs(&["commit"]);
+    assert_eq!(parents(&repo, "HEAD"), [ours, theirs]);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:file"]), "resolved\n");
+    assert!(repo.git_rs(&["log"]).contains("    Merge branch 'feature'\n"));
+    assert!(!repo.path(".git/MERGE_HEAD").exists());
+    assert!(!repo.path(".git/MERGE_MSG").exists());
+}
+
+#[test]
+fn no_commit_stops_before_committing_a_clean_merge() {
+    let repo = TestRepo::new();
+    let (ours, theirs) = diverged(&repo, "ours\nbase\n", "base\ntheirs\n");
+
+    let output = repo.git_rs(&["merge", "--no-commit", "feature"]);
+    assert!(output.contains("stopped before committing as requested"));
+    assert_eq!(repo.rev_parse("HEAD"), ours);
+    assert_eq!(repo.read_string("file"), "ours\nbase\ntheirs\n");
+
+    repo.git_rs(&["commit"]);
+    assert_eq!(parents(&repo, "HEAD"), [ours, theirs]);
+}
+
+#[test]
+fn merge_file_combines_changes_to_different_lines() {
+    let repo = TestRepo::new();
+    repo.write("base.txt", "one\ntwo\nthree\nfour\nfive\n");
+    repo.write("ours.txt", "ONE\ntwo\nthree\nfour\nfive\n");
+    repo.write("theirs.txt", "one\ntwo\nthree\nfour\nFIVE\n");
+
+    assert_eq!(repo.git_rs(&["merge-file", "-p", "ours.txt", "base.txt", "theirs.txt"]), "ONE\ntwo\nthree\nfour\nFIVE\n");
+    assert_eq!(repo.read_string("ours.txt"), "ONE\ntwo\nthree\nfour\nfive\n");
+
+    repo.git_rs(&["merge-file", "ours.txt", "base.txt", "theirs.txt"]);
+    assert_eq!(repo.read_string("ours.txt"), "ONE\ntwo\nthree\nfour\nFIVE\n");
+    assert_eq!(repo.read_string("theirs.txt"), "one\ntwo\nthree\nfour\nFIVE\n");
+}
+
+#[test]
+fn merge_file_marks_conflicts_and_exits_with_their_count() {
+    let repo = TestRepo::new();
+    repo.write("base.txt", "one\ntwo\nthree\n");
+    repo.write("ours.txt", "one\nours\nthree\n");
+    repo.write("theirs.txt", "one\ntheirs\nthree\n");
+
+    let output = repo.run(&["merge-file", "-L", "mine", "-L", "base", "-L", "yours", "ours.txt", "base.txt", "theirs.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(repo.read_string("ours.txt"), "one\n<<<<<<< mine\nours\n=======\ntheirs\n>>>>>>> yours\nthree\n");
+
+    // Without labels the file names are used.
+    repo.write("ours.txt", "one\nours\nthree\n");
+    let output = repo.run(&["merge-file", "-p", "ours.txt", "base.txt", "theirs.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "one\n<<<<<<< ours.txt\nours\n=======\ntheirs\n>>>>>>> theirs.txt\nthree\n");
+    assert_eq!(repo.read_string("ours.txt"), "one\nours\nthree\n");
+
+    repo.write("base.txt", "one\ntwo\nthree\nfour\nfive\n");
+    repo.write("ours.txt", "one\n2\nthree\n4\nfive\n");
+    repo.write("theirs.txt", "one\nII\nthree\nIV\nfive\n");
+    assert_eq!(repo.run(&["merge-file", "-q", "ours.txt", "base.txt", "theirs.txt"]).status.code(), Some(2));
+}
+
+#[test]
+fn merge_refuses_to_overwrite_untracked_files() {
+    let repo = TestRepo::new();
+    let (ours, _) = diverged(&repo, "ours\n", "theirs\n");
+    repo.git_rs(&["checkout", "feature"]);
+    repo.write("added", "theirs\n");
+    repo.commit_all("add a file");
+    repo.git_rs(&["checkout", "master"]);
+
+    repo.write("added", "untracked\n");
+    let output = repo.run(&["merge", "feature"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("untracked working tree files would be overwritten by merge:\n\tadded\n"), "{}", stderr(&output));
+    assert_eq!(repo.read_string("added"), "untracked\n");
+    assert_eq!(repo.read_string("file"), "ours\n");
+    assert_eq!(repo.rev_parse("HEAD"), ours);
+    assert!(!repo.path(".git/MERGE_HEAD").exists());
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use std::fs;
+use std::io::Read;
+
+use common::{stderr, write_raw_object, TestRepo};
+use git_rs::error::GitError;
+use git_rs::object::{hash_data, read_commit, read_header_from, read_object, read_object_header, resolve_path_in_tree, store_object, FileMode, GitBlob, GitCommit, GitObject, ObjectType, ObjectWriteError};
+use git_rs::refs::find_object;
+use sha1::{Digest, Sha1};
+
+
+fn head_tree(repo: &TestRepo) -> String {
+    read_commit(&repo.open(), &repo.rev_parse("HEAD")).unwrap().tree().unwrap().to_string()
+}
+
+#[test]
+fn resolve_path_in_tree_descends_into_subtrees() {
+    let repo = TestRepo::new();
+    repo.write("src/commands/main.rs", "fn main() {}\n");
+    repo.write("README", "hello\n");
+    repo.commit_all("initial");
+
+    let leaf = resolve_path_in_tree(&repo.open(), &head_tree(&repo), "src/commands/main.rs").unwrap().unwrap();
+    assert_eq!(leaf.path, "main.rs");
+    assert_eq!(leaf.mode, FileMode::Regular);
+    assert_eq!(leaf.sha, repo.git_rs(&["hash-object", "src/commands/main.rs"]).trim_end());
+
+    let dir = resolve_path_in_tree(&repo.open(), &head_tree(&repo), "src/commands").unwrap().unwrap();
+    assert_eq!(dir.mode, FileMode::Tree);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:src/commands/main.rs"]), "fn main() {}\n");
+}
+
+#[test]
+fn resolve_path_in_tree_returns_none_for_missing_paths() {
+    let repo = TestRepo::new();
+    repo.write("src/main.rs", "fn main() {}\n");
+    repo.commit_all("initial");
+
+    let git = repo.open();
+    let tree = head_tree(&repo);
+    assert!(resolve_path_in_tree(&git, &tree, "src/lib.rs").unwrap().is_none());
+    assert!(resolve_path_in_tree(&git, &tree, "docs/README").unwrap().is_none());
+    assert!(resolve_path_in_tree(&git, &tree, "src/main.rs/inner").unwrap().is_none());
+    assert!(!repo.run(&["cat-file", "-p", "HEAD:src/lib.rs"]).status.success());
+}
+
+#[test]
+fn writing_into_an_unwritable_object_directory_is_an_error() {
+    let repo = TestRepo::new();
+    let git = repo.open();
+    let data = b"cannot be stored\n";
+    let sha = hash_data(ObjectType::Blob, data);
+
+    // A file where the fan-out directory belongs can't be written through
+    // even by root, unlike a read-only directory.
+    let fan_out = repo.path(&format!(".git/objects/{}", &sha[..2]));
+    fs::write(&fan_out, "").unwrap();
+    match store_object(&git, ObjectType::Blob, data) {
+        Err(ObjectWriteError::Io { path, .. }) => assert_eq!(path, fan_out),
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+    assert!(!repo.run_with_stdin(&["hash-object", "-w", "--stdin"], data).status.success());
+
+    fs::remove_file(&fan_out).unwrap();
+    assert_eq!(store_object(&git, ObjectType::Blob, data).unwrap(), sha);
+    assert!(matches!(store_object(&git, ObjectType::Blob, data), Err(ObjectWriteError::AlreadyExists(existing)) if existing == sha));
+}
+
+#[test]
+fn incremental_hash_matches_hashing_the_serialized_object() {
+    let data: Vec<u8> = (0..5 * 1024 * 1024).map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    let blob = GitBlob::new(data.clone());
+
+    let mut serialized = format!("blob {}\0", data.len()).into_bytes();
+    serialized.extend_from_slice(&blob.serialize().unwrap());
+    let expected: String = Sha1::digest(&serialized).iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(blob.hash().unwrap(), expected);
+    assert_eq!(hash_data(ObjectType::Blob, &data), expected);
+}
+
+#[test]
+fn objects_shorter_than_their_header_are_truncated() {
+    let repo = TestRepo::new();
+    let sha = write_raw_object(&repo, b"blob 100\0only a few bytes");
+
+    match read_object(&repo.open(), &sha) {
+        Err(GitError::Malformed(message)) => assert_eq!(message, format!("{}: truncated object (16 of 100 bytes)", sha)),
+        other => panic!("expected a truncated object error, got {:?}", other.map(|object| object.serialize())),
+    }
+    let output = repo.run(&["cat-file", "-p", &sha]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("truncated object"));
+}
+
+// Counts the bytes pulled through it.
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> Read for &mut CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn reading_a_header_leaves_the_payload_unread() {
+    let repo = TestRepo::new();
+    // Pseudo-random bytes barely compress, so the object file stays large.
+    let mut state = 0x2545f491u32;
+    let data: Vec<u8> = (0..4 * 1024 * 1024).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u8
+    }).collect();
+    let sha = store_object(&repo.open(), ObjectType::Blob, &data).unwrap();
+    let file = fs::File::open(repo.path(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..]))).unwrap();
+    let compressed = file.metadata().unwrap().len() as usize;
+    assert!(compressed > data.len() / 2);
+
+    let mut reader = CountingReader { inner: file, count: 0 };
+    assert_eq!(read_header_from(&sha, &mut reader).unwrap(), ("blob".to_string(), data.len()));
+    assert!(reader.count <= 64 * 1024, "read {} of {} bytes", reader.count, compressed);
+
+    assert_eq!(read_object_header(&repo.open(), &sha).unwrap(), (ObjectType::Blob, data.len()));
+    assert_eq!(repo.git_rs(&["cat-file", "-s", &sha]), format!("{}\n", data.len()));
+}
+
+#[test]
+fn missing_objects_are_not_found() {
+    let repo = TestRepo::new();
+    let sha = hash_data(ObjectType::Blob, b"never written\n");
+    assert!(matches!(read_object(&repo.open(), &sha), Err(GitError::ObjectNotFound(missing)) if missing == sha));
+    assert!(matches!(read_commit(&repo.open(), &sha), Err(GitError::ObjectNotFound(_))));
+}
+
+#[test]
+fn object_errors_keep_their_kind() {
+    let repo = TestRepo::new();
+    let git = repo.open();
+    let blob = repo.hash_object("blob", "content\n");
+    match read_commit(&git, &blob) {
+        Err(GitError::WrongType { sha, expected, actual }) => assert_eq!((sha, expected, actual), (blob.clone(), ObjectType::Commit, ObjectType::Blob)),
+        other => panic!("expected a wrong type error, got {:?}", other),
+    }
+
+    // A corrupt tag is reported as corrupt, not as a bad revision.
+    let tag = write_raw_object(&repo, b"tag 10\0no headers");
+    match find_object(&git, &tag, Some(ObjectType::Commit)) {
+        Err(GitError::Malformed(message)) => assert_eq!(message, format!("{}: missing message", tag)),
+        other => panic!("expected a malformed object error, got {:?}", other),
+    }
+    assert!(matches!(find_object(&git, "no-such-branch", None), Err(GitError::BadRevision(_))));
+
+    let existing = store_object(&git, ObjectType::Blob, b"content\n").unwrap_err();
+    assert!(matches!(GitError::from(existing), GitError::ObjectExists(sha) if sha == blob));
+}
+
+#[test]
+fn objects_stored_in_an_alternate_are_not_written_again() {
+    let repo = TestRepo::new();
+    let alternate = TestRepo::new();
+    let sha = alternate.hash_object("blob", "shared content\n");
+    repo.write(".git/objects/info/alternates", format!("{}\n", alternate.path(".git/objects").display()));
+
+    let git = repo.open();
+    assert!(git.object_exists(&sha));
+    assert_eq!(repo.hash_object("blob", "shared content\n"), sha);
+    assert!(!repo.path(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..])).exists());
+    assert!(!repo.path(&format!(".git/objects/{}", &sha[..2])).exists());
+    assert!(matches!(store_object(&git, ObjectType::Blob, b"shared content\n"), Err(ObjectWriteError::AlreadyExists(existing)) if existing == sha));
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &sha]), "shared content\n");
+}
+
+#[test]
+fn objects_stored_in_a_pack_are_not_written_again() {
+    let repo = TestRepo::new();
+    repo.write("file", "packed content\n");
+    repo.commit_all("initial");
+    let sha = repo.rev_parse("HEAD:file");
+    repo.git_rs(&["gc", "--force"]);
+    let loose = repo.path(&format!(".git/objects/{}/{}", &sha[..2], &sha[2..]));
+    assert!(!loose.exists());
+
+    let git = repo.open();
+    assert!(git.object_exists(&sha));
+    assert_eq!(repo.hash_object("blob", "packed content\n"), sha);
+    assert!(!loose.exists());
+    assert!(matches!(store_object(&git, ObjectType::Blob, b"packed content\n"), Err(ObjectWriteError::AlreadyExists(existing)) if existing == sha));
+    repo.git_rs(&["add", "file"]);
+    assert!(!loose.exists());
+}
+
+#[test]
+fn errors_below_revision_parsing_are_not_bad_revisions() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("initial");
+    let git = repo.open();
+
+    // A reflog too short is the revision's fault; a corrupt one is not.
+    assert!(matches!(find_object(&git, "HEAD@{5}", None), Err(GitError::BadRevision(message)) if message == "log for 'HEAD' only has 1 entries"));
+    repo.write(".git/logs/HEAD", "not a reflog line\n");
+    match find_object(&git, "HEAD@{0}", None) {
+        Err(GitError::Other(e)) => assert_eq!(e.to_string(), "Malformed reflog entry 'not a reflog line'"),
+        other => panic!("expected the reflog error to be kept, got {:?}", other),
+    }
+
+    let commit = GitCommit::deserialize(b"tree 4b825dc642cb6eb9a060e54bf8d69288fbe4904b\nauthor nobody\n\nmessage\n").unwrap();
+    assert!(matches!(commit.author(), Err(GitError::Malformed(reason)) if reason == "bad signature 'nobody'"));
+    assert!(matches!(commit.committer(), Err(GitError::Malformed(reason)) if reason == "commit has no committer"));
+    assert!(read_commit(&git, &head).unwrap().author().is_ok());
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+
+use common::{stderr, TestRepo};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use git_rs::object::{hash_data, ObjectType};
+use sha1::{Digest, Sha1};
+
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn entry_header(type_: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![(type_ << 4) | (size & 0x0f) as u8];
+    let mut rest = size >> 4;
+    while rest != 0 {
+        *header.last_mut().unwrap() |= 0x80;
+        header.push((rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    header
+}
+
+fn varint(mut n: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return out;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+// Writes a pack of ready-made entries and a version 2 index for it, the
+// way git would after a repack that stored some objects as deltas.
+fn write_pack(repo: &TestRepo, entries: &[(String, Vec<u8>)]) {
+    let mut pack = b"PACK".to_vec();
+    pack.extend_from_slice(&2u32.to_be_bytes());
+    pack.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    let mut index: Vec<([u8; 20], u32)> = Vec::new();
+    for (sha, entry) in entries {
+        let raw: Vec<u8> = (0..40).step_by(2).map(|i| u8::from_str_radix(&sha[i..i + 2], 16).unwrap()).collect();
+        index.push((raw.try_into().unwrap(), pack.len() as u32));
+        pack.extend_from_slice(entry);
+    }
+    let checksum = Sha1::digest(&pack);
+    pack.extend_from_slice(&checksum);
+
+    index.sort();
+    let mut idx = vec![0xff, b't', b'O', b'c', 0, 0, 0, 2];
+    for first in 0..=255u8 {
+        idx.extend_from_slice(&(index.iter().filter(|(sha, _)| sha[0] <= first).count() as u32).to_be_bytes());
+    }
+    for (sha, _) in &index {
+        idx.extend_from_slice(sha);
+    }
+    // The CRCs are only checked by verify-pack.
+    idx.extend(std::iter::repeat_n(0, index.len() * 4));
+    for (_, offset) in &index {
+        idx.extend_from_slice(&offset.to_be_bytes());
+    }
+    idx.extend_from_slice(&checksum);
+    let idx_checksum = Sha1::digest(&idx);
+    idx.extend_from_slice(&idx_checksum);
+
+    repo.write(".git/objects/pack/pack-test.pack", pack);
+    repo.write(".git/objects/pack/pack-test.idx", idx);
+}
+
+#[test]
+fn packed_objects_stored_as_deltas_are_read_back_whole() {
+    let repo = TestRepo::new();
+    let base = "line\n".repeat(100).into_bytes();
+    let appended = [base.as_slice(), b"extra\n"].concat();
+    let prepended = [b"prefix\n".as_slice(), &base].concat();
+    let [base_sha, appended_sha, prepended_sha] = [&base, &appended, &prepended].map(|data| hash_data(ObjectType::Blob, data));
+
+    assert_eq!(base.len(), 0x1f4);
+
+    let base_entry = [entry_header(3, base.len()), compress(&base)].concat();
+    // Copy all of the base (offset 0, two length bytes), then insert.
+    let append = [varint(base.len()), varint(appended.len()), vec![0x80 | 0x10 | 0x20, 0xf4, 0x01, 6], b"extra\n".to_vec()].concat();
+    // The base is found by going back from this entry to the first one.
+    let ofs_entry = [entry_header(6, append.len()), vec![base_entry.len() as u8], compress(&append)].concat();
+    let prepend = [varint(base.len()), varint(prepended.len()), vec![7], b"prefix\n".to_vec(), vec![0x80 | 0x10 | 0x20, 0xf4, 0x01]].concat();
+    let base_raw: Vec<u8> = (0..40).step_by(2).map(|i| u8::from_str_radix(&base_sha[i..i + 2], 16).unwrap()).collect();
+    let ref_entry = [entry_header(7, prepend.len()), base_raw, compress(&prepend)].concat();
+    write_pack(&repo, &[(base_sha.clone(), base_entry), (appended_sha.clone(), ofs_entry), (prepended_sha.clone(), ref_entry)]);
+
+    assert!(fs::read_dir(repo.path(".git/objects")).unwrap().all(|entry| entry.unwrap().file_name().len() != 2));
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &base_sha]).as_bytes(), base);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &appended_sha]).as_bytes(), appended);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &prepended_sha]).as_bytes(), prepended);
+    assert_eq!(repo.git_rs(&["cat-file", "-t", &appended_sha]), "blob\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-s", &prepended_sha]), format!("{}\n", prepended.len()));
+    assert_eq!(repo.git_rs(&["cat-file", "-s", &base_sha[..8]]), format!("{}\n", base.len()));
+}
+
+#[test]
+fn corrupt_deltas_are_reported_as_malformed() {
+    let repo = TestRepo::new();
+    let base = b"short base\n".to_vec();
+    let target = b"never built\n".to_vec();
+    let [base_sha, target_sha] = [&base, &target].map(|data| hash_data(ObjectType::Blob, data));
+
+    let base_entry = [entry_header(3, base.len()), compress(&base)].concat();
+    // Copies far past the end of the base.
+    let delta = [varint(base.len()), varint(target.len()), vec![0x80 | 0x01 | 0x10, 0x40, 0x0c]].concat();
+    let ofs_entry = [entry_header(6, delta.len()), vec![base_entry.len() as u8], compress(&delta)].concat();
+    write_pack(&repo, &[(base_sha, base_entry), (target_sha.clone(), ofs_entry)]);
+
+    let output = repo.run(&["cat-file", "-p", &target_sha]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains(&format!("Malformed object {}: ", target_sha)), "{}", stderr(&output));
+    assert!(stderr(&output).contains("bad delta"));
+}
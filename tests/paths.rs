@@ -0,0 +1,61 @@
+mod common;
+
+use git_rs::utils::{decode_path, encode_path};
+
+
+#[test]
+fn decoded_paths_encode_back_to_the_same_bytes() {
+    let names: [&[u8]; 5] = [
+        b"plain.txt",
+        "caf\u{e9}".as_bytes(),
+        b"caf\xe9",
+        // A valid character that lies in the range used for escaped bytes.
+        b"\xf4\x8f\xbe\x80",
+        b"\xf4\x8f\xbf\xbf-\xff\xf4\x8f",
+    ];
+    for name in names {
+        assert_eq!(encode_path(&decode_path(name)), name, "{:?}", name);
+    }
+    assert_ne!(decode_path(b"\xf4\x8f\xbe\x80"), decode_path(b"\x80"));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_file_names_round_trip_through_a_tree() {
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+
+    use common::TestRepo;
+
+    let repo = TestRepo::new();
+    let names: [&[u8]; 2] = [b"caf\xe9", b"\xf4\x8f\xbe\x80"];
+    for name in names {
+        fs::write(repo.dir.join(OsStr::from_bytes(name)), name).unwrap();
+    }
+    repo.git_rs(&["add", "."]);
+    let tree = repo.git_rs(&["write-tree"]).trim_end().to_string();
+
+    let raw = repo.run(&["cat-file", "tree", &tree]).stdout;
+    for name in names {
+        let mut entry = b"100644 ".to_vec();
+        entry.extend_from_slice(name);
+        entry.push(0);
+        assert!(raw.windows(entry.len()).any(|window| window == entry), "{:?} missing from the tree", name);
+    }
+
+    // Reading the tree back into a fresh index and writing it again must
+    // give the same tree, and checking it out the same files.
+    fs::remove_file(repo.path(".git/index")).unwrap();
+    repo.git_rs(&["read-tree", &tree]);
+    assert_eq!(repo.git_rs(&["write-tree"]).trim_end(), tree);
+
+    repo.git_rs(&["commit", "-m", "names"]);
+    for name in names {
+        fs::remove_file(repo.dir.join(OsStr::from_bytes(name))).unwrap();
+    }
+    repo.git_rs(&["checkout", "HEAD", "--", "."]);
+    for name in names {
+        assert_eq!(fs::read(repo.dir.join(OsStr::from_bytes(name))).unwrap(), name);
+    }
+}
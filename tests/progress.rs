@@ -0,0 +1,23 @@
+mod common;
+
+use common::{stderr, TestRepo};
+
+
+#[test]
+fn quiet_keeps_stderr_empty() {
+    let repo = TestRepo::new();
+    for n in 0..20 {
+        repo.write(&format!("dir/file{}", n), format!("{}\n", n));
+    }
+    repo.commit_all("initial");
+
+    let loud = repo.run(&["checkout", "-b", "loud"]);
+    assert!(loud.status.success());
+    assert_eq!(stderr(&loud), "Switched to a new branch 'loud'\n");
+
+    for args in [&["--quiet", "checkout", "-b", "quiet"][..], &["-q", "checkout", "master"], &["-q", "gc"]] {
+        let output = repo.run(args);
+        assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stderr(&output), "", "{:?}", args);
+    }
+}
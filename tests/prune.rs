@@ -0,0 +1,31 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn prune_removes_dangling_objects_and_keeps_reachable_ones() {
+    let repo = TestRepo::new();
+    repo.write("kept.txt", "reachable\n");
+    let head = repo.commit_all("initial");
+    let kept = repo.rev_parse("HEAD:kept.txt");
+    let dangling = repo.hash_object("blob", "nobody points at me\n");
+
+    assert_eq!(repo.git_rs(&["prune", "--dry-run", "--expire=now"]), format!("{} blob\n", dangling));
+    assert!(repo.open().object_exists(&dangling));
+
+    repo.git_rs(&["prune", "--expire=now"]);
+    let git = repo.open();
+    assert!(!git.object_exists(&dangling));
+    assert!(git.object_exists(&kept));
+    assert!(git.object_exists(&head));
+    assert!(git.object_exists(&repo.rev_parse("HEAD^{tree}")));
+}
+
+#[test]
+fn prune_keeps_recent_objects_by_default() {
+    let repo = TestRepo::new();
+    let dangling = repo.hash_object("blob", "too new to prune\n");
+    repo.git_rs(&["prune"]);
+    assert!(repo.open().object_exists(&dangling));
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn at_n_resolves_to_earlier_ref_values() {
+    let repo = TestRepo::new();
+    repo.write("file", "one\n");
+    let first = repo.commit_all("first");
+    repo.write("file", "two\n");
+    let second = repo.commit_all("second");
+
+    assert_eq!(repo.rev_parse("HEAD@{0}"), second);
+    assert_eq!(repo.rev_parse("HEAD@{1}"), first);
+    assert_eq!(repo.rev_parse("master@{1}"), first);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD@{1}:file"]), "one\n");
+    assert!(!repo.run(&["rev-parse", "HEAD@{2}"]).status.success());
+}
+
+#[test]
+fn expire_drops_entries_older_than_the_cutoff() {
+    let repo = TestRepo::new();
+    repo.write("file", "one\n");
+    repo.commit_all("first");
+    let commit = repo.git_rs(&["cat-file", "-p", "HEAD"]);
+    let committer = commit.lines().find(|line| line.starts_with("committer ")).unwrap();
+    let time: i64 = committer.rsplit(' ').nth(1).unwrap().parse().unwrap();
+    repo.write("file", "two\n");
+    let second = repo.commit_all("second");
+
+    repo.git_rs(&["reflog", "expire", &format!("--expire={}", time + 1), "--all"]);
+    assert_eq!(repo.rev_parse("HEAD@{0}"), second);
+    assert!(!repo.run(&["rev-parse", "HEAD@{1}"]).status.success());
+    assert_eq!(repo.git_rs(&["reflog"]).lines().count(), 1);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::TestRepo;
+use git_rs::object::read_commit;
+
+
+#[test]
+fn symbolic_ref_reads_head_after_init() {
+    let repo = TestRepo::new();
+    assert_eq!(repo.git_rs(&["symbolic-ref", "HEAD"]), "refs/heads/master\n");
+
+    repo.git_rs(&["symbolic-ref", "HEAD", "refs/heads/main"]);
+    assert_eq!(repo.git_rs(&["symbolic-ref", "HEAD"]), "refs/heads/main\n");
+}
+
+#[test]
+fn checkout_b_points_head_at_the_new_branch() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("initial");
+
+    repo.git_rs(&["checkout", "-b", "topic"]);
+    assert_eq!(repo.git_rs(&["symbolic-ref", "HEAD"]), "refs/heads/topic\n");
+    assert_eq!(repo.rev_parse("topic"), head);
+    assert_eq!(repo.read_string("file"), "content\n");
+
+    assert!(!repo.run(&["checkout", "-b", "topic"]).status.success());
+}
+
+#[test]
+fn annotated_tags_peel_to_their_commit_and_tree() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("initial");
+    repo.git_rs(&["tag", "-a", "-m", "release", "v1.0"]);
+
+    let tag = repo.rev_parse("v1.0");
+    assert_ne!(tag, head);
+    assert_eq!(repo.git_rs(&["cat-file", "-t", &tag]), "tag\n");
+    assert_eq!(repo.rev_parse("v1.0^{commit}"), head);
+    let tree = read_commit(&repo.open(), &head).unwrap().tree().unwrap().to_string();
+    assert_eq!(repo.rev_parse("v1.0^{tree}"), tree);
+    assert_eq!(repo.rev_parse("v1.0^{tag}"), tag);
+    assert_eq!(repo.rev_parse("@"), head);
+    assert!(!repo.run(&["rev-parse", "v1.0^{blob}"]).status.success());
+}
+
+#[test]
+fn for_each_ref_formats_short_names_and_types() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("first");
+    repo.git_rs(&["checkout", "-b", "feature"]);
+    repo.git_rs(&["tag", "-a", "v1.0", "-m", "release"]);
+    repo.git_rs(&["tag", "light"]);
+
+    assert_eq!(
+        repo.git_rs(&["for-each-ref", "--format=%(refname:short) %(objecttype)"]),
+        "feature commit\nmaster commit\nlight commit\nv1.0 tag\n"
+    );
+    assert_eq!(repo.git_rs(&["for-each-ref", "--format=%(refname) %(objectname)", "refs/heads"]), format!("refs/heads/feature {0}\nrefs/heads/master {0}\n", head));
+}
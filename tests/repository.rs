@@ -0,0 +1,145 @@
+mod common;
+
+use std::fs;
+
+use common::{stderr, success, TestRepo};
+use git_rs::error::GitError;
+use git_rs::repository::{HeadState, Repository};
+
+
+#[test]
+fn head_is_unborn_before_the_first_commit() {
+    let repo = TestRepo::new();
+    let head = repo.open().head().unwrap();
+    assert_eq!(head, HeadState::Unborn { name: "refs/heads/master".to_string() });
+    assert_eq!(head.branch(), Some("refs/heads/master"));
+    assert_eq!(repo.open().head_commit().unwrap(), None);
+}
+
+#[test]
+fn head_is_on_a_branch_after_committing() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let sha = repo.commit_all("first");
+    assert_eq!(repo.open().head().unwrap(), HeadState::Branch { name: "refs/heads/master".to_string(), sha: sha.clone() });
+    assert_eq!(repo.open().head_commit().unwrap(), Some(sha));
+}
+
+#[test]
+fn head_is_detached_after_checking_out_a_commit() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let first = repo.commit_all("first");
+    repo.write("file", "changed\n");
+    repo.commit_all("second");
+
+    repo.git_rs(&["checkout", &first]);
+    let head = repo.open().head().unwrap();
+    assert_eq!(head, HeadState::Detached(first.clone()));
+    assert_eq!(head.sha(), Some(first.as_str()));
+    assert_eq!(head.branch(), None);
+    assert!(repo.git_rs(&["status"]).starts_with(&format!("HEAD detached at {}\n", &first[..7])));
+}
+
+#[test]
+fn init_twice_keeps_the_existing_config() {
+    let repo = TestRepo::new();
+    repo.append_config("[custom]\n\tkey = value\n");
+
+    let output = repo.git_rs(&["init"]);
+    assert!(output.starts_with("Reinitialized existing Git repository in "), "{}", output);
+    assert!(repo.read_string(".git/config").contains("[custom]\n\tkey = value\n"));
+}
+
+#[test]
+fn init_fails_when_a_file_is_in_the_way() {
+    let repo = TestRepo::empty();
+    repo.write(".git/refs", "not a directory\n");
+
+    let output = repo.run(&["init"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Failed to create directory"), "{}", stderr(&output));
+}
+
+// Bumps the repository to format version 1 and declares `extensions`.
+fn set_format_1(repo: &TestRepo, extensions: &str) {
+    let config = repo.read_string(".git/config").replace("repositoryformatversion=0", "repositoryformatversion=1");
+    assert!(config.contains("repositoryformatversion=1"));
+    repo.write(".git/config", format!("{}[extensions]\n{}", config, extensions));
+}
+
+#[test]
+fn version_1_with_known_extensions_opens() {
+    let repo = TestRepo::new();
+    set_format_1(&repo, "\tobjectformat = sha1\n\tnoop = true\n");
+
+    assert!(Repository::find(&repo.dir).is_ok());
+    repo.write("file", "content\n");
+    repo.commit_all("first");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:file"]), "content\n");
+}
+
+#[test]
+fn version_1_with_an_unknown_extension_is_a_clean_error() {
+    let repo = TestRepo::new();
+    set_format_1(&repo, "\tfrobnicate = true\n");
+
+    match Repository::find(&repo.dir) {
+        Err(GitError::UnsupportedFormat(message)) => assert_eq!(message, "unknown repository extension found:\n\tfrobnicate"),
+        other => panic!("expected an unsupported format error, got {:?}", other.err()),
+    }
+    let output = repo.run(&["status"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("unknown repository extension found"));
+    assert!(!stderr(&output).contains("panicked"));
+}
+
+#[test]
+fn objects_resolve_through_a_gitdir_file() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("first");
+
+    // Move the git dir out of the worktree and leave a `.git` file behind.
+    fs::rename(repo.path(".git"), repo.root.join("external.git")).unwrap();
+    repo.write(".git", "gitdir: ../external.git\n");
+
+    assert_eq!(repo.open().gitdir, repo.dir.join("../external.git"));
+    assert_eq!(repo.rev_parse("HEAD"), head);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", "HEAD:file"]), "content\n");
+    repo.write("file", "changed\n");
+    repo.commit_all("second");
+    assert_eq!(repo.rev_parse("HEAD@{1}"), head);
+    assert!(repo.root.join("external.git/logs/HEAD").is_file());
+    assert!(!repo.path(".git").is_dir());
+}
+
+#[test]
+fn a_linked_worktree_keeps_its_own_head_log() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("first");
+    let main_log = repo.read_string(".git/logs/HEAD");
+
+    // Lay out a linked worktree the way `git worktree add --detach` does.
+    let admin = repo.path(".git/worktrees/wt");
+    let worktree = repo.root.join("wt");
+    fs::create_dir_all(&admin).unwrap();
+    fs::create_dir_all(&worktree).unwrap();
+    fs::write(admin.join("commondir"), "../..\n").unwrap();
+    fs::write(admin.join("HEAD"), format!("{}\n", head)).unwrap();
+    fs::write(admin.join("gitdir"), format!("{}\n", worktree.join(".git").display())).unwrap();
+    fs::write(worktree.join(".git"), format!("gitdir: {}\n", admin.display())).unwrap();
+
+    let in_worktree = |args: &[&str]| success(repo.command_in(&worktree, args).output().unwrap(), args);
+    in_worktree(&["read-tree", &head]);
+    fs::write(worktree.join("file"), "from the worktree\n").unwrap();
+    in_worktree(&["add", "file"]);
+    in_worktree(&["commit", "-m", "in wt"]);
+    let commit = in_worktree(&["rev-parse", "HEAD"]);
+
+    assert_eq!(repo.read_string(".git/logs/HEAD"), main_log);
+    assert_eq!(fs::read_to_string(admin.join("logs/HEAD")).unwrap().lines().count(), 1);
+    let reflog = in_worktree(&["reflog"]);
+    assert_eq!(reflog, format!("{} HEAD@{{0}}: commit: in wt\n", &commit[..7]));
+}
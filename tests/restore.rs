@@ -0,0 +1,37 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn restore_rewrites_a_modified_file_from_the_index() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "committed\n");
+    repo.write("other.txt", "other\n");
+    repo.commit_all("initial");
+    repo.write("file.txt", "staged\n");
+    repo.git_rs(&["add", "file.txt"]);
+    repo.write("file.txt", "scribbled over\n");
+    repo.write("other.txt", "also changed\n");
+
+    repo.git_rs(&["restore", "file.txt"]);
+    assert_eq!(repo.read_string("file.txt"), "staged\n");
+    assert_eq!(repo.read_string("other.txt"), "also changed\n");
+}
+
+#[test]
+fn restore_source_takes_content_from_a_commit() {
+    let repo = TestRepo::new();
+    repo.write("file.txt", "version one\n");
+    let first = repo.commit_all("first");
+    repo.write("file.txt", "version two\n");
+    repo.commit_all("second");
+
+    repo.git_rs(&["restore", &format!("--source={}", first), "file.txt"]);
+    assert_eq!(repo.read_string("file.txt"), "version one\n");
+    // Without --staged only the worktree changes.
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), " M file.txt\n");
+
+    repo.git_rs(&["restore", "--source", &first, "--staged", "file.txt"]);
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "M  file.txt\n");
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{stderr, success, TestRepo};
+
+
+#[test]
+fn show_toplevel_prints_the_worktree_root_from_a_subdirectory() {
+    let repo = TestRepo::new();
+    repo.write("src/nested/file", "content\n");
+    let nested = repo.path("src/nested");
+
+    let args = ["rev-parse", "--show-toplevel", "--is-inside-work-tree", "--git-dir"];
+    let output = success(repo.command_in(&nested, &args).output().unwrap(), &args);
+    assert_eq!(output, format!("{0}\ntrue\n{0}/.git\n", repo.dir.display()));
+    assert_eq!(repo.git_rs(&["rev-parse", "--show-toplevel"]), format!("{}\n", repo.dir.display()));
+    assert_eq!(repo.git_rs(&["rev-parse", "--git-dir"]), ".git\n");
+}
+
+#[test]
+fn verify_fails_on_a_bad_revision() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("initial");
+    assert_eq!(repo.git_rs(&["rev-parse", "--verify", "HEAD"]), format!("{}\n", head));
+
+    let output = repo.run(&["rev-parse", "--verify", "no-such-branch"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(stderr(&output), "Error: Needed a single revision\n");
+
+    let quiet = repo.run(&["rev-parse", "--verify", "-q", "no-such-branch"]);
+    assert_eq!(quiet.status.code(), Some(1));
+    assert!(quiet.stdout.is_empty() && quiet.stderr.is_empty());
+    assert!(!repo.run(&["rev-parse", "--verify", "HEAD", "HEAD"]).status.success());
+}
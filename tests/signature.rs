@@ -0,0 +1,93 @@
+mod common;
+
+use common::TestRepo;
+use git_rs::object::read_commit;
+
+
+const SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----\n\niQEzBAABCAAdFiEEexample0signature0bytes0only0for0tests0AAoJEA\n=Zx9k\n-----END PGP SIGNATURE-----\n";
+
+// The commit as git hands it to gpg: everything but the gpgsig header.
+fn unsigned_commit(tree: &str) -> String {
+    format!("tree {}\nauthor A U Thor <author@example.com> 1700000000 +0200\ncommitter C O Mitter <committer@example.com> 1700000100 +0200\n\nSigned commit\n\nWith a body.\n", tree)
+}
+
+// The same commit with the signature folded into a gpgsig header after the
+// committer line, continuation lines indented by one space.
+fn signed_commit(tree: &str) -> String {
+    let folded = SIGNATURE.trim_end().replace('\n', "\n ");
+    unsigned_commit(tree).replacen("\n\nSigned commit", &format!("\ngpgsig {}\n\nSigned commit", folded), 1)
+}
+
+#[test]
+fn signed_payload_is_the_commit_without_its_signature() {
+    let repo = TestRepo::new();
+    let tree = repo.git_rs_with_stdin(&["mktree"], "").trim_end().to_string();
+    let sha = repo.hash_object("commit", signed_commit(&tree));
+
+    let commit = read_commit(&repo.open(), &sha).unwrap();
+    let (payload, signature) = commit.signature().unwrap();
+    assert_eq!(String::from_utf8(payload).unwrap(), unsigned_commit(&tree));
+    assert_eq!(signature, SIGNATURE.trim_end());
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_commit_hands_the_payload_to_the_verifier() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::new();
+    let tree = repo.git_rs_with_stdin(&["mktree"], "").trim_end().to_string();
+    let sha = repo.hash_object("commit", signed_commit(&tree));
+
+    // Called as `<program> --status-fd=1 --verify <signature-file> -`.
+    let verifier = repo.root.join("verify.sh");
+    let saved = repo.root.join("saved");
+    std::fs::write(&verifier, format!("#!/bin/sh\ncat > '{0}.payload'\ncp \"$3\" '{0}.signature'\necho '[GNUPG:] GOODSIG 0123456789ABCDEF Stub'\n", saved.display())).unwrap();
+    std::fs::set_permissions(&verifier, std::fs::Permissions::from_mode(0o755)).unwrap();
+    repo.append_config(&format!("[gpg]\n\tprogram = {}\n", verifier.display()));
+
+    repo.git_rs(&["verify-commit", &sha]);
+    assert_eq!(std::fs::read_to_string(saved.with_extension("payload")).unwrap(), unsigned_commit(&tree));
+    assert_eq!(std::fs::read_to_string(saved.with_extension("signature")).unwrap().trim_end(), SIGNATURE.trim_end());
+}
+
+#[cfg(unix)]
+#[test]
+fn commit_gpg_sign_embeds_a_folded_signature() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::new();
+    // Called as `<program> --status-fd=2 -bsau <key>` with the payload on stdin.
+    let signer = repo.root.join("sign.sh");
+    let saved = repo.root.join("signed.payload");
+    std::fs::write(&signer, format!("#!/bin/sh\ncat > '{}'\necho '[GNUPG:] SIG_CREATED D 1 8 00 1700000000 0123456789ABCDEF' >&2\nprintf '%s' '{}'\n", saved.display(), SIGNATURE)).unwrap();
+    std::fs::set_permissions(&signer, std::fs::Permissions::from_mode(0o755)).unwrap();
+    repo.append_config(&format!("[gpg]\n\tprogram = {}\n", signer.display()));
+
+    repo.write("file", "signed\n");
+    repo.git_rs(&["add", "file"]);
+    repo.git_rs(&["commit", "-S", "-m", "Signed commit"]);
+    let sha = repo.rev_parse("HEAD");
+
+    let raw = repo.git_rs(&["cat-file", "commit", &sha]);
+    let folded = format!("gpgsig {}\n", SIGNATURE.trim_end().replace('\n', "\n "));
+    assert!(raw.contains(&format!(" +0000\n{}\nSigned commit\n", folded)), "{}", raw);
+    // The stored bytes hash to the commit's SHA and parse back cleanly.
+    assert_eq!(repo.hash_object("commit", &raw), sha);
+    let commit = read_commit(&repo.open(), &sha).unwrap();
+    let (payload, signature) = commit.signature().unwrap();
+    assert_eq!(payload, std::fs::read(&saved).unwrap());
+    assert_eq!(signature, SIGNATURE.trim_end());
+    assert_eq!(commit.kvlm.message, "Signed commit\n");
+
+    // --no-gpg-sign wins over commit.gpgsign.
+    repo.append_config("[commit]\n\tgpgsign = true\n");
+    repo.write("file", "unsigned\n");
+    repo.git_rs(&["add", "file"]);
+    repo.git_rs(&["commit", "--no-gpg-sign", "-m", "Unsigned commit"]);
+    assert!(read_commit(&repo.open(), &repo.rev_parse("HEAD")).unwrap().signature().is_none());
+    repo.write("file", "signed by config\n");
+    repo.git_rs(&["add", "file"]);
+    repo.git_rs(&["commit", "-m", "Signed by config"]);
+    assert!(read_commit(&repo.open(), &repo.rev_parse("HEAD")).unwrap().signature().is_some());
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn push_then_pop_restores_the_changes() {
+    let repo = TestRepo::new();
+    repo.write("tracked", "one\n");
+    repo.write("staged", "one\n");
+    let head = repo.commit_all("initial");
+
+    repo.write("tracked", "two\n");
+    repo.write("staged", "two\n");
+    repo.git_rs(&["add", "staged"]);
+    assert!(repo.git_rs(&["stash"]).starts_with("Saved working directory and index state WIP on master: "));
+
+    assert_eq!(repo.read_string("tracked"), "one\n");
+    assert_eq!(repo.read_string("staged"), "one\n");
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "");
+    assert_eq!(repo.rev_parse("HEAD"), head);
+    let stash = repo.rev_parse("refs/stash");
+    let commit = repo.git_rs(&["cat-file", "-p", &stash]);
+    let parents: Vec<&str> = commit.lines().filter_map(|line| line.strip_prefix("parent ")).collect();
+    assert_eq!(parents.len(), 2);
+    assert_eq!(parents[0], head);
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &format!("{}:staged", parents[1])]), "two\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &format!("{}:tracked", parents[1])]), "one\n");
+    assert_eq!(repo.git_rs(&["cat-file", "-p", &format!("{}:tracked", stash)]), "two\n");
+    assert_eq!(repo.git_rs(&["stash", "list"]).lines().count(), 1);
+
+    repo.git_rs(&["stash", "pop"]);
+    assert_eq!(repo.read_string("tracked"), "two\n");
+    assert_eq!(repo.read_string("staged"), "two\n");
+    assert_eq!(repo.rev_parse("HEAD"), head);
+    assert_eq!(repo.git_rs(&["stash", "list"]), "");
+    assert!(!repo.run(&["rev-parse", "--verify", "refs/stash"]).status.success());
+}
+
+#[test]
+fn pop_without_a_stash_fails() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    repo.commit_all("initial");
+    assert!(!repo.run(&["stash", "pop"]).status.success());
+    assert_eq!(repo.git_rs(&["stash"]), "No local changes to save\n");
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn porcelain_reports_staged_unstaged_and_untracked_paths() {
+    let repo = TestRepo::new();
+    repo.write("modified", "committed\n");
+    repo.write("both", "committed\n");
+    repo.commit_all("initial");
+
+    repo.write("added", "new\n");
+    repo.git_rs(&["add", "added"]);
+    repo.write("modified", "changed\n");
+    repo.write("both", "staged\n");
+    repo.git_rs(&["add", "both"]);
+    repo.write("both", "changed again\n");
+    repo.write("untracked", "unknown\n");
+
+    assert_eq!(repo.git_rs(&["status", "--porcelain"]), "A  added\nMM both\n M modified\n?? untracked\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn porcelain_quotes_unusual_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let repo = TestRepo::new();
+    std::fs::write(repo.dir.join(OsStr::from_bytes(b"caf\xe9")), "latin-1\n").unwrap();
+    repo.write("caf\u{e9}.txt", "utf-8\n");
+    repo.write("tab\there", "tab\n");
+    repo.write("say \"hi\"", "quotes\n");
+    repo.write("plain name", "spaces\n");
+    repo.git_rs(&["add", "say \"hi\""]);
+
+    assert_eq!(
+        repo.git_rs(&["status", "--porcelain"]),
+        "A  \"say \\\"hi\\\"\"\n?? \"caf\\303\\251.txt\"\n?? \"caf\\351\"\n?? \"plain name\"\n?? \"tab\\there\"\n"
+    );
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::TestRepo;
+
+
+#[test]
+fn tag_d_removes_loose_and_packed_tags() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    let head = repo.commit_all("initial");
+    repo.git_rs(&["tag", "v1.0"]);
+    std::fs::write(repo.path(".git/packed-refs"), format!("# pack-refs with: peeled fully-peeled sorted \n{} refs/tags/packed\n", head)).unwrap();
+    assert_eq!(repo.git_rs(&["show-ref", "--tags"]), format!("{0} refs/tags/packed\n{0} refs/tags/v1.0\n", head));
+
+    assert_eq!(repo.git_rs(&["tag", "-d", "v1.0"]), format!("Deleted tag 'v1.0' (was {})\n", &head[..7]));
+    assert_eq!(repo.git_rs(&["show-ref", "--tags"]), format!("{} refs/tags/packed\n", head));
+    repo.git_rs(&["tag", "-d", "packed"]);
+    assert!(!repo.run(&["show-ref", "--tags"]).status.success());
+    assert!(!repo.run(&["tag", "-d", "v1.0"]).status.success());
+}
+
+#[test]
+fn tag_list_filters_by_pattern_and_sorts() {
+    let repo = TestRepo::new();
+    repo.write("file", "content\n");
+    repo.commit_all("initial");
+    // Created newest name first, so date order differs from name order.
+    repo.git_rs(&["tag", "-a", "v2.0", "-m", "Second release\n\nWith notes."]);
+    repo.git_rs(&["tag", "-a", "v1.10", "-m", "Patch release"]);
+    repo.git_rs(&["tag", "-a", "v1.2", "-m", "First release"]);
+    repo.git_rs(&["tag", "other"]);
+
+    assert_eq!(repo.git_rs(&["tag", "-l", "v1.*", "--sort=refname"]), "v1.10\nv1.2\n");
+    assert_eq!(repo.git_rs(&["tag", "-l", "v*", "--sort=-refname"]), "v2.0\nv1.2\nv1.10\n");
+    assert_eq!(repo.git_rs(&["tag", "--list", "v*", "--sort=creatordate"]), "v2.0\nv1.10\nv1.2\n");
+    assert_eq!(repo.git_rs(&["tag"]), "other\nv1.10\nv1.2\nv2.0\n");
+    assert_eq!(repo.git_rs(&["tag", "-l", "v2*", "-n1"]), "v2.0            Second release\n");
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::{stderr, TestRepo};
+use git_rs::error::GitError;
+use git_rs::object::{FileMode, GitObject, GitTree, GitTreeLeaf};
+
+
+#[test]
+fn mktree_rebuilds_the_tree_ls_tree_lists() {
+    let repo = TestRepo::new();
+    repo.write("README", "hello\n");
+    repo.write("bin/run.sh", "#!/bin/sh\n");
+    repo.write("src/lib.rs", "pub fn f() {}\n");
+    repo.commit_all("initial");
+
+    let tree = repo.rev_parse("HEAD^{tree}");
+    let listing = repo.git_rs(&["ls-tree", &tree]);
+    assert_eq!(listing.lines().count(), 3);
+    assert_eq!(repo.git_rs_with_stdin(&["mktree"], &listing).trim_end(), tree);
+}
+
+#[test]
+fn mktree_checks_every_line_before_looking_up_objects() {
+    let repo = TestRepo::new();
+    let missing = "1".repeat(40);
+    let input = format!("100644 blob {}\tmissing.txt\n100644 blob {}\tshort.txt\n", missing, &missing[..39]);
+    let output = repo.run_with_stdin(&["mktree"], input);
+    assert!(!output.status.success());
+    assert_eq!(stderr(&output).trim_end(), format!("Error: input format error: 100644 blob {}\tshort.txt", &missing[..39]));
+
+    let output = repo.run_with_stdin(&["mktree"], format!("100644 blob {}\tmissing.txt\n", missing));
+    assert!(stderr(&output).contains(&format!("entry 'missing.txt' object {} is unavailable", missing)), "{}", stderr(&output));
+}
+
+#[test]
+fn trees_with_invalid_object_names_are_not_serialized() {
+    let tree = GitTree::from_leaves(vec![GitTreeLeaf { mode: FileMode::Regular, path: "file".to_string(), sha: "abc".to_string() }]);
+    match tree.serialize() {
+        Err(GitError::Malformed(message)) => assert_eq!(message, "entry 'file' has invalid object name 'abc'"),
+        other => panic!("expected a malformed tree, got {:?}", other),
+    }
+    assert!(tree.hash().is_err());
+}
+
+#[test]
+fn read_tree_then_write_tree_gives_back_the_same_tree() {
+    let repo = TestRepo::new();
+    repo.write("README", "hello\n");
+    repo.write("src/deep/nested/file.rs", "// nested\n");
+    repo.commit_all("first");
+    let tree = repo.rev_parse("HEAD^{tree}");
+
+    repo.write("extra.txt", "not in the tree\n");
+    repo.git_rs(&["add", "extra.txt"]);
+    repo.git_rs(&["read-tree", &tree]);
+    assert_eq!(repo.git_rs(&["ls-files"]), "README\nsrc/deep/nested/file.rs\n");
+    assert_eq!(repo.git_rs(&["write-tree"]).trim_end(), tree);
+    assert!(repo.path("extra.txt").exists());
+}
+
+#[test]
+fn ls_tree_long_shows_blob_sizes() {
+    let repo = TestRepo::new();
+    let contents = "x".repeat(1234);
+    repo.write("big.txt", &contents);
+    repo.write("dir/small.txt", "hi\n");
+    repo.commit_all("initial");
+
+    let blob = repo.rev_parse("HEAD:big.txt");
+    let tree = repo.rev_parse("HEAD:dir");
+    assert_eq!(
+        repo.git_rs(&["ls-tree", "-l", "HEAD"]),
+        format!("100644 blob {}    1234\tbig.txt\n040000 tree {}       -\tdir\n", blob, tree)
+    );
+    assert!(repo.git_rs(&["ls-tree", "--long", "-r", "HEAD"]).contains("       3\tdir/small.txt\n"));
+}
+
+#[test]
+fn file_modes_round_trip_through_their_octal_form() {
+    let modes = [
+        (FileMode::Regular, "100644"),
+        (FileMode::Executable, "100755"),
+        (FileMode::Symlink, "120000"),
+        (FileMode::Tree, "40000"),
+        (FileMode::Gitlink, "160000"),
+    ];
+    for (mode, octal) in modes {
+        assert_eq!(mode.to_string(), octal);
+        assert_eq!(octal.parse::<FileMode>().unwrap(), mode);
+        assert_eq!(mode.to_string().parse::<FileMode>().unwrap(), mode);
+        assert_eq!(FileMode::from_bits(mode.bits()), Some(mode));
+    }
+
+    // ls-tree pads to six digits, and older trees may use group bits.
+    assert_eq!(format!("{:0>6}", FileMode::Tree), "040000");
+    assert_eq!("040000".parse::<FileMode>().unwrap(), FileMode::Tree);
+    assert_eq!("100664".parse::<FileMode>().unwrap(), FileMode::Regular);
+    assert!("100000x".parse::<FileMode>().is_err());
+    assert!("070000".parse::<FileMode>().is_err());
+}